@@ -25,6 +25,7 @@ mod ws;
 criterion_main!(
     ws::encstr::benches,
     ws::hl::benches,
+    misc::epoch::benches,
     misc::fncall::benches,
     misc::mem::benches,
     misc::wildcard::benches,
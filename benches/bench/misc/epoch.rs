@@ -0,0 +1,58 @@
+// std imports
+use std::time::Duration;
+
+// third-party imports
+use chrono::FixedOffset;
+use const_str::concat as strcat;
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group};
+
+// local imports
+use super::{BencherExt, ND};
+use hl::{
+    datefmt::{DateTimeFormatter, LinuxDateFormat},
+    timestamp::Timestamp,
+    timezone::Tz,
+};
+
+criterion_group!(benches, bench);
+
+const GROUP: &str = strcat!(super::GROUP, ND, "epoch");
+
+fn bench(c: &mut Criterion) {
+    let mut c = c.benchmark_group(GROUP);
+    c.warm_up_time(Duration::from_secs(1));
+    c.measurement_time(Duration::from_secs(3));
+
+    let inputs = [
+        ("s", "1597994448"),
+        ("ms", "1597994448123"),
+        ("ns", "1597994448123456789"),
+    ];
+    let formatter = DateTimeFormatter::new(
+        LinuxDateFormat::new("%y-%m-%d %T.%3N").compile(),
+        Tz::FixedOffset(FixedOffset::east_opt(0).unwrap()),
+    );
+
+    for (name, input) in inputs {
+        c.bench_function(BenchmarkId::new("generic", name), |b| {
+            let setup = || (Timestamp::new(input), Vec::<u8>::with_capacity(128));
+            let routine = |(ts, mut buf): (Timestamp, Vec<u8>)| {
+                formatter.format(&mut buf, ts.parse().unwrap());
+                buf.len()
+            };
+            b.iter_batched_fixed(setup, routine, BatchSize::SmallInput);
+        });
+
+        c.bench_function(BenchmarkId::new("integer", name), |b| {
+            let setup = || (Timestamp::new(input), Vec::<u8>::with_capacity(128));
+            let routine = |(ts, mut buf): (Timestamp, Vec<u8>)| {
+                let (secs, nsecs) = ts.unix_integer().unwrap();
+                formatter.format_unix(&mut buf, secs, nsecs).unwrap();
+                buf.len()
+            };
+            b.iter_batched_fixed(setup, routine, BatchSize::SmallInput);
+        });
+    }
+
+    c.finish();
+}
@@ -3,6 +3,7 @@ use super::{BencherExt, ND, hash};
 
 const GROUP: &str = "misc";
 
+pub mod epoch;
 pub mod fncall;
 pub mod mem;
 pub mod wildcard;
@@ -73,6 +73,7 @@ pub struct Options {
     pub raw: bool,
     pub raw_fields: bool,
     pub allow_prefix: bool,
+    pub relaxed_json: bool,
     pub buffer_size: NonZeroUsize,
     pub max_message_size: NonZeroUsize,
     pub concurrency: usize,
@@ -377,6 +378,7 @@ impl App {
             fields: &self.options.fields.settings.predefined,
             delimiter: self.options.delimiter.clone(),
             allow_prefix: self.options.allow_prefix,
+            relaxed_json: self.options.relaxed_json,
             unix_ts_unit: self.options.unix_ts_unit,
             format: self.options.input_format,
             ..IndexerSettings::with_fs(LocalFileSystem)
@@ -970,6 +972,7 @@ impl App {
     fn new_segment_processor<'a>(&'a self, parser: &'a Parser) -> impl SegmentProcess + 'a {
         let options = SegmentProcessorOptions {
             allow_prefix: self.options.allow_prefix,
+            relaxed_json: self.options.relaxed_json,
             allow_unparsed_data: self.options.filter.is_empty() && self.options.input_format.is_none(),
             delimiter: self.options.delimiter.clone(),
             input_format: self.options.input_format,
@@ -1022,7 +1025,8 @@ impl App {
     /// Uses exact matching (no normalization) to match field names precisely.
     fn build_predefined_filter(options: &Options) -> Arc<ExactIncludeExcludeKeyFilter> {
         let mut filter = ExactIncludeExcludeKeyFilter::new(MatchOptions::<NoNormalizing>::default());
-        for name in options.fields.settings.predefined.nested_field_names() {
+        let predefined = options.fields.settings.predefined.with_preset_applied();
+        for name in predefined.nested_field_names() {
             filter.entry(name).exclude();
         }
         Arc::new(filter)
@@ -1047,6 +1051,7 @@ pub trait SegmentProcess {
 #[derive(Default)]
 pub struct SegmentProcessorOptions {
     pub allow_prefix: bool,
+    pub relaxed_json: bool,
     pub allow_unparsed_data: bool,
     pub delimiter: Delimiter,
     pub input_format: Option<InputFormat>,
@@ -1101,14 +1106,20 @@ impl<'a, Formatter: RecordWithSourceFormatter, Filter: RecordFilter> SegmentProc
                 continue;
             }
 
-            let mut stream = RawRecord::parser()
+            let parser = RawRecord::parser()
                 .allow_prefix(self.options.allow_prefix)
-                .format(self.options.input_format)
-                .parse(chunk);
+                .relaxed_json(self.options.relaxed_json)
+                .format(self.options.input_format);
+            let relaxed = parser.relax(chunk);
+            let mut stream = parser.parse(relaxed.as_deref().unwrap_or(chunk));
             let mut parsed_some = false;
             let mut produced_some = false;
             let mut last_offset = 0;
-            while let Some(Ok(ar)) = stream.next() {
+            while let Some(Ok(mut ar)) = stream.next() {
+                if relaxed.is_some() {
+                    // the relaxed chunk holds a single record, so it covers the rest of the original chunk
+                    ar.offsets.end = chunk.len();
+                }
                 i += 1;
                 last_offset = ar.offsets.end;
                 if parsed_some {
@@ -547,6 +547,7 @@ fn options() -> Options {
         raw: false,
         raw_fields: false,
         allow_prefix: false,
+        relaxed_json: false,
         buffer_size: NonZeroUsize::new(4096).unwrap(),
         max_message_size: NonZeroUsize::new(4096 * 1024).unwrap(),
         concurrency: 1,
@@ -555,6 +556,7 @@ fn options() -> Options {
         formatting: Formatting {
             message: MessageFormatting {
                 format: MessageFormat::AutoQuoted,
+                template: None,
             },
             ..Formatting::default()
         },
@@ -2425,3 +2427,48 @@ fn test_merge_segments_trailing_gap_with_source_history() {
     // So: trailing-gap(ts=100) sorts before line-B(ts=200).
     assert_eq!(result, "SSline-A\nSStrailing-gap\nSSline-B\n");
 }
+
+#[test]
+fn test_relaxed_json() {
+    let data = concat!("{msg: 'hello', level: 'info', a: 1,}\n", "{'msg': broken\n");
+
+    let mut output = Vec::new();
+    let app = App::new(options());
+    app.run(vec![input(data)], &mut output).unwrap();
+    assert_eq!(std::str::from_utf8(&output).unwrap(), data);
+
+    let mut output = Vec::new();
+    let mut opts = options();
+    opts.relaxed_json = true;
+    let app = App::new(opts);
+    app.run(vec![input(data)], &mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "|INF| hello a=1\n{'msg': broken\n"
+    );
+
+    let mut output = Vec::new();
+    let mut opts = options().with_raw(true);
+    opts.relaxed_json = true;
+    let app = App::new(opts);
+    app.run(vec![input(data)], &mut output).unwrap();
+    assert_eq!(std::str::from_utf8(&output).unwrap(), data);
+}
+
+#[test]
+fn test_json_array_of_records() {
+    let data = concat!(
+        r#"[{"level":"info","msg":"first"},{"level":"warning","msg":"second"},{"level":"error","msg":"third"}]"#,
+        "\n",
+        r#"{"msg":"fourth"}"#,
+        "\n",
+    );
+
+    let mut output = Vec::new();
+    let app = App::new(options());
+    app.run(vec![input(data)], &mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "|INF| first\n|WRN| second\n|ERR| third\nfourth\n"
+    );
+}
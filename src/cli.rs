@@ -428,6 +428,12 @@ pub struct Opt {
     #[arg(long, env = "HL_ALLOW_PREFIX", overrides_with = "allow_prefix", help_heading = heading::INPUT)]
     pub allow_prefix: bool,
 
+    /// Accept relaxed JSON with single quotes, unquoted keys, trailing commas and comments
+    ///
+    /// Entries are parsed as relaxed JSON only if they fail to parse as strict JSON.
+    #[arg(long, env = "HL_RELAXED_JSON", overrides_with = "relaxed_json", help_heading = heading::INPUT)]
+    pub relaxed_json: bool,
+
     /// Log entry delimiter
     ///
     /// By default, the delimiter is considered to be LF or CRLF followed by an empty line or a line that
@@ -1,8 +1,11 @@
 // std imports
-use std::cmp::{PartialOrd, min};
+use std::{
+    cmp::{PartialOrd, min},
+    sync::Arc,
+};
 
 // third-party imports
-use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, Offset, TimeZone, Timelike};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike};
 use chrono_tz::OffsetName;
 use enumset::{EnumSet, EnumSetType, enum_set as mask};
 
@@ -16,6 +19,7 @@ use crate::timezone::Tz;
 
 // ---
 
+#[derive(Clone, Copy, Debug)]
 pub struct TextWidth {
     pub bytes: usize,
     pub chars: usize,
@@ -43,11 +47,50 @@ impl DateTimeFormatter {
         format_date(buf, dt.with_timezone(&self.tz), &self.format)
     }
 
+    /// Formats a Unix timestamp given as whole seconds and nanoseconds since the epoch.
+    ///
+    /// Writes directly into `buf` without converting the timestamp to a fixed offset date-time first.
+    /// Returns `None` if the timestamp is out of the supported range.
+    #[inline]
+    pub fn format_unix<B>(&self, buf: &mut B, secs: i64, nsecs: u32) -> Option<()>
+    where
+        B: Push<u8>,
+    {
+        let dt = DateTime::from_timestamp(secs, nsecs)?;
+        format_date(buf, dt.with_timezone(&self.tz), &self.format);
+        Some(())
+    }
+
+    /// Returns a formatter producing only the time part of the format, starting with its first time item,
+    /// or `None` if the format does not have a date part preceding the time part.
+    pub fn time_only(&self) -> Option<Self> {
+        let pos = self.format.iter().position(|item| {
+            matches!(
+                item,
+                Item::Hour(_) | Item::Hour12(_) | Item::Minute(_) | Item::Second(_) | Item::Nanosecond(_)
+            )
+        })?;
+        (pos != 0).then(|| Self {
+            format: self.format[pos..].to_vec(),
+            tz: self.tz,
+        })
+    }
+
     #[inline]
     pub fn tz(&self) -> &Tz {
         &self.tz
     }
 
+    /// Appends the time zone offset or abbreviation to the format, separated by a space.
+    pub fn with_zone_suffix(mut self, suffix: ZoneSuffix) -> Self {
+        self.format.push(Item::Char(b' '));
+        self.format.push(match suffix {
+            ZoneSuffix::Offset => Item::TimeZoneOffset((Flags::empty(), 2)),
+            ZoneSuffix::Abbreviation => Item::TimeZoneName((Flags::empty(), 0)),
+        });
+        self
+    }
+
     #[inline]
     pub fn reformat_rfc3339<'a, B>(&self, buf: &mut B, ts: rfc3339::Timestamp<'a>) -> Option<()>
     where
@@ -61,26 +104,41 @@ impl DateTimeFormatter {
         }
     }
 
+    /// Returns the maximum length in bytes of a formatted timestamp, checking the first day of each month
+    /// to account for the longest month name.
     #[inline]
     pub fn max_length(&self) -> usize {
-        let mut counter = Counter::new();
-        let ts = DateTime::from_timestamp(1654041600, 999_999_999).unwrap().naive_utc();
-        let ts = DateTime::from_naive_utc_and_offset(ts, self.tz.offset_from_utc_date(&ts.date()).fix());
-        self.format(&mut counter, ts);
-        counter.result()
+        self.samples()
+            .map(|ts| {
+                let mut counter = Counter::new();
+                self.format(&mut counter, ts);
+                counter.result()
+            })
+            .max()
+            .unwrap_or_default()
     }
 
     pub fn max_width(&self) -> TextWidth {
         let mut buf = Vec::new();
-        let ts = DateTime::from_timestamp(1654041600, 999_999_999).unwrap().naive_utc();
-        let ts = DateTime::from_naive_utc_and_offset(ts, self.tz.offset_from_utc_date(&ts.date()).fix());
+        let mut result = TextWidth { bytes: 0, chars: 0 };
 
-        self.format(&mut buf, ts);
-
-        TextWidth {
-            bytes: buf.len(),
-            chars: std::str::from_utf8(&buf).unwrap().chars().count(),
+        for ts in self.samples() {
+            buf.clear();
+            self.format(&mut buf, ts);
+            result.bytes = result.bytes.max(buf.len());
+            result.chars = result.chars.max(std::str::from_utf8(&buf).unwrap().chars().count());
         }
+
+        result
+    }
+
+    fn samples(&self) -> impl Iterator<Item = DateTime<FixedOffset>> + '_ {
+        (1..=12).map(|month| {
+            let ts = NaiveDate::from_ymd_opt(2022, month, 1)
+                .and_then(|date| date.and_hms_nano_opt(0, 0, 0, 999_999_999))
+                .unwrap();
+            DateTime::from_naive_utc_and_offset(ts, self.tz.offset_from_utc_date(&ts.date()).fix())
+        })
     }
 }
 
@@ -95,6 +153,17 @@ impl Default for DateTimeFormatter {
 
 // ---
 
+/// Time zone suffix appended to formatted timestamps.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ZoneSuffix {
+    /// Offset from UTC, e.g. `+02:00`.
+    Offset,
+    /// Time zone abbreviation, e.g. `UTC` or `CEST`.
+    Abbreviation,
+}
+
+// ---
+
 #[derive(EnumSetType, Debug)]
 pub enum Flag {
     SpacePadding,
@@ -129,6 +198,8 @@ pub enum Item {
     MonthNumeric(Flags),
     MonthShort(Flags),
     MonthLong(Flags),
+    /// Month name from a custom [`MonthNames`] table, with letter case already applied.
+    MonthName((Flags, Arc<NameTable>)),
     Day(Flags),
     WeekdayNumeric(Flags),
     WeekdayShort(Flags),
@@ -161,6 +232,66 @@ pub type DateTimeFormat = Vec<Item>;
 
 // ---
 
+/// Custom month names used by `%b` and `%B` specifiers instead of the built-in English ones,
+/// e.g. `janv.` and `janvier`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonthNames {
+    short: [String; 12],
+    long: [String; 12],
+}
+
+impl MonthNames {
+    pub fn new<S: Into<String>>(short: [S; 12], long: [S; 12]) -> Self {
+        Self {
+            short: short.map(Into::into),
+            long: long.map(Into::into),
+        }
+    }
+
+    pub fn short(&self) -> &[String; 12] {
+        &self.short
+    }
+
+    pub fn long(&self) -> &[String; 12] {
+        &self.long
+    }
+}
+
+/// Table of names referenced by [`Item::MonthName`].
+///
+/// Names are right-aligned to the width of the longest one unless padding is disabled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTable {
+    names: Vec<String>,
+    width: usize,
+}
+
+impl NameTable {
+    fn new(names: &[String], flags: Flags) -> Self {
+        let names: Vec<_> = names
+            .iter()
+            .map(|name| {
+                if flags.contains(UpperCase) {
+                    name.to_uppercase()
+                } else if flags.contains(LowerCase) {
+                    name.to_lowercase()
+                } else {
+                    name.clone()
+                }
+            })
+            .collect();
+        let width = names.iter().map(|name| name.chars().count()).max().unwrap_or_default();
+        Self { names, width }
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> &str {
+        self.names.get(index).map(|name| name.as_str()).unwrap_or_default()
+    }
+}
+
+// ---
+
 #[derive(Clone)]
 pub struct LinuxDateFormat<'a> {
     spec: &'a [u8],
@@ -168,6 +299,7 @@ pub struct LinuxDateFormat<'a> {
     pad_counter: u8,
     pad: u8,
     flags: Flags,
+    month_names: Option<Arc<MonthNames>>,
 }
 
 impl<'a> LinuxDateFormat<'a> {
@@ -178,9 +310,15 @@ impl<'a> LinuxDateFormat<'a> {
             pad_counter: 0,
             pad: b' ',
             flags: Flags::empty(),
+            month_names: None,
         }
     }
 
+    /// Uses the given month names for `%b`, `%h` and `%B` specifiers.
+    pub fn with_month_names(self, month_names: Option<Arc<MonthNames>>) -> Self {
+        Self { month_names, ..self }
+    }
+
     pub fn compile(&mut self) -> DateTimeFormat {
         self.collect()
     }
@@ -229,6 +367,7 @@ impl<'a> LinuxDateFormat<'a> {
         let (tzf, b) = self.parse_tz_format(b);
         let b = self.skip_modifier(b);
         self.flags = Flags::empty();
+        let month_names = self.month_names.clone();
         let with_padding = |default| {
             if flags.intersects(PADDING) {
                 flags
@@ -262,12 +401,16 @@ impl<'a> LinuxDateFormat<'a> {
             return None;
         }
         let precision = || min(width, 9);
+        let month = |names: fn(&MonthNames) -> &[String; 12], item: fn(Flags) -> Item| match &month_names {
+            Some(month_names) => Item::MonthName((flags, Arc::new(NameTable::new(names(month_names), flags)))),
+            None => item(flags),
+        };
         match b {
             Some(b'%') => pad(1, b' ', b' ', b"%%", Some(Item::Char(b'%'))),
             Some(b'a') => pad(3, b' ', b' ', b"%a", Some(Item::WeekdayShort(flags))),
             Some(b'A') => pad(9, b' ', b' ', b"%A", Some(Item::WeekdayLong(flags))),
-            Some(b'b') => pad(3, b' ', b' ', b"%b", Some(Item::MonthShort(flags))),
-            Some(b'B') => pad(3, b' ', b' ', b"%B", Some(Item::MonthLong(flags))),
+            Some(b'b') => pad(3, b' ', b' ', b"%b", Some(month(MonthNames::short, Item::MonthShort))),
+            Some(b'B') => pad(3, b' ', b' ', b"%B", Some(month(MonthNames::long, Item::MonthLong))),
             Some(b'c') => self.jump(b"%a %b %e %H:%M:%S %Y", 24, width),
             Some(b'C') => pad(2, b'0', b'0', b"%C", Some(Item::Century(flags))),
             Some(b'd') => pad(2, b'0', b'0', b"%d", Some(Item::Day(flags))),
@@ -276,7 +419,7 @@ impl<'a> LinuxDateFormat<'a> {
             Some(b'F') => self.jump(b"%Y-%m-%d", 10, width),
             Some(b'g') => pad(2, b'0', b'0', b"%g", Some(Item::IsoYearShort(flags))),
             Some(b'G') => pad(4, b'0', b'0', b"%G", Some(Item::IsoYear(flags))),
-            Some(b'h') => pad(3, b' ', b' ', b"%h", Some(Item::MonthShort(flags))),
+            Some(b'h') => pad(3, b' ', b' ', b"%h", Some(month(MonthNames::short, Item::MonthShort))),
             Some(b'H') => pad(2, b'0', b'0', b"%H", Some(Item::Hour(flags))),
             Some(b'I') => pad(2, b'0', b'0', b"%I", Some(Item::Hour12(flags))),
             Some(b'j') => pad(3, b'0', b'0', b"%j", Some(Item::YearDay(flags))),
@@ -445,6 +588,9 @@ where
             Item::MonthLong(flags) => {
                 f.month_long(dt.month0() as usize, flags);
             }
+            Item::MonthName((flags, ref names)) => {
+                f.month_name(names, dt.month0() as usize, flags);
+            }
             Item::Day(flags) => {
                 f.numeric(dt.day(), 2, flags);
             }
@@ -618,6 +764,9 @@ where
             Item::MonthLong(flags) => {
                 f.month_long(month(), flags);
             }
+            Item::MonthName((flags, ref names)) => {
+                f.month_name(names, month(), flags);
+            }
             Item::Day(n) => {
                 reformat_numeric_2(f.buf, n, ts[8], ts[9]);
             }
@@ -852,6 +1001,17 @@ impl<'a, B: Push<u8>> Formatter<'a, B> {
         }
     }
 
+    #[inline]
+    fn month_name(&mut self, names: &NameTable, index: usize, flags: Flags) {
+        let text = names.get(index);
+        if !flags.contains(NoPadding) {
+            for _ in text.chars().count()..names.width {
+                self.buf.push(b' ');
+            }
+        }
+        self.buf.extend_from_slice(text.as_bytes());
+    }
+
     #[inline]
     fn weekday_short(&mut self, index: usize, flags: Flags) {
         let text = &WEEKDAYS_SHORT[case_index(flags)][index].as_bytes();
@@ -904,7 +1064,8 @@ impl<'a, B: Push<u8>> Formatter<'a, B> {
     #[inline]
     fn iso_week(&mut self, dt: &NaiveDateTime, flags: Flags) {
         let value = if flags.contains(FromZero) {
-            dt.iso_week().week0()
+            // week of the year starting from the first Monday, with preceding days in week 0
+            (dt.ordinal0() + 7 - dt.weekday().num_days_from_monday()) / 7
         } else {
             dt.iso_week().week()
         };
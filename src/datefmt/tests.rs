@@ -34,6 +34,74 @@ fn test_default_formatter() {
     assert_eq!(formatted, "2023-05-15 14:30:45");
 }
 
+#[test]
+fn test_time_only() {
+    let dt = utc(2023, 5, 15, 14, 30, 45).fixed_offset();
+    let formatter = DateTimeFormatter::new(format("%y-%m-%d %T.%3N"), Tz::IANA(UTC));
+    let mut buf = Vec::new();
+    formatter.time_only().unwrap().format(&mut buf, dt);
+    assert_eq!(String::from_utf8(buf).unwrap(), "14:30:45.000");
+
+    assert!(
+        DateTimeFormatter::new(format("%T %d"), Tz::IANA(UTC))
+            .time_only()
+            .is_none()
+    );
+    assert!(
+        DateTimeFormatter::new(format("%Y-%m-%d"), Tz::IANA(UTC))
+            .time_only()
+            .is_none()
+    );
+}
+
+#[test]
+fn test_custom_month_names() {
+    let names = Arc::new(MonthNames::new(
+        [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc.",
+        ],
+        [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+    ));
+    let format = |fmt: &str| {
+        LinuxDateFormat::new(fmt)
+            .with_month_names(Some(names.clone()))
+            .compile()
+    };
+    let f = |fmt: &str, dt: DateTime<Tz>| {
+        let mut buf = Vec::new();
+        format_date(&mut buf, dt, format(fmt));
+        String::from_utf8(buf).unwrap()
+    };
+
+    assert_eq!(f("%d %b", utc(2023, 2, 1, 0, 0, 0)), "01 févr.");
+    assert_eq!(f("%d %b", utc(2023, 5, 1, 0, 0, 0)), "01   mai");
+    assert_eq!(f("%d %-b", utc(2023, 5, 1, 0, 0, 0)), "01 mai");
+    assert_eq!(f("%d %^B", utc(2023, 12, 1, 0, 0, 0)), "01  DÉCEMBRE");
+    assert_eq!(f("%d %-h", utc(2023, 8, 1, 0, 0, 0)), "01 août");
+    assert_eq!(f("%d %-B", utc(2023, 6, 1, 0, 0, 0)), "01 juin");
+
+    let formatter = DateTimeFormatter::new(format("%d %-B"), Tz::IANA(UTC));
+    assert_eq!(formatter.max_length(), "01 septembre".len());
+    assert_eq!(formatter.max_width().chars, "01 septembre".len());
+
+    let formatter = DateTimeFormatter::new(format("%d %b"), Tz::IANA(UTC));
+    assert_eq!(formatter.max_length(), "01 févr.".len());
+    assert_eq!(formatter.max_width().chars, "01 janv.".len());
+}
+
 #[test]
 fn test_compile_offset() {
     assert_eq!(format("%:z"), vec![Item::TimeZoneOffset((Flags::empty(), 2))]);
@@ -130,6 +198,91 @@ fn test_weekday_numeric_formats() {
     assert_eq!(f("%w", sunday), "0"); // Sunday=0 in %w format
 }
 
+#[rstest]
+#[case::millis_micro("2020-06-27T00:48:30.466Z", "%6N", "466000")]
+#[case::millis_nano("2020-06-27T00:48:30.466Z", "%9N", "466000000")]
+#[case::nanos_micro("2020-06-27T00:48:30.466249792Z", "%6N", "466249")]
+#[case::nanos_nano("2020-06-27T00:48:30.466249792Z", "%9N", "466249792")]
+#[case::nanos_milli("2020-06-27T00:48:30.466249792Z", "%3N", "466")]
+#[case::no_fraction("2020-06-27T00:48:30Z", "%6N", "000000")]
+fn test_fraction_precision(#[case] input: &str, #[case] fmt: &str, #[case] expected: &str) {
+    use crate::timestamp::Timestamp;
+
+    let formatter = DateTimeFormatter::new(format(fmt), Tz::IANA(UTC));
+
+    let mut buf = Vec::new();
+    formatter.format(&mut buf, DateTime::parse_from_rfc3339(input).unwrap());
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+
+    let ts = Timestamp::new(input);
+    let mut buf = Vec::new();
+    formatter.reformat_rfc3339(&mut buf, ts.as_rfc3339().unwrap()).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+}
+
+#[rstest]
+#[case("%T.%3N", 12)]
+#[case("%T.%6N", 15)]
+#[case("%T.%9N", 18)]
+#[case("%T.%N", 18)]
+fn test_fraction_precision_max_length(#[case] fmt: &str, #[case] expected: usize) {
+    let formatter = DateTimeFormatter::new(format(fmt), Tz::IANA(UTC));
+    assert_eq!(formatter.max_length(), expected);
+    assert_eq!(formatter.max_width().chars, expected);
+}
+
+#[rstest]
+#[case::mid_week(utc(2020, 1, 2, 12, 0, 0), "2020-W01-4", "2020-002")]
+#[case::previous_iso_year(utc(2021, 1, 1, 12, 0, 0), "2020-W53-5", "2021-001")]
+#[case::next_iso_year(utc(2019, 12, 30, 12, 0, 0), "2020-W01-1", "2019-364")]
+#[case::leap_year_end(utc(2020, 12, 31, 12, 0, 0), "2020-W53-4", "2020-366")]
+fn test_week_date_and_year_day(#[case] dt: DateTime<Tz>, #[case] week_date: &str, #[case] year_day: &str) {
+    assert_eq!(f("%G-W%V-%u", dt), week_date);
+    assert_eq!(f("%Y-%j", dt), year_day);
+}
+
+#[test]
+fn test_week_date_and_year_day_in_timezone() {
+    let format = LinuxDateFormat::new("%G-W%V-%u %Y-%j").compile();
+    let formatter = DateTimeFormatter::new(format, Tz::FixedOffset(FixedOffset::east_opt(3600).unwrap()));
+
+    // 2020-01-01T23:30:00Z is already Thursday, January 2 at UTC+1
+    let mut buf = Vec::new();
+    formatter.format(&mut buf, utc(2020, 1, 1, 23, 30, 0).fixed_offset());
+    assert_eq!(String::from_utf8(buf).unwrap(), "2020-W01-4 2020-002");
+}
+
+#[rstest]
+#[case::utc(0, "%Y-%m-%d %T.%6N %:z")]
+#[case::cet(3600, "%Y-%m-%d %T.%6N %:z")]
+#[case::epoch(0, "%s.%3N")]
+fn test_format_unix(#[case] offset: i32, #[case] fmt: &str) {
+    let formatter = DateTimeFormatter::new(format(fmt), Tz::FixedOffset(FixedOffset::east_opt(offset).unwrap()));
+
+    for (secs, nsecs) in [(1597994448, 123456789), (0, 0), (-1, 999_000_000)] {
+        let mut expected = Vec::new();
+        formatter.format(
+            &mut expected,
+            DateTime::from_timestamp(secs, nsecs).unwrap().fixed_offset(),
+        );
+
+        let mut buf = Vec::new();
+        formatter.format_unix(&mut buf, secs, nsecs).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), String::from_utf8(expected).unwrap());
+    }
+
+    assert_eq!(formatter.format_unix(&mut Vec::new(), i64::MAX, 0), None);
+}
+
+#[test]
+fn test_week_of_year_from_monday() {
+    assert_eq!(f("%W", utc(2021, 1, 1, 12, 0, 0)), "00");
+    assert_eq!(f("%W", utc(2021, 1, 4, 12, 0, 0)), "01");
+    assert_eq!(f("%W", utc(2020, 1, 1, 12, 0, 0)), "00");
+    assert_eq!(f("%W", utc(2020, 1, 6, 12, 0, 0)), "01");
+    assert_eq!(f("%W", utc(2020, 12, 31, 12, 0, 0)), "52");
+}
+
 #[test]
 fn test_year_padding_flags_rfc3339() {
     // Test year formatting with padding flags in reformat_rfc3339 to cover lines 953-954, 956, 960-961
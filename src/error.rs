@@ -127,6 +127,8 @@ pub enum Error {
     ParseIntError(#[from] ParseIntError),
     #[error("failed to detect application directories")]
     AppDirs,
+    #[error("unknown filter {0:?}")]
+    UnknownFilter(String),
 }
 
 impl Error {
@@ -217,6 +217,94 @@ impl From<Vec<StyleCode>> for Sequence {
 
 // ---
 
+/// A chunk of a byte string that may contain ANSI escape sequences.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Chunk<'a> {
+    Text(&'a [u8]),
+    Escape(&'a [u8]),
+}
+
+/// Splits `data` into plain text and escape sequence chunks.
+///
+/// Recognizes CSI sequences (including SGR), OSC sequences terminated by BEL or ST,
+/// and other two-byte escape sequences. An incomplete trailing sequence is returned as a single escape chunk.
+#[inline]
+pub fn chunks(data: &[u8]) -> Chunks<'_> {
+    Chunks { data }
+}
+
+pub struct Chunks<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Chunk<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let (chunk, n) = if self.data[0] == ESC {
+            let n = escape_len(self.data);
+            (Chunk::Escape(&self.data[..n]), n)
+        } else {
+            let n = memchr::memchr(ESC, self.data).unwrap_or(self.data.len());
+            (Chunk::Text(&self.data[..n]), n)
+        };
+
+        self.data = &self.data[n..];
+        Some(chunk)
+    }
+}
+
+/// Removes all escape sequences from `buf` in place, leaving only plain text.
+///
+/// Useful as a post-processor for output targets that do not interpret ANSI escape sequences,
+/// such as legacy Windows consoles without VT support.
+pub fn strip_ansi(buf: &mut Vec<u8>) {
+    let mut src = 0;
+    let mut dst = 0;
+    while src < buf.len() {
+        if buf[src] == ESC {
+            src += escape_len(&buf[src..]);
+        } else {
+            let n = memchr::memchr(ESC, &buf[src..]).unwrap_or(buf.len() - src);
+            buf.copy_within(src..src + n, dst);
+            src += n;
+            dst += n;
+        }
+    }
+    buf.truncate(dst);
+}
+
+fn escape_len(data: &[u8]) -> usize {
+    match data.get(1) {
+        Some(b'[') => data[2..]
+            .iter()
+            .position(|&c| (0x40..=0x7e).contains(&c))
+            .map_or(data.len(), |i| i + 3),
+        Some(b']') => {
+            for i in 2..data.len() {
+                match data[i] {
+                    BEL => return i + 1,
+                    ESC if data.get(i + 1) == Some(&b'\\') => return i + 2,
+                    _ => {}
+                }
+            }
+            data.len()
+        }
+        Some(_) => 2,
+        None => 1,
+    }
+}
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+// ---
+
 #[inline]
 fn begin(buf: &mut Vec<u8>) {
     buf.push(b'\x1b');
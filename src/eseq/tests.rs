@@ -197,3 +197,50 @@ fn test_color_code_fg_bg() {
         StyleCode::Background(ColorCode::Plain(Color::Yellow, Brightness::Bright))
     ));
 }
+
+#[test]
+fn test_chunks() {
+    let data = b"\x1b[0;1mab\x1b]8;;http://x\x07c\x1b]8;;\x1b\\\x1b(d\x1b[3";
+    let result: Vec<_> = chunks(data).collect();
+    assert_eq!(
+        result,
+        vec![
+            Chunk::Escape(b"\x1b[0;1m"),
+            Chunk::Text(b"ab"),
+            Chunk::Escape(b"\x1b]8;;http://x\x07"),
+            Chunk::Text(b"c"),
+            Chunk::Escape(b"\x1b]8;;\x1b\\"),
+            Chunk::Escape(b"\x1b("),
+            Chunk::Text(b"d"),
+            Chunk::Escape(b"\x1b[3"),
+        ]
+    );
+}
+
+#[test]
+fn test_chunks_plain() {
+    assert_eq!(chunks(b"").next(), None);
+    assert_eq!(chunks(b"abc").collect::<Vec<_>>(), vec![Chunk::Text(b"abc")]);
+}
+
+#[test]
+fn test_strip_ansi() {
+    let mut buf = b"\x1b[0;1mab\x1b]8;;http://x\x07c\x1b]8;;\x1b\\\x1b(d\x1b[0m e\x1b[3".to_vec();
+    strip_ansi(&mut buf);
+    assert_eq!(buf, b"abcd e");
+}
+
+#[test]
+fn test_strip_ansi_plain() {
+    let mut buf = Vec::new();
+    strip_ansi(&mut buf);
+    assert_eq!(buf, b"");
+
+    let mut buf = b"plain text".to_vec();
+    strip_ansi(&mut buf);
+    assert_eq!(buf, b"plain text");
+
+    let mut buf = b"\x1b[31m\x1b[0m".to_vec();
+    strip_ansi(&mut buf);
+    assert_eq!(buf, b"");
+}
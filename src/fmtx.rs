@@ -1,5 +1,12 @@
+// std imports
 use std::cmp::min;
 
+// third-party imports
+use unicode_width::UnicodeWidthStr;
+
+// local imports
+use crate::eseq::{Chunk, chunks};
+
 // ---
 
 pub trait Push<T> {
@@ -427,5 +434,19 @@ where
     f(BufferedAligner::new(out, Padding::new(pad, width), Alignment::Center))
 }
 
+// ---
+
+/// Returns the number of terminal columns occupied by `buf`, ignoring ANSI escape sequences.
+///
+/// Wide and zero-width characters are accounted for using Unicode width tables.
+pub fn visible_width(buf: &[u8]) -> usize {
+    chunks(buf)
+        .map(|chunk| match chunk {
+            Chunk::Text(text) => String::from_utf8_lossy(text).width(),
+            Chunk::Escape(_) => 0,
+        })
+        .sum()
+}
+
 #[cfg(test)]
 mod tests;
@@ -189,3 +189,13 @@ fn test_centered() {
     });
     assert_eq!(buf, b"__xy__");
 }
+
+#[test]
+fn test_visible_width() {
+    assert_eq!(visible_width(b""), 0);
+    assert_eq!(visible_width(b"abc"), 3);
+    assert_eq!(visible_width(b"\x1b[0;32mkey\x1b[0;2m=\x1b[0mvalue\x1b[0m"), 9);
+    assert_eq!(visible_width("\x1b[1m漢字\x1b[0m x".as_bytes()), 6);
+    assert_eq!(visible_width("e\u{301}".as_bytes()), 1);
+    assert_eq!(visible_width(b"\x1b]8;;http://example.com\x07link\x1b]8;;\x1b\\"), 4);
+}
@@ -1,29 +1,41 @@
 // std imports
 use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    io,
     mem::{replace, take},
     ops::{Deref, DerefMut, Range},
-    sync::Arc,
+    string::FromUtf8Error,
+    sync::{Arc, Mutex},
 };
 
 // third-party imports
-use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone};
+use enum_map::EnumMap;
 use enumset::{EnumSet, EnumSetType};
-use itertools::izip;
+use itertools::{Either, Itertools, izip};
+use strum::IntoEnumIterator;
+use thiserror::Error;
 
 // workspace imports
-use encstr::EncodedString;
+use encstr::{AnyEncodedString, EncodedString};
 
 // local imports
 use crate::{
     ExactIncludeExcludeKeyFilter, IncludeExcludeKeyFilter,
-    datefmt::{DateTimeFormatter, TextWidth},
+    datefmt::{DateTimeFormatter, TextWidth, ZoneSuffix},
+    error,
+    eseq::{Chunk, Sequence, chunks},
     filtering::IncludeExcludeSetting,
-    fmtx::{OptimizedBuf, Push, aligned_left},
+    fmtx::{OptimizedBuf, Push, aligned_left, visible_width},
     model::{self, Caller, Level, RawValue},
     scanning::{Delimit, Newline, SearchExt},
     settings::{self, AsciiMode, ExpansionMode, Formatting, ResolvedPunctuation},
     syntax::*,
     theme::{Element, Styler, StylingPush, Theme},
+    timestamp::Timestamp,
 };
 
 // test imports
@@ -33,6 +45,13 @@ use crate::testing::Sample;
 // relative imports
 use string::{DynMessageFormat, ExtendedSpaceAction, Format, ValueFormatAuto};
 
+// sub-modules
+mod anonymize;
+pub mod columnar;
+pub mod markdown;
+
+pub use anonymize::{AnonymizeTarget, AnonymizeTargets};
+
 // ---
 
 type Buf = Vec<u8>;
@@ -66,168 +85,1870 @@ impl From<settings::ExpansionOptions> for Expansion {
     #[inline(always)]
     fn from(options: settings::ExpansionOptions) -> Self {
         Self {
-            mode: options.mode.unwrap_or_default(),
+            mode: options.mode.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<settings::ExpansionMode> for Expansion {
+    #[inline(always)]
+    fn from(mode: settings::ExpansionMode) -> Self {
+        Self { mode }
+    }
+}
+
+// ---
+
+/// Line terminator appended by [`RecordFormatter`] after each formatted record.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineTerminator {
+    /// Nothing is appended, the caller is responsible for separating records.
+    #[default]
+    None,
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl LineTerminator {
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            Self::None => b"",
+            Self::Lf => b"\n",
+            Self::CrLf => b"\r\n",
+        }
+    }
+}
+
+// ---
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MultilineExpansion {
+    #[default]
+    Standard,
+    Disabled,
+    Inline,
+}
+
+// ---
+
+#[derive(Clone, Debug)]
+pub struct ExpansionThresholds {}
+
+// ---
+
+#[derive(Clone, Debug)]
+pub struct ExpansionProfile {
+    pub multiline: MultilineExpansion,
+    pub thresholds: ExpansionThresholds,
+    pub expand_all: bool,
+}
+
+impl ExpansionProfile {
+    pub const NEVER: Self = Self {
+        multiline: MultilineExpansion::Disabled,
+        thresholds: ExpansionThresholds {},
+        expand_all: false,
+    };
+
+    pub const ALWAYS: Self = Self {
+        multiline: MultilineExpansion::Standard,
+        thresholds: ExpansionThresholds {},
+        expand_all: true,
+    };
+
+    pub const INLINE: Self = Self {
+        multiline: MultilineExpansion::Inline,
+        thresholds: ExpansionThresholds {},
+        expand_all: false,
+    };
+
+    pub const AUTO: Self = Self {
+        multiline: MultilineExpansion::Standard,
+        thresholds: ExpansionThresholds {},
+        expand_all: false,
+    };
+}
+
+impl Default for &ExpansionProfile {
+    fn default() -> Self {
+        &ExpansionProfile::NEVER
+    }
+}
+
+// ---
+
+pub trait RecordWithSourceFormatter {
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource);
+}
+
+/// Formatter able to omit selected fields of a record, showing the hidden fields indicator in their place.
+pub trait RecordWithSkipFormatter: RecordWithSourceFormatter {
+    fn format_record_with_skip(
+        &self,
+        buf: &mut Buf,
+        prefix: Range<usize>,
+        rec: model::RecordWithSource,
+        skip: &FieldSkip,
+    );
+}
+
+pub struct RawRecordFormatter {
+    pub delimiter: String,
+}
+
+impl RawRecordFormatter {
+    #[inline(always)]
+    fn format_source(&self, buf: &mut Buf, prefix: Range<usize>, source: &[u8]) {
+        let mut first = true;
+        for line in Newline.into_searcher().split(source) {
+            if !first {
+                buf.extend_from_slice(self.delimiter.as_bytes());
+                buf.extend_from_within(prefix.clone());
+            }
+            first = false;
+            buf.extend_from_slice(line);
+        }
+    }
+}
+
+impl RecordWithSourceFormatter for RawRecordFormatter {
+    #[inline(always)]
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        self.format_source(buf, prefix, rec.source)
+    }
+}
+
+// ---
+
+/// Formats sources of single records using the inner formatter and falls back to the raw source
+/// styled as [`Element::Unparsed`] for input that cannot be parsed.
+///
+/// The source is split into a record using the given raw record parser, so that its settings,
+/// such as the input format, allowed prefix and relaxed JSON, apply the same way as to the rest of the input.
+pub struct FallbackFormatter<F> {
+    formatter: F,
+    raw_parser: model::RawRecordParser,
+    parser: model::Parser,
+    theme: Arc<Theme>,
+    raw: RawRecordFormatter,
+}
+
+impl<F: RecordWithSourceFormatter> FallbackFormatter<F> {
+    pub fn new(
+        formatter: F,
+        raw_parser: model::RawRecordParser,
+        parser: model::Parser,
+        theme: Arc<Theme>,
+        delimiter: String,
+    ) -> Self {
+        Self {
+            formatter,
+            raw_parser,
+            parser,
+            theme,
+            raw: RawRecordFormatter { delimiter },
+        }
+    }
+
+    /// Parses a single record from `source` and formats it using the inner formatter.
+    /// If the record cannot be parsed, `source` is emitted as is.
+    pub fn format_source(&self, buf: &mut Buf, prefix: Range<usize>, source: &[u8]) {
+        let relaxed = self.raw_parser.relax(source);
+        match self.raw_parser.parse(relaxed.as_deref().unwrap_or(source)).next() {
+            Some(Ok(mut ar)) => {
+                if relaxed.is_some() {
+                    ar.offsets.end = source.len();
+                }
+                let record = self.parser.parse(&ar.record);
+                self.formatter
+                    .format_record(buf, prefix, model::RecordWithSource::new(&record, &source[ar.offsets]));
+            }
+            Some(Err(_)) | None => {
+                self.theme.apply(buf, &None, |s| {
+                    s.element(Element::Unparsed, |s| {
+                        s.batch(|buf| self.raw.format_source(buf, prefix, source));
+                    });
+                });
+            }
+        }
+    }
+}
+
+// ---
+
+/// Formats records using the inner formatter, prefixed with a column showing the time
+/// elapsed since the previous record, styled as [`Element::Delta`].
+///
+/// The column is left blank for the first record and for records without a timestamp.
+/// Records with a timestamp earlier than the previous one get a negative delta.
+pub struct DeltaFormatter<F> {
+    formatter: F,
+    theme: Arc<Theme>,
+    previous: Mutex<Option<DateTime<FixedOffset>>>,
+}
+
+impl<F: RecordWithSourceFormatter> DeltaFormatter<F> {
+    /// Width of the delta column, in characters.
+    pub const WIDTH: usize = 7;
+
+    pub fn new(formatter: F, theme: Arc<Theme>) -> Self {
+        Self {
+            formatter,
+            theme,
+            previous: Mutex::new(None),
+        }
+    }
+
+    fn delta(&self, rec: &model::Record) -> Option<chrono::TimeDelta> {
+        let ts = rec.ts.as_ref()?.parse()?;
+        let mut previous = self.previous.lock().unwrap();
+        previous.replace(ts).map(|previous| ts - previous)
+    }
+}
+
+impl<F: RecordWithSourceFormatter> RecordWithSourceFormatter for DeltaFormatter<F> {
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        let delta = self.delta(rec.record);
+        self.theme.apply(buf, &rec.record.level, |s| {
+            s.element(Element::Delta, |s| {
+                s.batch(|buf| {
+                    aligned_left(buf, Self::WIDTH, b' ', |mut buf| {
+                        if let Some(delta) = delta {
+                            buf.extend_from_slice(format_delta(delta).as_bytes());
+                        }
+                    })
+                })
+            });
+            s.space();
+        });
+        self.formatter.format_record(buf, prefix, rec)
+    }
+}
+
+fn format_delta(delta: chrono::TimeDelta) -> String {
+    let sign = if delta < chrono::TimeDelta::zero() { '-' } else { '+' };
+    let delta = delta.abs();
+    let ms = delta.num_milliseconds();
+    match delta.num_seconds() {
+        0 => format!("{sign}{ms}ms"),
+        s @ 1..60 => format!("{sign}{s}.{}s", ms % 1000 / 100),
+        s @ 60..3600 => format!("{sign}{}m{:02}s", s / 60, s % 60),
+        s => format!("{sign}{}h{:02}m", s / 3600, s % 3600 / 60),
+    }
+}
+
+// ---
+
+/// Formats records using the inner formatter, showing only the fields whose values differ from the previous record.
+///
+/// Time, level, logger, message and caller are always shown, and unchanged fields are replaced
+/// with the hidden fields indicator. Records are compared in the order they are formatted,
+/// so the formatter is only meaningful for sequential processing.
+pub struct ChangedFieldsFormatter<F> {
+    formatter: F,
+    previous: Mutex<Vec<(String, String)>>,
+}
+
+impl<F: RecordWithSkipFormatter> ChangedFieldsFormatter<F> {
+    pub fn new(formatter: F) -> Self {
+        Self {
+            formatter,
+            previous: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Forgets the previous record, so that all fields of the next record are shown.
+    pub fn reset(&self) {
+        self.previous.lock().unwrap().clear();
+    }
+}
+
+impl<F: RecordWithSkipFormatter> RecordWithSourceFormatter for ChangedFieldsFormatter<F> {
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        let mut previous = self.previous.lock().unwrap();
+        let record = rec.record;
+
+        let unchanged = |key: &str, value: RawValue| previous.iter().any(|(k, v)| k == key && v == value.raw_str());
+        self.formatter.format_record_with_skip(buf, prefix, rec, &unchanged);
+
+        previous.clear();
+        previous.extend(record.fields().map(|(k, v)| (k.to_string(), v.raw_str().to_string())));
+    }
+}
+
+// ---
+
+/// Criterion deciding when a new group of records starts, see [`GroupSeparatorFormatter`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GroupBy {
+    /// A new group starts when the logger differs from the logger of the previous record.
+    Logger,
+}
+
+/// Formats records using the inner formatter, emitting a separator line styled as [`Element::GroupSeparator`]
+/// before each record starting a new group, to visually group records of merged logs.
+///
+/// Groups are delimited according to the given criterion. No separator is emitted before the first record.
+/// The separator line is terminated by the delimiter and followed by the line prefix.
+pub struct GroupSeparatorFormatter<F> {
+    formatter: F,
+    theme: Arc<Theme>,
+    group_by: GroupBy,
+    separator: String,
+    delimiter: String,
+    previous: Mutex<Option<Option<String>>>,
+}
+
+impl<F: RecordWithSourceFormatter> GroupSeparatorFormatter<F> {
+    pub fn new(formatter: F, theme: Arc<Theme>, group_by: GroupBy, separator: String, delimiter: String) -> Self {
+        Self {
+            formatter,
+            theme,
+            group_by,
+            separator,
+            delimiter,
+            previous: Mutex::new(None),
+        }
+    }
+
+    fn starts_group(&self, rec: &model::Record) -> bool {
+        match self.group_by {
+            GroupBy::Logger => {
+                let mut previous = self.previous.lock().unwrap();
+                let changed = previous.as_ref().is_some_and(|logger| logger.as_deref() != rec.logger);
+                *previous = Some(rec.logger.map(|logger| logger.to_owned()));
+                changed
+            }
+        }
+    }
+}
+
+impl<F: RecordWithSourceFormatter> RecordWithSourceFormatter for GroupSeparatorFormatter<F> {
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        if self.starts_group(rec.record) {
+            self.theme.apply(buf, &None, |s| {
+                s.element(Element::GroupSeparator, |s| {
+                    s.batch(|buf| buf.extend_from_slice(self.separator.as_bytes()))
+                });
+            });
+            buf.extend_from_slice(self.delimiter.as_bytes());
+            buf.extend_from_within(prefix.clone());
+        }
+        self.formatter.format_record(buf, prefix, rec)
+    }
+}
+
+/// Formats each record using all of the inner formatters in order, separating their outputs with the delimiter,
+/// e.g. to compare how the same record is rendered in different output formats.
+///
+/// The line prefix is repeated after each delimiter.
+pub struct TeeFormatter {
+    formatters: Vec<DynRecordWithSourceFormatter>,
+    delimiter: String,
+}
+
+impl TeeFormatter {
+    pub fn new<I>(formatters: I, delimiter: String) -> Self
+    where
+        I: IntoIterator<Item = DynRecordWithSourceFormatter>,
+    {
+        Self {
+            formatters: formatters.into_iter().collect(),
+            delimiter,
+        }
+    }
+}
+
+impl RecordWithSourceFormatter for TeeFormatter {
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        for (i, formatter) in self.formatters.iter().enumerate() {
+            if i != 0 {
+                buf.extend_from_slice(self.delimiter.as_bytes());
+                buf.extend_from_within(prefix.clone());
+            }
+            formatter.format_record(
+                buf,
+                prefix.clone(),
+                model::RecordWithSource::new(rec.record, rec.source),
+            );
+        }
+    }
+}
+
+// ---
+
+/// Formats records using the inner formatter, omitting the date from the time column if it is the same
+/// as in the previously formatted record, showing only the time padded to the width of the column.
+///
+/// Records should be formatted in the order they are shown, as the formatter keeps the date of the previous one.
+/// Has no effect if the time format has no date part preceding the time part.
+pub struct CompactTimestampFormatter {
+    formatter: RecordFormatter,
+    time_formatter: Option<RecordFormatter>,
+    previous: Mutex<Option<NaiveDate>>,
+}
+
+impl CompactTimestampFormatter {
+    pub fn new(formatter: RecordFormatter) -> Self {
+        let time_formatter = formatter.ts_formatter.time_only().map(|ts_formatter| RecordFormatter {
+            ts_formatter,
+            ..formatter.clone()
+        });
+        Self {
+            formatter,
+            time_formatter,
+            previous: Mutex::new(None),
+        }
+    }
+
+    fn same_date(&self, rec: &model::Record) -> bool {
+        let Some(dt) = rec.ts.as_ref().and_then(|ts| self.formatter.record_time(ts)) else {
+            return false;
+        };
+        let date = dt.with_timezone(self.formatter.ts_formatter.tz()).date_naive();
+        self.previous.lock().unwrap().replace(date) == Some(date)
+    }
+}
+
+impl RecordWithSourceFormatter for CompactTimestampFormatter {
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        let formatter = match &self.time_formatter {
+            Some(formatter) if self.same_date(rec.record) => formatter,
+            _ => &self.formatter,
+        };
+        formatter.format_record(buf, prefix, rec.record)
+    }
+}
+
+impl<T: RecordWithSourceFormatter + ?Sized> RecordWithSourceFormatter for &T {
+    #[inline(always)]
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        (**self).format_record(buf, prefix, rec)
+    }
+}
+
+impl<T: RecordWithSkipFormatter + ?Sized> RecordWithSkipFormatter for &T {
+    #[inline(always)]
+    fn format_record_with_skip(
+        &self,
+        buf: &mut Buf,
+        prefix: Range<usize>,
+        rec: model::RecordWithSource,
+        skip: &FieldSkip,
+    ) {
+        (**self).format_record_with_skip(buf, prefix, rec, skip)
+    }
+}
+
+impl<T: RecordWithSourceFormatter + ?Sized> RecordWithSourceFormatter for Arc<T> {
+    #[inline(always)]
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        (**self).format_record(buf, prefix, rec)
+    }
+}
+
+// ---
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoOpRecordWithSourceFormatter;
+
+impl RecordWithSourceFormatter for NoOpRecordWithSourceFormatter {
+    #[inline(always)]
+    fn format_record(&self, _: &mut Buf, _: Range<usize>, _: model::RecordWithSource) {}
+}
+
+// ---
+
+pub type DynRecordWithSourceFormatter = Arc<dyn RecordWithSourceFormatter + Send + Sync>;
+
+// ---
+
+/// Rewrites values of matching fields before they are rendered.
+///
+/// Transforms registered with [`RecordFormatterBuilder::with_value_transforms`] are tried in order,
+/// and only the first one matching a field is applied.
+pub trait ValueTransform: Send + Sync {
+    /// Returns true if the transform applies to `value` of the field with the given `key`.
+    fn matches(&self, key: &str, value: RawValue) -> bool;
+
+    /// Returns the replacement for `value`, rendered the same way as an unquoted string value,
+    /// or `None` to render the original value.
+    fn transform(&self, key: &str, value: RawValue) -> Option<String>;
+}
+
+pub type DynValueTransform = Arc<dyn ValueTransform>;
+
+/// Predicate selecting fields to be omitted by [`RecordWithSkipFormatter::format_record_with_skip`].
+pub type FieldSkip<'a> = dyn Fn(&str, RawValue) -> bool + 'a;
+
+// ---
+
+/// Custom renderer for field values.
+///
+/// The hook is called with the field key and value after the key and the key-value separator are rendered.
+/// Returning `true` means the value has been rendered by the hook and the default rendering is skipped.
+pub type FieldHook = Arc<dyn Fn(&str, RawValue, &mut dyn FieldHookOutput) -> bool + Send + Sync>;
+
+/// Styled output available to a [`FieldHook`].
+pub trait FieldHookOutput {
+    /// Appends `text` styled as `element`.
+    fn push(&mut self, element: Element, text: &[u8]);
+}
+
+impl<S: StylingPush<Buf>> FieldHookOutput for S {
+    #[inline]
+    fn push(&mut self, element: Element, text: &[u8]) {
+        self.element(element, |s| s.batch(|buf| buf.extend(text)));
+    }
+}
+
+/// Comparator of field keys defining the order of fields, both top-level and nested.
+///
+/// The sort is stable, so fields with keys comparing as equal keep their original order.
+pub type FieldSort = Arc<dyn Fn(&str, &str) -> Ordering + Send + Sync>;
+
+/// Returns a [`FieldSort`] placing fields with any of the given keys last, in the order of the keys,
+/// e.g. to move message-like fields to the end of the line, and keeping the original order of other fields.
+pub fn keys_last_field_sort<I, K>(keys: I) -> FieldSort
+where
+    I: IntoIterator<Item = K>,
+    K: Into<String>,
+{
+    let keys: Vec<String> = keys.into_iter().map(Into::into).collect();
+    Arc::new(move |a, b| {
+        let rank = |key: &str| keys.iter().position(|k| k == key);
+        match (rank(a), rank(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        }
+    })
+}
+
+// ---
+
+/// Maximum nesting level of JSON embedded into strings expanded by [`RecordFormatterBuilder::with_expand_embedded_json`].
+pub const MAX_EMBEDDED_JSON_DEPTH: usize = 4;
+
+/// Maximum size of a chunk written by [`RecordFormatter::write_record_buffered`].
+pub const WRITE_CHUNK_SIZE: usize = 8 * 1024;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+thread_local! {
+    static WRITE_BUF: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(WRITE_CHUNK_SIZE));
+}
+
+// ---
+
+/// Sets of string values rendered as booleans, e.g. `"yes"` or `"no"`.
+#[derive(Clone, Debug)]
+pub struct BoolStrings {
+    true_values: Vec<String>,
+    false_values: Vec<String>,
+    max_len: usize,
+}
+
+impl BoolStrings {
+    pub const DEFAULT_TRUE_VALUES: &[&str] = &["true", "yes"];
+    pub const DEFAULT_FALSE_VALUES: &[&str] = &["false", "no"];
+
+    pub fn new<T, F, K>(true_values: T, false_values: F) -> Self
+    where
+        T: IntoIterator<Item = K>,
+        F: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        let true_values: Vec<String> = true_values.into_iter().map(Into::into).collect();
+        let false_values: Vec<String> = false_values.into_iter().map(Into::into).collect();
+        let max_len = true_values
+            .iter()
+            .chain(&false_values)
+            .map(|v| v.len())
+            .max()
+            .unwrap_or(0);
+        Self {
+            true_values,
+            false_values,
+            max_len,
+        }
+    }
+
+    /// Returns the boolean value of the string if it exactly matches one of the configured values.
+    fn classify(&self, value: EncodedString) -> Option<bool> {
+        // JSON source includes the quotes and possibly `\uXXXX` escape sequences, so it can only be longer
+        if value.source().len() > self.max_len * 6 + 2 {
+            return None;
+        }
+        let mut decoded = Vec::new();
+        value.decode(&mut decoded).ok()?;
+        let matches = |values: &[String]| values.iter().any(|v| v.as_bytes() == decoded);
+        if matches(&self.true_values) {
+            Some(true)
+        } else if matches(&self.false_values) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for BoolStrings {
+    fn default() -> Self {
+        Self::new(
+            Self::DEFAULT_TRUE_VALUES.iter().copied(),
+            Self::DEFAULT_FALSE_VALUES.iter().copied(),
+        )
+    }
+}
+
+// ---
+
+/// Styling of numeric HTTP status code values by class, e.g. `2xx` or `5xx`.
+///
+/// Fields are selected by their key paths, with nested object keys separated by dots.
+#[derive(Clone, Debug)]
+pub struct HttpStatusStyling {
+    keys: Vec<String>,
+    classes: [Option<Element>; 5],
+}
+
+impl HttpStatusStyling {
+    pub const DEFAULT_KEYS: &[&str] = &["status", "http.status"];
+
+    /// Creates styling for the given key paths with default class elements:
+    /// [`Element::HttpStatusSuccess`] for `2xx`, [`Element::HttpStatusRedirect`] for `3xx`,
+    /// [`Element::HttpStatusClientError`] for `4xx` and [`Element::HttpStatusServerError`] for `5xx`.
+    pub fn new<I, K>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            classes: [
+                None,
+                Some(Element::HttpStatusSuccess),
+                Some(Element::HttpStatusRedirect),
+                Some(Element::HttpStatusClientError),
+                Some(Element::HttpStatusServerError),
+            ],
+        }
+    }
+
+    /// Sets the element used for status codes of the given class, from `1` for `1xx` to `5` for `5xx`.
+    /// Status codes of a class without an element are styled as regular numbers.
+    pub fn with_class(mut self, class: u8, element: Option<Element>) -> Self {
+        if let Some(slot) = (class as usize).checked_sub(1).and_then(|i| self.classes.get_mut(i)) {
+            *slot = element;
+        }
+        self
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    #[inline]
+    fn element(&self, value: &str) -> Option<Element> {
+        match value.parse::<u16>() {
+            Ok(code @ 100..=599) => self.classes[code as usize / 100 - 1],
+            _ => None,
+        }
+    }
+}
+
+impl Default for HttpStatusStyling {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_KEYS.iter().copied())
+    }
+}
+
+// ---
+
+/// Unit of numeric values in [`ElapsedTimeFields`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ElapsedTimeUnit {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    #[default]
+    Nanoseconds,
+}
+
+impl ElapsedTimeUnit {
+    #[inline]
+    fn nanoseconds(&self) -> u128 {
+        match self {
+            Self::Seconds => 1_000_000_000,
+            Self::Milliseconds => 1_000_000,
+            Self::Microseconds => 1_000,
+            Self::Nanoseconds => 1,
+        }
+    }
+}
+
+/// Numeric fields holding time elapsed since a starting point, e.g. process uptime or a monotonic clock reading,
+/// rendered as `HH:MM:SS.mmm` and styled as [`Element::ElapsedTime`].
+///
+/// Fields are selected by their key paths, with nested object keys separated by dots.
+#[derive(Clone, Debug)]
+pub struct ElapsedTimeFields {
+    keys: Vec<String>,
+    unit: ElapsedTimeUnit,
+}
+
+impl ElapsedTimeFields {
+    pub const DEFAULT_KEYS: &[&str] = &["uptime_ns"];
+
+    pub fn new<I, K>(keys: I, unit: ElapsedTimeUnit) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            unit,
+        }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub fn unit(&self) -> ElapsedTimeUnit {
+        self.unit
+    }
+
+    /// Formats `value` as elapsed time if it is a non-negative number.
+    fn format(&self, value: &str) -> Option<String> {
+        let factor = self.unit.nanoseconds();
+        let ns = match value.parse::<u128>() {
+            Ok(v) => v.saturating_mul(factor),
+            Err(_) => match value.parse::<f64>() {
+                Ok(v) if v.is_finite() && v >= 0.0 => (v * factor as f64) as u128,
+                _ => return None,
+            },
+        };
+        let ms = ns / 1_000_000;
+        let s = ms / 1000;
+        Some(format!(
+            "{:02}:{:02}:{:02}.{:03}",
+            s / 3600,
+            s / 60 % 60,
+            s % 60,
+            ms % 1000
+        ))
+    }
+}
+
+impl Default for ElapsedTimeFields {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_KEYS.iter().copied(), ElapsedTimeUnit::default())
+    }
+}
+
+// ---
+
+/// Trace context fields, such as OpenTelemetry `trace_id` and `span_id`.
+///
+/// The fields are pinned before all other fields, their identifiers are optionally shortened
+/// and styled as [`Element::TraceId`]. The trace identifier can be rendered as an OSC 8 hyperlink
+/// to a tracing UI, with `{trace_id}` in the link template replaced by the full identifier.
+#[derive(Clone, Debug)]
+pub struct TraceContext {
+    trace_key: String,
+    span_key: String,
+    short_len: Option<usize>,
+    link_template: Option<String>,
+}
+
+impl TraceContext {
+    pub const DEFAULT_TRACE_KEY: &str = "trace_id";
+    pub const DEFAULT_SPAN_KEY: &str = "span_id";
+    pub const DEFAULT_SHORT_LEN: usize = 8;
+    pub const LINK_PLACEHOLDER: &str = "{trace_id}";
+
+    pub fn new(trace_key: impl Into<String>, span_key: impl Into<String>) -> Self {
+        Self {
+            trace_key: trace_key.into(),
+            span_key: span_key.into(),
+            short_len: Some(Self::DEFAULT_SHORT_LEN),
+            link_template: None,
+        }
+    }
+
+    /// Sets the number of leading characters of identifiers to show, or `None` to show them in full.
+    pub fn with_short_len(self, short_len: Option<usize>) -> Self {
+        Self { short_len, ..self }
+    }
+
+    /// Sets the URL template of trace links, e.g. `https://tracing.example.com/trace/{trace_id}`.
+    pub fn with_trace_link_template(self, link_template: Option<String>) -> Self {
+        Self { link_template, ..self }
+    }
+
+    pub fn trace_key(&self) -> &str {
+        &self.trace_key
+    }
+
+    pub fn span_key(&self) -> &str {
+        &self.span_key
+    }
+
+    #[inline]
+    fn keys(&self) -> [&str; 2] {
+        [&self.trace_key, &self.span_key]
+    }
+
+    /// Formats identifier `id` of the field at `key` if it looks like a trace or span identifier.
+    fn format(&self, buf: &mut Buf, key: &str, id: &str) -> bool {
+        if id.is_empty() || !id.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-') {
+            return false;
+        }
+
+        let link = self
+            .link_template
+            .as_ref()
+            .filter(|_| key == self.trace_key)
+            .map(|template| template.replace(Self::LINK_PLACEHOLDER, id));
+        if let Some(link) = &link {
+            buf.extend(b"\x1b]8;;");
+            buf.extend(link.bytes().filter(|c| !c.is_ascii_control()));
+            buf.extend(b"\x1b\\");
+        }
+        match self.short_len {
+            Some(n) if n < id.len() => buf.extend(&id.as_bytes()[..n]),
+            _ => buf.extend(id.as_bytes()),
+        }
+        if link.is_some() {
+            buf.extend(b"\x1b]8;;\x1b\\");
+        }
+
+        true
+    }
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TRACE_KEY, Self::DEFAULT_SPAN_KEY)
+    }
+}
+
+// ---
+
+/// Error fields, such as `error` or `err`, whose messages are styled as [`Element::ErrorMessage`].
+///
+/// If the value of an error field is an object with a string `message` field, the message is shown
+/// as the value of the error field, followed by the remaining fields of the object with their keys
+/// prefixed by the key of the error field, e.g. `error=failed error.code=42`. The `stack` field is
+/// hidden unless enabled with [`ErrorFields::with_stack`], multi-line stacks are then expanded as usual.
+///
+/// Only top-level fields are matched.
+#[derive(Clone, Debug)]
+pub struct ErrorFields {
+    keys: Vec<String>,
+    stack: bool,
+}
+
+impl ErrorFields {
+    pub const DEFAULT_KEYS: &[&str] = &["error", "err"];
+    pub const MESSAGE_KEY: &str = "message";
+    pub const STACK_KEY: &str = "stack";
+
+    pub fn new<I, K>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            stack: false,
+        }
+    }
+
+    /// Sets whether the `stack` field of error objects is shown.
+    pub fn with_stack(self, stack: bool) -> Self {
+        Self { stack, ..self }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub fn stack(&self) -> bool {
+        self.stack
+    }
+
+    #[inline]
+    fn matches(&self, key: &str) -> bool {
+        self.keys.iter().any(|k| k == key)
+    }
+}
+
+impl Default for ErrorFields {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_KEYS.iter().copied())
+    }
+}
+
+// ---
+
+/// Radix used to render integer values of the fields selected by [`RecordFormatterBuilder::with_number_radix`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Radix {
+    /// Binary with `0b` prefix, e.g. `0b101`.
+    Binary,
+    /// Octal with `0o` prefix, e.g. `0o17`.
+    Octal,
+    /// Hexadecimal with `0x` prefix, e.g. `0x1f`.
+    Hexadecimal,
+}
+
+impl Radix {
+    /// Formats `value` in this radix if it is an integer.
+    fn format(&self, value: &str) -> Option<String> {
+        let value = value.parse::<i128>().ok()?;
+        let sign = if value < 0 { "-" } else { "" };
+        let value = value.unsigned_abs();
+        Some(match self {
+            Self::Binary => format!("{sign}0b{value:b}"),
+            Self::Octal => format!("{sign}0o{value:o}"),
+            Self::Hexadecimal => format!("{sign}0x{value:x}"),
+        })
+    }
+}
+
+// ---
+
+/// Rounding of numbers with a fractional part or an exponent, see [`RecordFormatterBuilder::with_number_rounding`].
+///
+/// Numbers in scientific notation keep it, with the mantissa rounded, e.g. `1.2345e-7` becomes `1.23e-7`
+/// when rounded to 2 decimal places or 3 significant digits. Other numbers are rendered in plain notation
+/// and their integer part is never rounded, e.g. `12345.678` becomes `12346` when rounded to 3 significant digits.
+/// Integers are left as they are.
+///
+/// Rounding is done on the decimal digits of the value, half away from zero, so that no precision is lost
+/// and no binary floating point artifacts appear when many digits are requested.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NumberRounding {
+    /// Rounds to the given number of digits after the decimal point, e.g. `0.123` for 3.
+    DecimalPlaces(usize),
+    /// Rounds to the given number of significant digits, e.g. `0.000123` for 3.
+    SignificantDigits(usize),
+}
+
+impl NumberRounding {
+    /// Formats `value` rounded if it has a fractional part or an exponent.
+    fn format(&self, value: &str) -> Option<String> {
+        let exponential = value.contains(['e', 'E']);
+        if !exponential && !value.contains('.') {
+            return None;
+        }
+        let mut number = Decimal::parse(value)?;
+        let count = |n: usize| i64::try_from(n).unwrap_or(i64::MAX);
+        match (*self, exponential) {
+            (Self::DecimalPlaces(n), true) => {
+                number.round(count(n).saturating_add(1));
+                Some(number.format_exponential(n))
+            }
+            (Self::DecimalPlaces(n), false) => {
+                number.round(number.point.saturating_add(count(n)));
+                Some(number.format_plain(n))
+            }
+            (Self::SignificantDigits(n), true) => {
+                number.round(count(n).max(1));
+                Some(number.format_exponential(n.saturating_sub(1)))
+            }
+            (Self::SignificantDigits(n), false) => {
+                let point = if number.digits.is_empty() { 1 } else { number.point };
+                let decimals = count(n).saturating_sub(point).max(0);
+                number.round(number.point.saturating_add(decimals));
+                Some(number.format_plain(decimals as usize))
+            }
+        }
+    }
+}
+
+/// Decimal number represented by its significant digits, `0.{digits} * 10^{point}`.
+///
+/// Zero has no digits.
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    point: i64,
+}
+
+impl Decimal {
+    /// Parses a number in plain or scientific notation, e.g. `-12.5` or `1.25e-3`.
+    fn parse(value: &str) -> Option<Self> {
+        let (mantissa, exponent) = match value.find(['e', 'E']) {
+            Some(i) => (&value[..i], value[i + 1..].parse::<i64>().ok()?),
+            None => (value, 0),
+        };
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(mantissa) => (true, mantissa),
+            None => (false, mantissa),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+        if (int.is_empty() && frac.is_empty()) || !digits(int) || !digits(frac) {
+            return None;
+        }
+
+        let mut point = i64::try_from(int.len()).ok()?.checked_add(exponent)?;
+        let mut digits = Vec::with_capacity(int.len() + frac.len());
+        for c in int.bytes().chain(frac.bytes()) {
+            if digits.is_empty() && c == b'0' {
+                point = point.checked_sub(1)?;
+            } else {
+                digits.push(c);
+            }
+        }
+        while digits.last() == Some(&b'0') {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            point = 0;
+        }
+
+        Some(Self {
+            negative,
+            digits,
+            point,
+        })
+    }
+
+    /// Rounds the number to the first `keep` significant digits, half away from zero.
+    fn round(&mut self, keep: i64) {
+        let Ok(keep) = usize::try_from(keep) else {
+            self.digits.clear();
+            return;
+        };
+        if keep >= self.digits.len() {
+            return;
+        }
+
+        let up = self.digits[keep] >= b'5';
+        self.digits.truncate(keep);
+        if up {
+            while self.digits.last() == Some(&b'9') {
+                self.digits.pop();
+            }
+            match self.digits.last_mut() {
+                Some(digit) => *digit += 1,
+                None => {
+                    self.digits.push(b'1');
+                    self.point += 1;
+                }
+            }
+        }
+        while self.digits.last() == Some(&b'0') {
+            self.digits.pop();
+        }
+    }
+
+    /// Returns the digit at the given position relative to the decimal point, or zero if it is out of range.
+    fn digit(&self, i: i64) -> u8 {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| self.digits.get(i))
+            .copied()
+            .unwrap_or(b'0')
+    }
+
+    /// Formats the number in plain notation with the given number of decimal places.
+    fn format_plain(&self, decimals: usize) -> String {
+        let mut text = self.sign().to_owned();
+        if self.point <= 0 {
+            text.push('0');
+        }
+        text.extend((0..self.point).map(|i| self.digit(i) as char));
+        if decimals != 0 {
+            text.push('.');
+            text.extend((0..decimals as i64).map(|i| self.digit(self.point + i) as char));
+        }
+        text
+    }
+
+    /// Formats the number in scientific notation with the given number of decimal places in the mantissa.
+    fn format_exponential(&self, decimals: usize) -> String {
+        let mut text = self.sign().to_owned();
+        text.push(self.digit(0) as char);
+        if decimals != 0 {
+            text.push('.');
+            text.extend((1..=decimals as i64).map(|i| self.digit(i) as char));
+        }
+        let exponent = if self.digits.is_empty() { 0 } else { self.point - 1 };
+        text.push('e');
+        text.push_str(itoa::Buffer::new().format(exponent));
+        text
+    }
+
+    /// Returns the sign of the number, omitted for numbers rounded to zero.
+    fn sign(&self) -> &'static str {
+        if self.negative && !self.digits.is_empty() {
+            "-"
+        } else {
+            ""
+        }
+    }
+}
+
+// ---
+
+/// Position of the message relative to the fields of a record.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MessagePosition {
+    /// Message goes first, followed by the fields.
+    #[default]
+    BeforeFields,
+    /// Fields go first, followed by the message.
+    AfterFields,
+}
+
+// ---
+
+/// Error preventing a value from being formatted according to its type.
+///
+/// The formatter recovers from it by rendering the raw value, see [`Element::FieldWarning`].
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error("malformed string: {0}")]
+    String(#[from] string::Error),
+    #[error("malformed nested value: {0}")]
+    Nested(#[from] error::Error),
+}
+
+// ---
+
+/// Coloring of logger names with colors selected from a palette by a stable hash of the name,
+/// so that each logger gets the same color in all records.
+#[derive(Clone, Debug)]
+pub struct LoggerColors {
+    palette: Vec<u8>,
+}
+
+impl LoggerColors {
+    /// Entries of the 256-color palette readable on both dark and light backgrounds.
+    pub const DEFAULT_PALETTE: &[u8] = &[32, 37, 41, 69, 97, 130, 134, 166, 168, 172, 178, 204];
+
+    /// Creates coloring using the given entries of the 256-color palette, their number determines the palette size.
+    pub fn new<I: IntoIterator<Item = u8>>(palette: I) -> Self {
+        Self {
+            palette: palette.into_iter().collect(),
+        }
+    }
+
+    pub fn palette(&self) -> &[u8] {
+        &self.palette
+    }
+
+    /// Returns the color of the logger with the given name.
+    pub fn color(&self, name: &str) -> Option<u8> {
+        if self.palette.is_empty() {
+            return None;
+        }
+        let hash = wyhash::wyhash(name.as_bytes(), 0);
+        Some(self.palette[(hash % self.palette.len() as u64) as usize])
+    }
+}
+
+impl Default for LoggerColors {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PALETTE.iter().copied())
+    }
+}
+
+// ---
+
+/// Position of the time column within the line.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimePosition {
+    /// Time goes first, before the level.
+    #[default]
+    Leading,
+    /// Time goes last, separated from the rest of the line by a space.
+    Trailing,
+    /// Time goes last, aligned to the right edge of a line of the given width, e.g. the terminal width.
+    /// Lines not fitting the width are separated from the time by a space.
+    TrailingAligned(usize),
+}
+
+// ---
+
+/// Rendering of object values of top-level fields.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ObjectStyle {
+    /// Objects are enclosed in braces, e.g. `parent={ child1=1 child2=2 }`.
+    #[default]
+    Braces,
+    /// Objects are enclosed in parentheses following the parent key, e.g. `parent(child1=1 child2=2)`.
+    /// Nested objects are still enclosed in braces.
+    Parens,
+    /// Objects are flattened, e.g. `parent.child1=1 parent.child2=2`, same as [`RecordFormatterBuilder::with_flatten`].
+    Flatten,
+    /// Objects and arrays are rendered in YAML-like block style, one field or item per line,
+    /// with each nesting level indented by the given number of spaces, e.g.
+    /// ```text
+    /// parent:
+    ///   child1: 1
+    ///   child2:
+    ///     - x
+    ///     - y
+    /// ```
+    /// Has no effect if flattening is enabled.
+    Block(usize),
+}
+
+// ---
+
+/// Rendering of the file path of the caller.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CallerPath {
+    /// The path is shown as is.
+    #[default]
+    Full,
+    /// Only the last component of the path is shown, e.g. `main.rs`.
+    Basename,
+    /// Only the given number of trailing components of the path are shown, e.g. `src/main.rs` for 2.
+    LastN(usize),
+}
+
+impl CallerPath {
+    /// Returns the part of `path` to be shown.
+    pub fn apply<'a>(&self, path: &'a str) -> &'a str {
+        let n = match self {
+            Self::Full => return path,
+            Self::Basename => 1,
+            Self::LastN(n) => (*n).max(1),
+        };
+        path.rmatch_indices(['/', '\\'])
+            .nth(n - 1)
+            .map_or(path, |(i, _)| &path[i + 1..])
+    }
+}
+
+// ---
+
+/// Letter case of level labels.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LevelCase {
+    /// All letters are uppercase, e.g. `DBG`.
+    #[default]
+    Upper,
+    /// All letters are lowercase, e.g. `dbg`.
+    Lower,
+    /// The first letter is uppercase and the rest are lowercase, e.g. `Dbg`.
+    Title,
+}
+
+impl LevelCase {
+    /// Appends `label` converted to this case to `buf`.
+    fn apply(&self, buf: &mut Buf, label: &[u8]) {
+        for (i, c) in label.iter().enumerate() {
+            buf.push(match self {
+                Self::Upper => c.to_ascii_uppercase(),
+                Self::Lower => c.to_ascii_lowercase(),
+                Self::Title if i == 0 => c.to_ascii_uppercase(),
+                Self::Title => c.to_ascii_lowercase(),
+            });
+        }
+    }
+}
+
+// ---
+
+/// Numeric severities of levels shown after level labels, e.g. `|ERR(3)|`,
+/// see [`RecordFormatterBuilder::with_level_severities`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LevelSeverities(EnumMap<Level, i64>);
+
+impl LevelSeverities {
+    pub fn new(severities: EnumMap<Level, i64>) -> Self {
+        Self(severities)
+    }
+
+    /// Severities as defined by syslog, with trace sharing the severity of debug.
+    pub fn syslog() -> Self {
+        Self(enum_map::enum_map! {
+            Level::Error => 3,
+            Level::Warning => 4,
+            Level::Info => 6,
+            Level::Debug => 7,
+            Level::Trace => 7,
+        })
+    }
+
+    pub fn get(&self, level: Level) -> i64 {
+        self.0[level]
+    }
+
+    /// Appends the severity of the level enclosed in parentheses to `buf`.
+    fn format(&self, buf: &mut Buf, level: Level) {
+        buf.push(b'(');
+        buf.extend_from_slice(itoa::Buffer::new().format(self.0[level]).as_bytes());
+        buf.push(b')');
+    }
+
+    /// Maximum width of the severity suffix.
+    fn width(&self) -> usize {
+        self.0
+            .values()
+            .map(|&n| itoa::Buffer::new().format(n).len() + 2)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for LevelSeverities {
+    fn default() -> Self {
+        Self::syslog()
+    }
+}
+
+// ---
+
+/// Rendering of the level column.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LevelStyle {
+    /// Level is rendered as text, e.g. `|INF|`.
+    #[default]
+    Text,
+    /// Level is rendered as a block of spaces of the same width styled as [`Element::LevelBlock`],
+    /// which is filled with the color of the level unless styled explicitly by the theme.
+    Block,
+}
+
+// ---
+
+/// Policy of applying [`StyleRule`]s when several of them match the same record.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum StyleRuleMode {
+    /// Only the first matching rule applies.
+    #[default]
+    FirstMatch,
+    /// All matching rules apply in order.
+    All,
+}
+
+/// Rule rendering a marker at the start of the line of each record matching a field condition.
+pub struct StyleRule {
+    filter: model::FieldFilter,
+    marker: String,
+    element: Element,
+}
+
+impl StyleRule {
+    /// Creates a rule with a condition in the syntax of field filters, e.g. `env=prod` or `http.status~=5`.
+    /// The marker is styled as [`Element::Marker`] by default.
+    pub fn new(condition: &str, marker: impl Into<String>) -> error::Result<Self> {
+        Ok(Self {
+            filter: model::FieldFilter::parse(condition)?,
+            marker: marker.into(),
+            element: Element::Marker,
+        })
+    }
+
+    /// Sets the element used to style the marker.
+    pub fn with_element(self, element: Element) -> Self {
+        Self { element, ..self }
+    }
+
+    #[inline]
+    fn matches(&self, rec: &model::Record) -> bool {
+        rec.matches(&self.filter)
+    }
+}
+
+// ---
+
+/// Style overrides of a field selected by its key, see [`RecordFormatterBuilder::with_field_styles`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FieldStyle {
+    /// Element used to style the key instead of [`Element::Key`].
+    pub key: Option<Element>,
+    /// Element used to style scalar values, including those nested in objects and arrays, regardless of their type.
+    pub value: Option<Element>,
+}
+
+// ---
+
+#[derive(Default, Clone)]
+pub struct RecordFormatterBuilder {
+    theme: Option<Arc<Theme>>,
+    raw_fields: bool,
+    ts_formatter: Option<DateTimeFormatter>,
+    ts_zone_suffix: Option<ZoneSuffix>,
+    hide_empty_fields: bool,
+    hide_null_fields: bool,
+    flatten: bool,
+    flatten_arrays: bool,
+    collapse_single_key_objects: bool,
+    array_indices: bool,
+    field_hook: Option<FieldHook>,
+    field_sort: Option<FieldSort>,
+    value_transforms: Arc<Vec<DynValueTransform>>,
+    line_terminator: LineTerminator,
+    wrap_width: Option<usize>,
+    max_line_bytes: Option<usize>,
+    strip_key_prefix: Option<String>,
+    debug_strings: bool,
+    hex_undecodable: bool,
+    expand_embedded_json: bool,
+    null_label: Option<String>,
+    empty_record_placeholder: Option<String>,
+    multiline_block_indent: Option<usize>,
+    field_styles: HashMap<String, FieldStyle>,
+    max_fields: Option<usize>,
+    logger_colors: Option<Arc<LoggerColors>>,
+    boolean_labels: Option<(String, String)>,
+    object_style: ObjectStyle,
+    caller_path: CallerPath,
+    message_width: Option<usize>,
+    expand_table: bool,
+    message_position: MessagePosition,
+    time_position: TimePosition,
+    level_message_positions: EnumMap<Level, Option<MessagePosition>>,
+    dim_fields: bool,
+    secondary_ts_key: Option<String>,
+    secondary_ts_skew: bool,
+    time_shift: chrono::Duration,
+    http_status: Option<Arc<HttpStatusStyling>>,
+    bool_strings: Option<Arc<BoolStrings>>,
+    anonymize: AnonymizeTargets,
+    trace_context: Option<Arc<TraceContext>>,
+    elapsed_time_fields: Option<Arc<ElapsedTimeFields>>,
+    error_fields: Option<Arc<ErrorFields>>,
+    style_rules: Arc<Vec<StyleRule>>,
+    style_rule_mode: StyleRuleMode,
+    gutter: bool,
+    field_diagnostics: bool,
+    level_style: LevelStyle,
+    single_char_level: bool,
+    level_case: LevelCase,
+    level_severities: Option<LevelSeverities>,
+    collapse_whitespace: bool,
+    dedup_message_field: bool,
+    number_radix: Arc<Vec<(String, Radix)>>,
+    compact_arrays: Arc<Vec<String>>,
+    message_fallback_keys: Arc<Vec<String>>,
+    sign_aware_numbers: bool,
+    number_rounding: Option<NumberRounding>,
+    ascii: AsciiMode,
+    always_show_time: bool,
+    always_show_level: bool,
+    fields: Option<Arc<IncludeExcludeKeyFilter>>,
+    predefined_fields: Option<Arc<ExactIncludeExcludeKeyFilter>>,
+    cfg: Option<Formatting>,
+    punctuation: Option<Arc<ResolvedPunctuation>>,
+    message_format: Option<DynMessageFormat>,
+    expansion: Option<Expansion>,
+}
+
+impl RecordFormatterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_raw_fields(self, enabled: bool) -> Self {
+        Self {
+            raw_fields: enabled,
+            ..self
+        }
+    }
+
+    pub fn with_empty_fields_hiding(self, enabled: bool) -> Self {
+        Self {
+            hide_empty_fields: enabled,
+            ..self
+        }
+    }
+
+    pub fn with_null_fields_hiding(self, enabled: bool) -> Self {
+        Self {
+            hide_null_fields: enabled,
+            ..self
+        }
+    }
+
+    pub fn with_flatten(self, flatten: bool) -> Self {
+        Self { flatten, ..self }
+    }
+
+    /// Makes flattening also dive into arrays, producing keys with index notation, e.g. `a.b[0].c=x`.
+    /// Has no effect unless flattening is enabled.
+    pub fn with_flatten_arrays(self, flatten_arrays: bool) -> Self {
+        Self { flatten_arrays, ..self }
+    }
+
+    pub fn with_single_key_objects_collapsing(self, enabled: bool) -> Self {
+        Self {
+            collapse_single_key_objects: enabled,
+            ..self
+        }
+    }
+
+    /// Shows indices of array items as their keys, separating the items by a single space, e.g. `[0=a 1=b 2=c]`.
+    pub fn with_array_indices(self, array_indices: bool) -> Self {
+        Self { array_indices, ..self }
+    }
+
+    pub fn with_field_hook(self, hook: FieldHook) -> Self {
+        Self {
+            field_hook: Some(hook),
+            ..self
+        }
+    }
+
+    /// Orders fields using the given comparator of their keys instead of keeping the source order.
+    /// Takes precedence over sorting of object keys.
+    pub fn with_field_sort(self, field_sort: Option<FieldSort>) -> Self {
+        Self { field_sort, ..self }
+    }
+
+    pub fn with_value_transforms(self, transforms: Vec<DynValueTransform>) -> Self {
+        Self {
+            value_transforms: Arc::new(transforms),
+            ..self
+        }
+    }
+
+    pub fn with_line_terminator(self, line_terminator: LineTerminator) -> Self {
+        Self {
+            line_terminator,
+            ..self
+        }
+    }
+
+    pub fn with_wrap_width(self, wrap_width: Option<usize>) -> Self {
+        Self { wrap_width, ..self }
+    }
+
+    /// Truncates formatted lines longer than the given number of bytes, not counting the line terminator,
+    /// and marks them with the hidden fields indicator.
+    /// Escape sequences and UTF-8 characters are never split.
+    pub fn with_max_line_bytes(self, max_line_bytes: Option<usize>) -> Self {
+        Self { max_line_bytes, ..self }
+    }
+
+    /// Strips the given prefix from displayed field keys, e.g. `ctx.` turns `ctx.user_id` into `user-id`.
+    ///
+    /// The prefix is matched against the full displayed key, including parent keys of flattened objects,
+    /// after the key is prettified, so `ctx_` matches `ctx-`. Field filters still match the original keys.
+    pub fn with_strip_key_prefix(self, strip_key_prefix: Option<String>) -> Self {
+        Self {
+            strip_key_prefix,
+            ..self
+        }
+    }
+
+    /// Shows the message both decoded and as its original source, e.g. `a"b ⟨"a\"b"⟩`, for diagnostics.
+    pub fn with_debug_strings(self, debug_strings: bool) -> Self {
+        Self { debug_strings, ..self }
+    }
+
+    /// Renders string values of fields that cannot be decoded to valid UTF-8, e.g. containing lone surrogates
+    /// in escape sequences, as a hex dump of their bytes, e.g. `<hex:41eda080>`, instead of their source.
+    /// Lone surrogates are encoded the same way as valid code points.
+    pub fn with_hex_undecodable(self, hex_undecodable: bool) -> Self {
+        Self {
+            hex_undecodable,
+            ..self
+        }
+    }
+
+    /// Renders string values and messages containing a JSON object or array as structured values,
+    /// e.g. a message `"{\"a\":1}"` as `{ a=1 }`.
+    /// JSON embedded into strings of already embedded JSON is expanded up to [`MAX_EMBEDDED_JSON_DEPTH`] levels deep.
+    pub fn with_expand_embedded_json(self, expand_embedded_json: bool) -> Self {
+        Self {
+            expand_embedded_json,
+            ..self
+        }
+    }
+
+    /// Sets the text displayed for null values, `null` by default.
+    /// An empty label displays nothing after the key-value separator.
+    pub fn with_null_label(self, null_label: Option<String>) -> Self {
+        Self { null_label, ..self }
+    }
+
+    /// Sets the text displayed for records having neither a message nor visible fields, e.g. `(no message)`.
+    pub fn with_empty_record_placeholder(self, empty_record_placeholder: Option<String>) -> Self {
+        Self {
+            empty_record_placeholder,
+            ..self
+        }
+    }
+
+    /// Renders multiline string values of fields as blocks of lines under their keys, indented by the given number of spaces,
+    /// instead of quoting or expanding them.
+    pub fn with_multiline_block_indent(self, multiline_block_indent: Option<usize>) -> Self {
+        Self {
+            multiline_block_indent,
+            ..self
+        }
+    }
+
+    /// Colors logger names with colors selected by a stable hash of the name.
+    pub fn with_logger_colors(self, logger_colors: Option<LoggerColors>) -> Self {
+        Self {
+            logger_colors: logger_colors.map(Arc::new),
+            ..self
+        }
+    }
+
+    /// Limits the number of visible top-level fields per record.
+    /// Fields over the limit are replaced by the hidden fields indicator followed by their number, e.g. `...+7`.
+    pub fn with_max_fields(self, max_fields: Option<usize>) -> Self {
+        Self { max_fields, ..self }
+    }
+
+    /// Overrides styles of the keys and values of top-level fields with the given keys.
+    /// Keys match the displayed keys, i.e. `request_id` matches `request-id` if keys are prettified.
+    pub fn with_field_styles(self, field_styles: HashMap<String, FieldStyle>) -> Self {
+        Self { field_styles, ..self }
+    }
+
+    /// Sets the texts displayed for `true` and `false` values, e.g. `T` and `F`.
+    /// Labels of any width are allowed, including single characters rendering booleans distinguishable by color only.
+    pub fn with_boolean_labels(self, boolean_labels: Option<(String, String)>) -> Self {
+        Self { boolean_labels, ..self }
+    }
+
+    /// Sets the position of the time column, at the start of the line by default.
+    pub fn with_time_position(self, time_position: TimePosition) -> Self {
+        Self { time_position, ..self }
+    }
+
+    /// Sets the position of the message for records of all levels unless overridden by [`Self::with_level_message_position`].
+    pub fn with_message_position(self, message_position: MessagePosition) -> Self {
+        Self {
+            message_position,
+            ..self
         }
     }
-}
 
-impl From<settings::ExpansionMode> for Expansion {
-    #[inline(always)]
-    fn from(mode: settings::ExpansionMode) -> Self {
-        Self { mode }
+    /// Overrides the position of the message for records of the given level.
+    pub fn with_level_message_position(mut self, level: Level, message_position: Option<MessagePosition>) -> Self {
+        self.level_message_positions[level] = message_position;
+        self
     }
-}
-
-// ---
 
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
-pub enum MultilineExpansion {
-    #[default]
-    Standard,
-    Disabled,
-    Inline,
-}
+    pub fn with_object_style(self, object_style: ObjectStyle) -> Self {
+        Self { object_style, ..self }
+    }
 
-// ---
+    /// Pads the message with spaces to at least `width` columns so that fields of consecutive records are aligned.
+    /// Longer messages are left as is.
+    pub fn with_message_width(self, message_width: Option<usize>) -> Self {
+        Self { message_width, ..self }
+    }
 
-#[derive(Clone, Debug)]
-pub struct ExpansionThresholds {}
+    pub fn with_caller_path(self, caller_path: CallerPath) -> Self {
+        Self { caller_path, ..self }
+    }
 
-// ---
+    /// Shows each top-level field on a separate line below the first line of the record,
+    /// as a two-column table with keys padded to the length of the longest key.
+    pub fn with_expand_table(self, expand_table: bool) -> Self {
+        Self { expand_table, ..self }
+    }
 
-#[derive(Clone, Debug)]
-pub struct ExpansionProfile {
-    pub multiline: MultilineExpansion,
-    pub thresholds: ExpansionThresholds,
-    pub expand_all: bool,
-}
+    /// Dims fields over their theme styles, leaving time, level, message and caller prominent.
+    pub fn with_dim_fields(self, dim_fields: bool) -> Self {
+        Self { dim_fields, ..self }
+    }
 
-impl ExpansionProfile {
-    pub const NEVER: Self = Self {
-        multiline: MultilineExpansion::Disabled,
-        thresholds: ExpansionThresholds {},
-        expand_all: false,
-    };
+    /// Shows the timestamp found in the given top-level field as an additional time column next to the primary one,
+    /// e.g. to compare the time an event happened with the time it was received.
+    pub fn with_secondary_timestamp_key(self, key: Option<String>) -> Self {
+        Self {
+            secondary_ts_key: key,
+            ..self
+        }
+    }
 
-    pub const ALWAYS: Self = Self {
-        multiline: MultilineExpansion::Standard,
-        thresholds: ExpansionThresholds {},
-        expand_all: true,
-    };
+    /// Shows the difference of the secondary timestamp from the primary one in a column
+    /// following the secondary time column, e.g. `+1.3s`, styled as [`Element::Delta`].
+    /// The column is left blank if either of the timestamps is missing or invalid.
+    /// Has no effect unless the secondary timestamp key is set.
+    pub fn with_secondary_timestamp_skew(self, secondary_ts_skew: bool) -> Self {
+        Self {
+            secondary_ts_skew,
+            ..self
+        }
+    }
 
-    pub const INLINE: Self = Self {
-        multiline: MultilineExpansion::Inline,
-        thresholds: ExpansionThresholds {},
-        expand_all: false,
-    };
+    /// Renders string values matching one of the given sets as booleans, using boolean styles and labels.
+    pub fn with_coerce_bool_strings(self, strings: Option<BoolStrings>) -> Self {
+        Self {
+            bool_strings: strings.map(Arc::new),
+            ..self
+        }
+    }
 
-    pub const AUTO: Self = Self {
-        multiline: MultilineExpansion::Standard,
-        thresholds: ExpansionThresholds {},
-        expand_all: false,
-    };
-}
+    /// Masks personal data of the given kinds in string values, e.g. `192.168.1.*` or `*@example.com`.
+    pub fn with_anonymize(self, anonymize: AnonymizeTargets) -> Self {
+        Self { anonymize, ..self }
+    }
 
-impl Default for &ExpansionProfile {
-    fn default() -> Self {
-        &ExpansionProfile::NEVER
+    /// Pins trace context fields before other fields and renders them in a compact style.
+    pub fn with_trace_context(self, trace_context: Option<TraceContext>) -> Self {
+        Self {
+            trace_context: trace_context.map(Arc::new),
+            ..self
+        }
     }
-}
 
-// ---
+    /// Styles numeric HTTP status codes in the selected fields according to their class.
+    pub fn with_http_status_styling(self, styling: Option<HttpStatusStyling>) -> Self {
+        Self {
+            http_status: styling.map(Arc::new),
+            ..self
+        }
+    }
 
-pub trait RecordWithSourceFormatter {
-    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource);
-}
+    /// Renders numeric values of the selected fields as elapsed time, e.g. `01:02:03.004`.
+    pub fn with_elapsed_time_fields(self, fields: Option<ElapsedTimeFields>) -> Self {
+        Self {
+            elapsed_time_fields: fields.map(Arc::new),
+            ..self
+        }
+    }
 
-pub struct RawRecordFormatter {
-    pub delimiter: String,
-}
+    /// Styles messages of error fields and shows the messages of error objects as their values, see [`ErrorFields`].
+    pub fn with_error_fields(self, fields: Option<ErrorFields>) -> Self {
+        Self {
+            error_fields: fields.map(Arc::new),
+            ..self
+        }
+    }
 
-impl RecordWithSourceFormatter for RawRecordFormatter {
-    #[inline(always)]
-    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
-        let mut first = true;
-        for line in Newline.into_searcher().split(rec.source) {
-            if !first {
-                buf.extend_from_slice(self.delimiter.as_bytes());
-                buf.extend_from_within(prefix.clone());
-            }
-            first = false;
-            buf.extend_from_slice(line);
+    /// Renders markers of the rules matching each record at the start of its line.
+    /// Rules are evaluated in order, and `mode` defines whether only the first or all matching rules apply.
+    pub fn with_style_rules(self, rules: Vec<StyleRule>, mode: StyleRuleMode) -> Self {
+        Self {
+            style_rules: Arc::new(rules),
+            style_rule_mode: mode,
+            ..self
         }
     }
-}
 
-impl<T: RecordWithSourceFormatter + ?Sized> RecordWithSourceFormatter for &T {
-    #[inline(always)]
-    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
-        (**self).format_record(buf, prefix, rec)
+    /// Renders a single-character severity marker at the start of each line, e.g. `!` for errors or `*` for warnings.
+    /// Records without a level get a blank marker, so the column keeps its width.
+    pub fn with_gutter(self, gutter: bool) -> Self {
+        Self { gutter, ..self }
     }
-}
 
-impl<T: RecordWithSourceFormatter + ?Sized> RecordWithSourceFormatter for Arc<T> {
-    #[inline(always)]
-    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
-        (**self).format_record(buf, prefix, rec)
+    /// Marks string values that cannot be decoded, e.g. because of invalid escape sequences, with a warning indicator.
+    /// Such values are always shown as they appear in the source.
+    pub fn with_field_diagnostics(self, field_diagnostics: bool) -> Self {
+        Self {
+            field_diagnostics,
+            ..self
+        }
     }
-}
 
-// ---
+    pub fn with_level_style(self, level_style: LevelStyle) -> Self {
+        Self { level_style, ..self }
+    }
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
-pub struct NoOpRecordWithSourceFormatter;
+    /// Shows levels as a single character, e.g. `E` instead of `|ERR|`, without level separators.
+    pub fn with_single_char_level(self, single_char_level: bool) -> Self {
+        Self {
+            single_char_level,
+            ..self
+        }
+    }
 
-impl RecordWithSourceFormatter for NoOpRecordWithSourceFormatter {
-    #[inline(always)]
-    fn format_record(&self, _: &mut Buf, _: Range<usize>, _: model::RecordWithSource) {}
-}
+    /// Shows the numeric severity of the level after the level label, e.g. `|INF(6)|`.
+    pub fn with_level_severities(self, level_severities: Option<LevelSeverities>) -> Self {
+        Self {
+            level_severities,
+            ..self
+        }
+    }
 
-// ---
+    pub fn with_level_case(self, level_case: LevelCase) -> Self {
+        Self { level_case, ..self }
+    }
 
-pub type DynRecordWithSourceFormatter = Arc<dyn RecordWithSourceFormatter + Send + Sync>;
+    /// Replaces runs of whitespace characters in messages, including line breaks, with a single space.
+    pub fn with_collapse_whitespace(self, collapse_whitespace: bool) -> Self {
+        Self {
+            collapse_whitespace,
+            ..self
+        }
+    }
 
-// ---
+    /// Hides string fields whose decoded value equals the decoded message, e.g. `event` duplicating `msg`.
+    pub fn with_dedup_message_field(self, dedup_message_field: bool) -> Self {
+        Self {
+            dedup_message_field,
+            ..self
+        }
+    }
 
-#[derive(Default, Clone)]
-pub struct RecordFormatterBuilder {
-    theme: Option<Arc<Theme>>,
-    raw_fields: bool,
-    ts_formatter: Option<DateTimeFormatter>,
-    hide_empty_fields: bool,
-    flatten: bool,
-    ascii: AsciiMode,
-    always_show_time: bool,
-    always_show_level: bool,
-    fields: Option<Arc<IncludeExcludeKeyFilter>>,
-    predefined_fields: Option<Arc<ExactIncludeExcludeKeyFilter>>,
-    cfg: Option<Formatting>,
-    punctuation: Option<Arc<ResolvedPunctuation>>,
-    message_format: Option<DynMessageFormat>,
-    expansion: Option<Expansion>,
-}
+    /// Uses the value of the first present top-level field among `keys` as the message if the record has none.
+    /// The field used as the message is not shown among the other fields.
+    pub fn with_message_fallback_keys<I, K>(self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        Self {
+            message_fallback_keys: Arc::new(keys.into_iter().map(Into::into).collect()),
+            ..self
+        }
+    }
 
-impl RecordFormatterBuilder {
-    pub fn new() -> Self {
-        Self::default()
+    /// Renders non-empty arrays of scalar values in the given fields without brackets, e.g. `tags=a, b, c`.
+    /// Arrays containing objects or arrays keep their brackets.
+    pub fn with_compact_arrays<I, K>(self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        Self {
+            compact_arrays: Arc::new(keys.into_iter().map(Into::into).collect()),
+            ..self
+        }
     }
 
-    pub fn with_raw_fields(self, enabled: bool) -> Self {
+    /// Renders integer values of the selected fields in the given radix, e.g. `flags=0x1f`.
+    /// Fields are selected by their key paths, with nested object keys separated by dots.
+    pub fn with_number_radix<I, K>(self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = (K, Radix)>,
+        K: Into<String>,
+    {
         Self {
-            raw_fields: enabled,
+            number_radix: Arc::new(fields.into_iter().map(|(key, radix)| (key.into(), radix)).collect()),
             ..self
         }
     }
 
-    pub fn with_empty_fields_hiding(self, enabled: bool) -> Self {
+    /// Rounds numbers with a fractional part or an exponent before rendering, integers are left as they are.
+    pub fn with_number_rounding(self, number_rounding: Option<NumberRounding>) -> Self {
         Self {
-            hide_empty_fields: enabled,
+            number_rounding,
             ..self
         }
     }
 
-    pub fn with_flatten(self, flatten: bool) -> Self {
-        Self { flatten, ..self }
+    /// Styles numbers according to their sign as [`Element::NumberPositive`], [`Element::NumberNegative`]
+    /// or [`Element::NumberZero`], which inherit the style of [`Element::Number`] unless styled explicitly.
+    pub fn with_sign_aware_numbers(self, sign_aware_numbers: bool) -> Self {
+        Self {
+            sign_aware_numbers,
+            ..self
+        }
     }
 
     pub fn with_ascii(self, ascii: AsciiMode) -> Self {
@@ -248,6 +1969,12 @@ impl RecordFormatterBuilder {
         }
     }
 
+    /// Shifts timestamps of records by the given offset before formatting them,
+    /// e.g. to correlate logs from hosts with clock skew. The offset may be negative.
+    pub fn with_time_shift(self, time_shift: chrono::Duration) -> Self {
+        Self { time_shift, ..self }
+    }
+
     pub fn with_theme(self, value: Arc<Theme>) -> Self {
         Self {
             theme: Some(value),
@@ -262,6 +1989,14 @@ impl RecordFormatterBuilder {
         }
     }
 
+    /// Appends the time zone offset or abbreviation to the time column, e.g. `+02:00` or `UTC`.
+    pub fn with_timestamp_zone_suffix(self, suffix: Option<ZoneSuffix>) -> Self {
+        Self {
+            ts_zone_suffix: suffix,
+            ..self
+        }
+    }
+
     pub fn with_options(self, value: Formatting) -> Self {
         Self {
             cfg: Some(value),
@@ -310,18 +2045,99 @@ impl RecordFormatterBuilder {
             .punctuation
             .unwrap_or_else(|| cfg.punctuation.resolve(self.ascii).into());
         let ts_formatter = self.ts_formatter.unwrap_or_default();
+        let ts_formatter = match self.ts_zone_suffix {
+            Some(suffix) => ts_formatter.with_zone_suffix(suffix),
+            None => ts_formatter,
+        };
         let ts_width = ts_formatter.max_width();
         let ts_stub = Self::make_ts_stub(&ts_formatter, ts_width.chars);
+        let prettify_field_keys = !self.raw_fields && cfg.prettify_field_keys.unwrap_or(true);
+        let strip_key_prefix = self.strip_key_prefix.filter(|prefix| !prefix.is_empty()).map(|prefix| {
+            if prettify_field_keys {
+                let mut buf = Vec::new();
+                prefix.key_prettify(&mut buf);
+                buf
+            } else {
+                prefix.into_bytes()
+            }
+        });
 
         RecordFormatter {
             theme: self.theme.unwrap_or_default(),
             unescape_fields: !self.raw_fields,
-            prettify_field_keys: !self.raw_fields && cfg.prettify_field_keys.unwrap_or(true),
+            prettify_field_keys,
+            sort_object_keys: cfg.sort_object_keys.unwrap_or(false),
             ts_formatter,
             ts_width,
             ts_stub,
             hide_empty_fields: self.hide_empty_fields,
-            flatten: self.flatten,
+            hide_null_fields: self.hide_null_fields,
+            flatten: self.flatten || self.object_style == ObjectStyle::Flatten,
+            flatten_arrays: self.flatten_arrays,
+            collapse_single_key_objects: self.collapse_single_key_objects,
+            array_indices: self.array_indices,
+            field_hook: self.field_hook,
+            field_sort: self.field_sort,
+            value_transforms: self.value_transforms,
+            line_terminator: self.line_terminator,
+            wrap_width: self.wrap_width,
+            max_line_bytes: self.max_line_bytes,
+            strip_key_prefix,
+            debug_strings: self.debug_strings,
+            hex_undecodable: self.hex_undecodable,
+            expand_embedded_json: self.expand_embedded_json,
+            null_label: self.null_label.unwrap_or_else(|| "null".into()),
+            empty_record_placeholder: self.empty_record_placeholder,
+            multiline_block_indent: self.multiline_block_indent,
+            max_fields: self.max_fields,
+            logger_colors: self.logger_colors,
+            field_styles: Arc::new(
+                self.field_styles
+                    .into_iter()
+                    .map(|(key, style)| {
+                        if prettify_field_keys {
+                            let mut buf = Vec::new();
+                            key.key_prettify(&mut buf);
+                            (buf, style)
+                        } else {
+                            (key.into_bytes(), style)
+                        }
+                    })
+                    .collect(),
+            ),
+            boolean_labels: self.boolean_labels.unwrap_or_else(|| ("true".into(), "false".into())),
+            object_style: self.object_style,
+            caller_path: self.caller_path,
+            message_width: self.message_width,
+            expand_table: self.expand_table,
+            message_position: self.message_position,
+            time_position: self.time_position,
+            level_message_positions: self.level_message_positions,
+            dim_fields: self.dim_fields,
+            secondary_ts_key: self.secondary_ts_key,
+            secondary_ts_skew: self.secondary_ts_skew,
+            time_shift: self.time_shift,
+            http_status: self.http_status,
+            bool_strings: self.bool_strings,
+            anonymize: self.anonymize,
+            trace_context: self.trace_context,
+            elapsed_time_fields: self.elapsed_time_fields,
+            error_fields: self.error_fields,
+            style_rules: self.style_rules,
+            style_rule_mode: self.style_rule_mode,
+            gutter: self.gutter,
+            field_diagnostics: self.field_diagnostics,
+            level_style: self.level_style,
+            single_char_level: self.single_char_level,
+            level_case: self.level_case,
+            level_severities: self.level_severities,
+            collapse_whitespace: self.collapse_whitespace,
+            dedup_message_field: self.dedup_message_field,
+            number_radix: self.number_radix,
+            compact_arrays: self.compact_arrays,
+            message_fallback_keys: self.message_fallback_keys,
+            sign_aware_numbers: self.sign_aware_numbers,
+            number_rounding: self.number_rounding,
             always_show_time: self.always_show_time,
             always_show_level: self.always_show_level,
             fields: self.fields.unwrap_or_default(),
@@ -329,8 +2145,17 @@ impl RecordFormatterBuilder {
             message_format: self
                 .message_format
                 .unwrap_or_else(|| DynMessageFormat::new(&cfg, self.ascii)),
+            message_template: cfg
+                .message
+                .template
+                .as_deref()
+                .map(|template| template.split_ascii_whitespace().map(String::from).collect()),
             punctuation,
-            expansion: self.expansion.unwrap_or_default(),
+            expansion: match self.expand_table {
+                true => self.expansion.unwrap_or_default().with_mode(ExpansionMode::Always),
+                false => self.expansion.unwrap_or_default(),
+            },
+            column_widths: ColumnWidths::default(),
         }
     }
 
@@ -379,41 +2204,275 @@ impl Sample for RecordFormatterBuilder {
     }
 }
 
+/// Minimum widths of variable-width columns, used to align output across a batch of records.
+#[derive(Clone, Copy, Debug, Default)]
+struct ColumnWidths {
+    logger: usize,
+    level: usize,
+}
+
+#[derive(Clone)]
 pub struct RecordFormatter {
     theme: Arc<Theme>,
     unescape_fields: bool,
     prettify_field_keys: bool,
+    sort_object_keys: bool,
     ts_formatter: DateTimeFormatter,
     ts_width: TextWidth,
     ts_stub: String,
     hide_empty_fields: bool,
+    hide_null_fields: bool,
     flatten: bool,
+    flatten_arrays: bool,
+    collapse_single_key_objects: bool,
+    array_indices: bool,
+    field_hook: Option<FieldHook>,
+    field_sort: Option<FieldSort>,
+    value_transforms: Arc<Vec<DynValueTransform>>,
+    line_terminator: LineTerminator,
+    wrap_width: Option<usize>,
+    max_line_bytes: Option<usize>,
+    strip_key_prefix: Option<Vec<u8>>,
+    debug_strings: bool,
+    hex_undecodable: bool,
+    expand_embedded_json: bool,
+    null_label: String,
+    empty_record_placeholder: Option<String>,
+    multiline_block_indent: Option<usize>,
+    field_styles: Arc<HashMap<Vec<u8>, FieldStyle>>,
+    max_fields: Option<usize>,
+    logger_colors: Option<Arc<LoggerColors>>,
+    boolean_labels: (String, String),
+    object_style: ObjectStyle,
+    caller_path: CallerPath,
+    message_width: Option<usize>,
+    expand_table: bool,
+    message_position: MessagePosition,
+    time_position: TimePosition,
+    level_message_positions: EnumMap<Level, Option<MessagePosition>>,
+    dim_fields: bool,
+    secondary_ts_key: Option<String>,
+    secondary_ts_skew: bool,
+    time_shift: chrono::Duration,
+    http_status: Option<Arc<HttpStatusStyling>>,
+    bool_strings: Option<Arc<BoolStrings>>,
+    anonymize: AnonymizeTargets,
+    trace_context: Option<Arc<TraceContext>>,
+    elapsed_time_fields: Option<Arc<ElapsedTimeFields>>,
+    error_fields: Option<Arc<ErrorFields>>,
+    style_rules: Arc<Vec<StyleRule>>,
+    style_rule_mode: StyleRuleMode,
+    gutter: bool,
+    field_diagnostics: bool,
+    level_style: LevelStyle,
+    single_char_level: bool,
+    level_case: LevelCase,
+    level_severities: Option<LevelSeverities>,
+    collapse_whitespace: bool,
+    dedup_message_field: bool,
+    number_radix: Arc<Vec<(String, Radix)>>,
+    compact_arrays: Arc<Vec<String>>,
+    message_fallback_keys: Arc<Vec<String>>,
+    sign_aware_numbers: bool,
+    number_rounding: Option<NumberRounding>,
     always_show_time: bool,
     always_show_level: bool,
     fields: Arc<IncludeExcludeKeyFilter>,
     predefined_fields: Arc<ExactIncludeExcludeKeyFilter>,
     message_format: DynMessageFormat,
+    message_template: Option<Vec<String>>,
     punctuation: Arc<ResolvedPunctuation>,
     expansion: Expansion,
+    column_widths: ColumnWidths,
 }
 
 impl RecordFormatter {
+    /// Formats the record into a newly allocated buffer.
+    pub fn format_to_vec(&self, rec: &model::Record) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.format_record(&mut buf, 0..0, rec);
+        buf
+    }
+
+    /// Formats the record into a newly allocated string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the formatted output is not valid UTF-8.
+    /// All punctuation, theme sequences and record values are valid UTF-8 strings,
+    /// so this can only happen if the formatter is misconfigured with invalid byte sequences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hl::{Parser, ParserSettings, RawRecord, formatting::RecordFormatterBuilder};
+    ///
+    /// let formatter = RecordFormatterBuilder::new().build();
+    /// let parser = Parser::new(ParserSettings::default());
+    ///
+    /// let raw = RawRecord::parser().parse(br#"{"msg":"hello","status":200}"#).next().unwrap().unwrap();
+    /// let record = parser.parse(&raw.record);
+    ///
+    /// assert_eq!(formatter.format_to_string(&record).unwrap(), "hello :: status=200");
+    /// ```
+    pub fn format_to_string(&self, rec: &model::Record) -> Result<String, FromUtf8Error> {
+        String::from_utf8(self.format_to_vec(rec))
+    }
+
+    /// Lazily formats each record produced by `iter` into a newly allocated buffer.
+    ///
+    /// Records can be given either by value or by reference.
+    pub fn format_iter<'r, I, R>(&self, iter: I) -> impl Iterator<Item = Buf>
+    where
+        I: IntoIterator<Item = R>,
+        R: Borrow<model::Record<'r>>,
+    {
+        iter.into_iter().map(|rec| self.format_to_vec(rec.borrow()))
+    }
+
+    /// Formats the whole record into a buffer and then writes it to `w` in chunks of at most [`WRITE_CHUNK_SIZE`] bytes.
+    ///
+    /// The buffer is reused by subsequent calls on the same thread and is shrunk back after formatting
+    /// unusually large records, so no allocation is needed per record in the common case.
+    /// Only the size of the writes is bounded, not the memory used for a single huge record.
+    /// Nested calls on the same thread, e.g. from a field hook, use a temporary buffer instead.
+    pub fn write_record_buffered<W: io::Write>(&self, w: &mut W, rec: &model::Record) -> io::Result<()> {
+        let mut write = |buf: &mut Buf| {
+            buf.clear();
+            self.format_record(buf, 0..0, rec);
+            buf.chunks(WRITE_CHUNK_SIZE).try_for_each(|chunk| w.write_all(chunk))
+        };
+        WRITE_BUF.with(|cell| match cell.try_borrow_mut() {
+            Ok(mut buf) => {
+                let result = write(&mut buf);
+                buf.clear();
+                buf.shrink_to(WRITE_CHUNK_SIZE);
+                result
+            }
+            Err(_) => write(&mut Vec::new()),
+        })
+    }
+
+    /// Renders a legend to be shown once before the records.
+    ///
+    /// The first line names the columns, each styled as the column itself. The second line shows
+    /// the label of each level styled according to the level, followed by the name of the level.
+    /// There is no line terminator after the last line.
+    pub fn format_header(&self) -> Buf {
+        let mut buf = Vec::new();
+        self.theme.apply(&mut buf, &None, |s| {
+            s.element(Element::Time, |s| {
+                s.batch(|buf| aligned_left(buf, self.ts_width.chars, b' ', |mut buf| buf.extend_from_slice(b"time")))
+            });
+            s.space();
+            s.element(Element::Level, |s| {
+                s.batch(|buf| aligned_left(buf, self.level_width(), b' ', |mut buf| buf.extend_from_slice(b"level")))
+            });
+            s.space();
+            s.element(Element::Logger, |s| {
+                s.element(Element::LoggerInner, |s| {
+                    s.batch(|buf| buf.extend_from_slice(b"logger"))
+                });
+                s.batch(|buf| buf.extend_from_slice(self.punctuation.logger_name_separator.as_bytes()));
+            });
+            s.space();
+            s.element(Element::Message, |s| s.batch(|buf| buf.extend_from_slice(b"message")));
+            s.space();
+            s.element(Element::Key, |s| s.batch(|buf| buf.extend_from_slice(b"key")));
+            s.element(Element::Field, |s| {
+                s.batch(|buf| buf.extend_from_slice(self.punctuation.string_key_value_separator.as_bytes()))
+            });
+            s.element(Element::String, |s| s.batch(|buf| buf.extend_from_slice(b"value")));
+        });
+        buf.push(b'\n');
+        for (i, level) in Level::iter().enumerate() {
+            if i != 0 {
+                buf.extend_from_slice(b"  ");
+            }
+            self.theme.apply(&mut buf, &Some(level), |s| {
+                self.format_level_label(s, level_label(level), Some(level));
+                s.space();
+                s.element(Element::Message, |s| {
+                    s.batch(|buf| buf.extend_from_slice(level.as_ref().as_bytes()))
+                });
+            });
+        }
+        buf
+    }
+
+    /// Formats a batch of records into separate buffers, padding the logger and level columns
+    /// to the widest values found in the batch so that the following columns are aligned.
+    pub fn format_aligned_batch(&self, recs: &[model::Record]) -> Vec<Buf> {
+        let logger = recs
+            .iter()
+            .filter_map(|rec| rec.logger)
+            .map(|logger| logger.chars().count())
+            .max()
+            .unwrap_or(0);
+        let level = if recs.iter().any(|rec| rec.level.is_some()) {
+            self.level_width()
+        } else {
+            0
+        };
+
+        let rf = Self {
+            column_widths: ColumnWidths { logger, level },
+            ..self.clone()
+        };
+        recs.iter().map(|rec| rf.format_to_vec(rec)).collect()
+    }
+
     pub fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: &model::Record) {
+        self.format_record_with_skip(buf, prefix, rec, None)
+    }
+
+    /// Formats the record, omitting the fields for which `skip` returns true
+    /// and showing the hidden fields indicator in their place.
+    pub(crate) fn format_record_with_skip(
+        &self,
+        buf: &mut Buf,
+        prefix: Range<usize>,
+        rec: &model::Record,
+        skip: Option<&FieldSkip>,
+    ) {
+        let begin = buf.len();
         let mut fs = FormattingStateWithRec {
             rec,
             fs: FormattingState {
                 flatten: self.flatten && self.unescape_fields,
                 expansion: self.expansion.profile(),
                 prefix,
+                table_key_width: match self.expand_table {
+                    true => self.table_key_width(rec, skip),
+                    false => 0,
+                },
                 ..Default::default()
             },
         };
 
+        let mut logger_separator = None;
+        let mut has_content = false;
+        let mut trailing_ts = None;
+
         self.theme.apply(buf, &rec.level, |s| {
+            //
+            // gutter
+            //
+            if self.gutter {
+                self.format_gutter(rec, s);
+            }
+
+            //
+            // markers
+            //
+            self.format_markers(rec, s);
+
             //
             // time
             //
-            if fs.transact(s, |fs, s| self.format_timestamp(rec, fs, s)).is_err() {
+            if self.time_position != TimePosition::Leading {
+                trailing_ts = self.format_trailing_timestamp(rec, &mut fs);
+            } else if fs.transact(s, |fs, s| self.format_timestamp(rec, fs, s)).is_err() {
                 if let Some(ts) = &rec.ts {
                     fs.extra_fields
                         .push(("ts", RawValue::String(EncodedString::raw(ts.raw()))))
@@ -423,22 +2482,21 @@ impl RecordFormatter {
                     self.format_timestamp_stub(&mut fs, s);
                 }
             }
+            if let Some(key) = &self.secondary_ts_key {
+                self.format_secondary_timestamp(rec, key, &mut fs, s);
+            }
 
             //
             // level
             //
-            let level = match rec.level {
-                Some(Level::Error) => Some(LEVEL_ERROR.as_bytes()),
-                Some(Level::Warning) => Some(LEVEL_WARNING.as_bytes()),
-                Some(Level::Info) => Some(LEVEL_INFO.as_bytes()),
-                Some(Level::Debug) => Some(LEVEL_DEBUG.as_bytes()),
-                Some(Level::Trace) => Some(LEVEL_TRACE.as_bytes()),
-                None => None,
-            };
+            let level = rec.level.map(level_label);
             let level = level.or(self.always_show_level.then_some(LEVEL_UNKNOWN.as_bytes()));
             if let Some(level) = level {
                 fs.has_level = true;
-                self.format_level(s, &mut fs, level);
+                self.format_level(s, &mut fs, level, rec.level);
+            } else if self.column_widths.level != 0 {
+                fs.add_element(|| s.space());
+                s.batch(|buf| buf.extend(std::iter::repeat_n(b' ', self.column_widths.level)));
             }
 
             //
@@ -447,23 +2505,62 @@ impl RecordFormatter {
             if let Some(logger) = rec.logger {
                 fs.add_element(|| s.batch(|buf| buf.push(b' ')));
                 s.element(Element::Logger, |s| {
-                    s.element(Element::LoggerInner, |s| {
-                        s.batch(|buf| buf.extend_from_slice(logger.as_bytes()))
+                    let color = self.logger_colors.as_ref().and_then(|lc| lc.color(logger));
+                    s.recolored(color, |s| {
+                        s.element(Element::LoggerInner, |s| {
+                            s.batch(|buf| buf.extend_from_slice(logger.as_bytes()))
+                        })
                     });
-                    s.batch(|buf| buf.extend_from_slice(self.punctuation.logger_name_separator.as_bytes()));
+                    logger_separator = Some(s.batch(|buf| {
+                        let begin = buf.len();
+                        buf.extend_from_slice(self.punctuation.logger_name_separator.as_bytes());
+                        begin..buf.len()
+                    }));
                     fs.first_line_used = true;
                 });
+                let padding = self.column_widths.logger.saturating_sub(logger.chars().count());
+                if padding != 0 {
+                    s.batch(|buf| buf.extend(std::iter::repeat_n(b' ', padding)));
+                }
+            } else if self.column_widths.logger != 0 {
+                fs.add_element(|| s.space());
+                let width =
+                    self.column_widths.logger + visible_width(self.punctuation.logger_name_separator.as_bytes());
+                s.batch(|buf| buf.extend(std::iter::repeat_n(b' ', width)));
             }
 
             //
             // message text
             //
-            if let Some(value) = &rec.message {
+            let mut message_key = None;
+            let message = rec.message.or_else(|| {
+                self.message_fallback_keys.iter().find_map(|key| {
+                    let (k, v) = rec.fields().find(|(k, _)| *k == key)?;
+                    message_key = Some(*k);
+                    Some(*v)
+                })
+            });
+            let dedup_message = match message {
+                Some(RawValue::String(value)) if self.dedup_message_field => {
+                    let mut decoded = Vec::new();
+                    value.decode(&mut decoded).ok().map(|_| decoded)
+                }
+                _ => None,
+            };
+            let message_position = rec
+                .level
+                .and_then(|level| self.level_message_positions[level])
+                .unwrap_or(self.message_position);
+            if message_position == MessagePosition::AfterFields {
+                s.reset();
+            } else if let Some(value) = &message {
                 match fs.transact(s, |fs, s| self.format_message(s, fs, *value)) {
                     Ok(()) => {
                         fs.first_line_used = true;
+                        has_content = true;
                     }
                     Err(MessageFormatError::ExpansionNeeded) => {
+                        has_content = true;
                         self.add_field_to_expand(
                             s,
                             &mut fs,
@@ -474,6 +2571,7 @@ impl RecordFormatter {
                         );
                     }
                     Err(MessageFormatError::FormattingAsFieldNeeded) => {
+                        has_content = true;
                         fs.extra_fields.push(("msg", *value)).ok();
                     }
                     Err(MessageFormatError::EmptyMessage) => {}
@@ -485,45 +2583,127 @@ impl RecordFormatter {
             //
             // fields
             //
-            let mut some_fields_hidden = false;
-            let x_fields = take(&mut fs.extra_fields);
-            for (k, v) in x_fields.iter().chain(rec.fields()) {
-                if !self.hide_empty_fields || !v.is_empty() {
-                    let result = fs.transact(s, |fs, s| {
-                        match self.format_field(s, k, *v, fs, Some(&self.fields), Some(&self.predefined_fields)) {
-                            FieldFormatResult::Ok => {
-                                if !fs.expanded {
-                                    fs.first_line_used = true;
+            s.dimmed(self.dim_fields, |s| {
+                let mut some_fields_hidden = false;
+                let mut fields_shown = 0;
+                let mut fields_over_limit = 0;
+                let x_fields = take(&mut fs.extra_fields);
+                let pinned = self.trace_context.as_ref().map(|tc| tc.keys());
+                let pinned = pinned.iter().flatten();
+                let pinned_fields = pinned.clone().filter_map(|key| rec.fields().find(|(k, _)| k == key));
+                let mut decoded = Vec::new();
+                let other_fields = rec
+                    .fields()
+                    .filter(|(k, _)| !pinned.clone().any(|key| key == k))
+                    .filter(|(_, v)| match (&dedup_message, v) {
+                        (Some(message), RawValue::String(v)) => {
+                            decoded.clear();
+                            v.decode(&mut decoded).is_err() || decoded != *message
+                        }
+                        _ => true,
+                    });
+                let fields = match &self.field_sort {
+                    Some(cmp) => Either::Left(
+                        pinned_fields.chain(x_fields.iter().chain(other_fields).sorted_by(|a, b| cmp(a.0, b.0))),
+                    ),
+                    None => Either::Right(x_fields.iter().chain(pinned_fields).chain(other_fields)),
+                };
+                for (k, v) in fields {
+                    if fs.secondary_ts_formatted && self.secondary_ts_key.as_deref() == Some(*k) {
+                        continue;
+                    }
+                    if message_key == Some(*k) {
+                        continue;
+                    }
+                    if skip.is_some_and(|skip| skip(k, *v)) || (self.hide_null_fields && matches!(v, RawValue::Null)) {
+                        some_fields_hidden = true;
+                    } else if self.max_fields.is_some_and(|max| fields_shown >= max) {
+                        // format the field and roll it back to find out whether it would be visible
+                        let wrap_points = fs.wrap_points.len();
+                        let result = fs.transact(s, |fs, s| {
+                            Err(self.format_field(s, k, *v, fs, Some(&self.fields), Some(&self.predefined_fields)))
+                        });
+                        fs.wrap_points.truncate(wrap_points);
+                        match result {
+                            Ok(()) | Err(FieldFormatResult::HiddenByPredefined) => {}
+                            Err(FieldFormatResult::Hidden) => some_fields_hidden = true,
+                            Err(FieldFormatResult::Ok | FieldFormatResult::ExpansionNeeded) => fields_over_limit += 1,
+                        }
+                    } else if !self.hide_empty_fields || !v.is_empty() {
+                        let result = fs.transact(s, |fs, s| {
+                            match self.format_field(s, k, *v, fs, Some(&self.fields), Some(&self.predefined_fields)) {
+                                FieldFormatResult::Ok => {
+                                    if !fs.expanded {
+                                        fs.first_line_used = true;
+                                    }
+                                    has_content = true;
+                                    fields_shown += 1;
+                                    Ok(())
                                 }
-                                Ok(())
-                            }
-                            FieldFormatResult::Hidden => {
-                                some_fields_hidden = true;
-                                Ok(())
+                                FieldFormatResult::Hidden => {
+                                    some_fields_hidden = true;
+                                    Ok(())
+                                }
+                                FieldFormatResult::HiddenByPredefined => Ok(()),
+                                FieldFormatResult::ExpansionNeeded => Err(()),
                             }
-                            FieldFormatResult::HiddenByPredefined => Ok(()),
-                            FieldFormatResult::ExpansionNeeded => Err(()),
+                        });
+                        if let Err(()) = result {
+                            has_content = true;
+                            fields_shown += 1;
+                            self.add_field_to_expand(
+                                s,
+                                &mut fs,
+                                k,
+                                *v,
+                                Some(&self.fields),
+                                Some(&self.predefined_fields),
+                            );
                         }
-                    });
-                    if let Err(()) = result {
-                        self.add_field_to_expand(s, &mut fs, k, *v, Some(&self.fields), Some(&self.predefined_fields));
                     }
                 }
-            }
+
+                //
+                // expanded fields
+                //
+                self.expand_enqueued(s, &mut fs);
+
+                if (some_fields_hidden || (fs.some_nested_fields_hidden && fs.flatten))
+                    || fs.some_fields_hidden
+                    || fields_over_limit != 0
+                {
+                    if fs.expanded {
+                        self.expand(s, &mut fs);
+                    }
+                    fs.add_element(|| s.batch(|buf| buf.push(b' ')));
+                    s.element(Element::Ellipsis, |s| {
+                        s.batch(|buf| {
+                            buf.extend_from_slice(self.punctuation.hidden_fields_indicator.as_bytes());
+                            if fields_over_limit != 0 {
+                                buf.push(b'+');
+                                buf.extend_from_slice(itoa::Buffer::new().format(fields_over_limit).as_bytes());
+                            }
+                        })
+                    });
+                    has_content = true;
+                }
+            });
 
             //
-            // expanded fields
+            // message text after fields
             //
-            self.expand_enqueued(s, &mut fs);
+            if let (MessagePosition::AfterFields, Some(value)) = (message_position, &message) {
+                has_content |= !value.is_empty();
+                self.format_message_after_fields(s, &mut fs, *value);
+            }
 
-            if (some_fields_hidden || (fs.some_nested_fields_hidden && fs.flatten)) || fs.some_fields_hidden {
-                if fs.expanded {
-                    self.expand(s, &mut fs);
-                }
+            //
+            // placeholder for records without a message and visible fields
+            //
+            if let (false, Some(placeholder)) = (has_content, &self.empty_record_placeholder) {
                 fs.add_element(|| s.batch(|buf| buf.push(b' ')));
-                s.element(Element::Ellipsis, |s| {
-                    s.batch(|buf| buf.extend_from_slice(self.punctuation.hidden_fields_indicator.as_bytes()))
-                });
+                s.element(Element::Ellipsis, |s| s.batch(|buf| buf.extend(placeholder.as_bytes())));
+                fs.first_line_used = true;
             }
 
             //
@@ -532,7 +2712,154 @@ impl RecordFormatter {
             if !fs.caller_formatted && !rec.caller.is_empty() {
                 self.format_caller(s, &rec.caller);
             }
+
+            //
+            // time at the end of the line
+            //
+            if let Some(ts) = trailing_ts {
+                s.reset();
+                s.batch(|buf| {
+                    let line = buf[begin..]
+                        .iter()
+                        .rposition(|&c| c == b'\n')
+                        .map_or(begin, |i| begin + i + 1);
+                    let used = visible_width(&buf[line..]);
+                    let padding = match self.time_position {
+                        TimePosition::TrailingAligned(width) => width.saturating_sub(used + visible_width(&ts)),
+                        _ => 0,
+                    };
+                    let padding = if used != 0 { padding.max(1) } else { padding };
+                    buf.extend(std::iter::repeat_n(b' ', padding));
+                });
+                s.element(Element::Time, |s| s.batch(|buf| buf.extend(ts)));
+            }
+        });
+
+        // drop the logger separator if nothing visible follows it
+        if let Some(separator) = logger_separator {
+            if chunks(&buf[separator.end..]).all(|chunk| matches!(chunk, Chunk::Escape(_))) {
+                buf.drain(separator);
+            }
+        }
+
+        if let Some(width) = self.wrap_width {
+            Self::wrap(buf, begin, fs.prefix.clone(), &fs.wrap_points, width);
+        }
+
+        if let Some(limit) = self.max_line_bytes {
+            if buf.len() - begin > limit {
+                if Self::truncate(buf, begin, limit) {
+                    buf.extend(Sequence::reset().data());
+                }
+                self.theme.apply(buf, &rec.level, |s| {
+                    s.element(Element::Ellipsis, |s| {
+                        s.batch(|buf| buf.extend(self.punctuation.hidden_fields_indicator.as_bytes()))
+                    })
+                });
+            }
+        }
+
+        buf.extend(self.line_terminator.as_bytes());
+    }
+
+    /// Truncates the part of `buf` starting at `begin` to at most `limit` bytes
+    /// at the nearest preceding boundary of an escape sequence or a UTF-8 character.
+    /// Returns true if the remaining part contains escape sequences.
+    fn truncate(buf: &mut Buf, begin: usize, limit: usize) -> bool {
+        let mut pos = 0;
+        let mut styled = false;
+        for chunk in chunks(&buf[begin..]) {
+            match chunk {
+                Chunk::Escape(seq) => {
+                    if pos + seq.len() > limit {
+                        break;
+                    }
+                    styled = true;
+                    pos += seq.len();
+                }
+                Chunk::Text(text) => {
+                    if pos + text.len() > limit {
+                        let mut n = limit - pos;
+                        while n != 0 && (text[n] & 0b1100_0000) == 0b1000_0000 {
+                            n -= 1;
+                        }
+                        pos += n;
+                        break;
+                    }
+                    pos += text.len();
+                }
+            }
+        }
+        buf.truncate(begin + pos);
+        styled
+    }
+
+    /// Replaces spaces at the given positions with a line break and a continuation indent
+    /// wherever the line would otherwise exceed `width` visible characters.
+    fn wrap(buf: &mut Buf, begin: usize, prefix: Range<usize>, points: &[usize], width: usize) {
+        let tail = buf.split_off(begin);
+        let indent = visible_width(&buf[prefix.clone()]) + WRAPPED_LINE_INDENT.len();
+        let mut column = visible_width(&buf[prefix.clone()]);
+
+        let points = points
+            .iter()
+            .map(|&p| p - begin)
+            .filter(|&p| tail.get(p) == Some(&b' '))
+            .chain([tail.len()]);
+
+        let mut start = 0;
+        for end in points {
+            let segment = &tail[start..end];
+            let segment_width = visible_width(segment);
+            if start != 0 && column > indent && column + segment_width > width {
+                buf.push(b'\n');
+                buf.extend_from_within(prefix.clone());
+                buf.extend(WRAPPED_LINE_INDENT.as_bytes());
+                buf.extend(&segment[1..]);
+                column = indent + segment_width - 1;
+            } else {
+                buf.extend(segment);
+                column += segment_width;
+            }
+            if let Some(i) = segment.iter().rposition(|&c| c == b'\n') {
+                column = visible_width(&segment[i + 1..]);
+            }
+            start = end;
+        }
+    }
+
+    #[inline]
+    fn format_gutter<S: StylingPush<Buf>>(&self, rec: &model::Record, s: &mut S) {
+        let marker = match rec.level {
+            Some(Level::Error) => GUTTER_ERROR,
+            Some(Level::Warning) => GUTTER_WARNING,
+            Some(Level::Info) => GUTTER_INFO,
+            Some(Level::Debug) => GUTTER_DEBUG,
+            Some(Level::Trace) => GUTTER_TRACE,
+            None => GUTTER_UNKNOWN,
+        };
+        s.element(Element::Level, |s| {
+            s.element(Element::LevelInner, |s| s.batch(|buf| buf.push(marker)))
         });
+        s.reset();
+        s.space();
+    }
+
+    #[inline]
+    fn format_markers<S: StylingPush<Buf>>(&self, rec: &model::Record, s: &mut S) {
+        let mut matched = false;
+        for rule in self.style_rules.iter().filter(|rule| rule.matches(rec)) {
+            s.element(rule.element, |s| {
+                s.batch(|buf| buf.extend_from_slice(rule.marker.as_bytes()))
+            });
+            matched = true;
+            if self.style_rule_mode == StyleRuleMode::FirstMatch {
+                break;
+            }
+        }
+        if matched {
+            s.space();
+        }
     }
 
     #[inline(always)]
@@ -549,23 +2876,184 @@ impl RecordFormatter {
         fs.ts_width = self.ts_width.chars;
         fs.add_element(|| {});
         s.element(Element::Time, |s| {
-            s.batch(|buf| {
-                aligned_left(buf, self.ts_width.bytes, b' ', |mut buf| {
-                    if ts
-                        .as_rfc3339()
-                        .and_then(|ts| self.ts_formatter.reformat_rfc3339(&mut buf, ts))
-                        .is_some()
-                    {
-                        Ok(())
-                    } else if let Some(ts) = ts.parse() {
-                        self.ts_formatter.format(&mut buf, ts);
-                        Ok(())
-                    } else {
-                        Err(())
-                    }
+            s.batch(|buf| aligned_left(buf, self.ts_width.bytes, b' ', |buf| self.format_record_ts(buf, ts)))
+        })
+    }
+
+    /// Formats the time for the end of the line, adding the raw value to the fields if it cannot be formatted.
+    fn format_trailing_timestamp<'a>(
+        &self,
+        rec: &'a model::Record,
+        fs: &mut FormattingStateWithRec<'a>,
+    ) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        match &rec.ts {
+            Some(ts) => {
+                if aligned_left(&mut buf, self.ts_width.bytes, b' ', |buf| {
+                    self.format_record_ts(buf, ts)
                 })
+                .is_err()
+                {
+                    fs.extra_fields
+                        .push(("ts", RawValue::String(EncodedString::raw(ts.raw()))))
+                        .ok();
+                    return None;
+                }
+            }
+            None if self.always_show_time => buf.extend(self.ts_stub.as_bytes()),
+            None => return None,
+        }
+        Some(buf)
+    }
+
+    /// Returns the width of the longest rendered key among the top-level fields
+    /// that are not hidden by the field filters, `skip` or the options hiding null or empty fields.
+    fn table_key_width(&self, rec: &model::Record, skip: Option<&FieldSkip>) -> usize {
+        let mut buf = Vec::new();
+        rec.fields()
+            .filter(|(k, v)| {
+                let hidden = skip.is_some_and(|skip| skip(k, *v))
+                    || (self.hide_null_fields && matches!(v, RawValue::Null))
+                    || (self.hide_empty_fields && v.is_empty());
+                !hidden && !self.field_excluded(k)
             })
-        })
+            .map(|(k, _)| {
+                buf.clear();
+                self.push_key(&mut buf, k);
+                visible_width(&buf)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns true if the top-level field is hidden by the field filters.
+    fn field_excluded(&self, key: &str) -> bool {
+        let setting = self.predefined_fields.setting();
+        let (setting, leaf) = match self.predefined_fields.get(key) {
+            Some(filter) => (setting.apply(filter.setting()), filter.leaf()),
+            None => (setting, true),
+        };
+        if setting == IncludeExcludeSetting::Exclude && leaf {
+            return true;
+        }
+
+        let setting = self.fields.setting();
+        let (setting, leaf) = match self.fields.get(key) {
+            Some(filter) => (setting.apply(filter.setting()), filter.leaf()),
+            None => (setting, true),
+        };
+        setting == IncludeExcludeSetting::Exclude && leaf
+    }
+
+    /// Pushes the top-level key as it is rendered, prettified and with the configured prefix stripped.
+    fn push_key(&self, buf: &mut Buf, key: &str) {
+        let begin = buf.len();
+        if self.prettify_field_keys {
+            key.key_prettify(buf);
+        } else {
+            buf.extend_from_slice(key.as_bytes());
+        }
+        self.strip_key_prefix_from(buf, begin);
+    }
+
+    /// Removes the configured key prefix from the key pushed to `buf` since `begin`, unless nothing would remain.
+    #[inline]
+    fn strip_key_prefix_from(&self, buf: &mut Buf, begin: usize) {
+        if let Some(prefix) = &self.strip_key_prefix {
+            if buf.len() > begin + prefix.len() && buf[begin..].starts_with(prefix) {
+                buf.drain(begin..begin + prefix.len());
+            }
+        }
+    }
+
+    /// Formats the time of the record, applying the time shift if it is configured.
+    fn format_record_ts<B: Push<u8>>(&self, mut buf: B, ts: &Timestamp) -> Result<(), ()> {
+        if self.time_shift.is_zero() {
+            return self.format_ts(buf, ts);
+        }
+        let dt = self.record_time(ts).ok_or(())?;
+        self.ts_formatter.format(&mut buf, dt);
+        Ok(())
+    }
+
+    /// Returns the parsed time of the record with the time shift applied.
+    #[inline]
+    fn record_time(&self, ts: &Timestamp) -> Option<DateTime<FixedOffset>> {
+        ts.parse()?.checked_add_signed(self.time_shift)
+    }
+
+    #[inline(always)]
+    fn format_ts<B: Push<u8>>(&self, mut buf: B, ts: &Timestamp) -> Result<(), ()> {
+        let formatted = ts
+            .as_rfc3339()
+            .and_then(|ts| self.ts_formatter.reformat_rfc3339(&mut buf, ts))
+            .or_else(|| {
+                let (secs, nsecs) = ts.unix_integer()?;
+                self.ts_formatter.format_unix(&mut buf, secs, nsecs)
+            });
+        if formatted.is_some() {
+            Ok(())
+        } else if let Some(ts) = ts.parse() {
+            self.ts_formatter.format(&mut buf, ts);
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Formats the secondary time column if the primary one is shown, padding it if the field is missing or invalid.
+    fn format_secondary_timestamp<S: StylingPush<Buf>>(
+        &self,
+        rec: &model::Record,
+        key: &str,
+        fs: &mut FormattingStateWithRec,
+        s: &mut S,
+    ) {
+        if fs.ts_width == 0 {
+            return;
+        }
+
+        let ts = rec.fields().find(|(k, _)| *k == key).and_then(|(_, v)| match v {
+            RawValue::String(EncodedString::Json(_)) => v.raw_str().strip_prefix('"')?.strip_suffix('"'),
+            RawValue::String(EncodedString::Raw(_)) | RawValue::Number(_) => Some(v.raw_str()),
+            _ => None,
+        });
+
+        fs.ts_width += 1 + self.ts_width.chars;
+        s.space();
+        s.element(Element::Time, |s| {
+            s.batch(|buf| {
+                let l0 = buf.len();
+                let formatted = ts.is_some_and(|ts| {
+                    let ts = Timestamp::new(ts);
+                    aligned_left(buf, self.ts_width.bytes, b' ', |buf| self.format_ts(buf, &ts)).is_ok()
+                });
+                if !formatted {
+                    buf.truncate(l0);
+                    aligned_left(buf, self.ts_width.chars, b' ', |_| {});
+                }
+                fs.secondary_ts_formatted = formatted;
+            })
+        });
+
+        if self.secondary_ts_skew {
+            let width = DeltaFormatter::<RecordFormatter>::WIDTH;
+            let skew = ts
+                .and_then(|ts| Timestamp::new(ts).parse())
+                .zip(rec.ts.as_ref().and_then(|ts| ts.parse()))
+                .map(|(secondary, primary)| secondary - primary);
+            fs.ts_width += 1 + width;
+            s.space();
+            s.element(Element::Delta, |s| {
+                s.batch(|buf| {
+                    aligned_left(buf, width, b' ', |mut buf| {
+                        if let Some(skew) = skew {
+                            buf.extend_from_slice(format_delta(skew).as_bytes());
+                        }
+                    })
+                })
+            });
+        }
     }
 
     #[inline(always)]
@@ -595,7 +3083,7 @@ impl RecordFormatter {
                         if !caller.name.is_empty() {
                             buf.extend(self.punctuation.caller_name_file_separator.as_bytes());
                         }
-                        buf.extend(caller.file.as_bytes());
+                        buf.extend(self.caller_path.apply(caller.file).as_bytes());
                         if !caller.line.is_empty() {
                             buf.push(b':');
                             buf.extend(caller.line.as_bytes());
@@ -607,6 +3095,18 @@ impl RecordFormatter {
     }
 
     #[inline(always)]
+    fn field_style(&self, key: &str) -> Option<FieldStyle> {
+        if self.field_styles.is_empty() {
+            return None;
+        }
+        if !self.prettify_field_keys {
+            return self.field_styles.get(key.as_bytes()).copied();
+        }
+        let mut buf = Vec::new();
+        key.key_prettify(&mut buf);
+        self.field_styles.get(&buf).copied()
+    }
+
     fn format_field<'a, S: StylingPush<Buf>>(
         &self,
         s: &mut S,
@@ -629,6 +3129,53 @@ impl RecordFormatter {
         )
     }
 
+    /// Formats the message following the fields, either inline or, if it does not fit, as a field.
+    fn format_message_after_fields<'a>(
+        &self,
+        s: &mut Styler<Buf>,
+        fs: &mut FormattingStateWithRec<'a>,
+        value: RawValue<'a>,
+    ) {
+        let result = (!fs.expanded).then(|| fs.transact(s, |fs, s| self.format_message(s, fs, value)));
+        match result {
+            Some(Ok(())) => {
+                fs.first_line_used = true;
+            }
+            Some(Err(MessageFormatError::EmptyMessage)) => {}
+            _ => {
+                if value.is_empty() {
+                    return;
+                }
+                let result = fs.transact(s, |fs, s| {
+                    match self.format_field(s, "msg", value, fs, Some(&self.fields), Some(&self.predefined_fields)) {
+                        FieldFormatResult::ExpansionNeeded => Err(()),
+                        _ => Ok(()),
+                    }
+                });
+                if result.is_err() {
+                    self.add_field_to_expand(s, fs, "msg", value, Some(&self.fields), Some(&self.predefined_fields));
+                    self.expand_enqueued(s, fs);
+                }
+            }
+        }
+    }
+
+    /// Renders the source of a message that cannot be decoded.
+    fn format_malformed_message(buf: &mut Buf, value: EncodedString, err: FormatError) {
+        log::debug!("failed to format message {:?}: {err}", value.source());
+        buf.extend(value.source().as_bytes());
+    }
+
+    /// Returns the decoded value with runs of whitespace replaced by a single space,
+    /// or `None` if the value cannot be decoded or needs no changes.
+    fn collapse_whitespace(value: EncodedString) -> Option<String> {
+        let mut decoded = Vec::new();
+        value.decode(&mut decoded).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let collapsed = decoded.split_whitespace().join(" ");
+        (collapsed != decoded).then_some(collapsed)
+    }
+
     #[inline(always)]
     fn format_message<'a, S: StylingPush<Buf>>(
         &self,
@@ -638,25 +3185,79 @@ impl RecordFormatter {
     ) -> Result<(), MessageFormatError> {
         match value {
             RawValue::String(value) => {
+                if let Some(json) = self.embedded_json(value, fs) {
+                    fs.add_element(|| {
+                        s.reset();
+                        s.space();
+                    });
+                    return s.element(Element::Message, |s| {
+                        let value = RawValue::from(&*json);
+                        fs.embedded_json_depth += 1;
+                        let setting = IncludeExcludeSetting::Unspecified;
+                        let result = FieldFormatter::new(self).format_value(s, value, fs, None, None, setting, setting);
+                        fs.embedded_json_depth -= 1;
+                        match result {
+                            ValueFormatResult::Ok | ValueFormatResult::Empty => Ok(()),
+                            ValueFormatResult::ExpansionNeeded => Err(MessageFormatError::ExpansionNeeded),
+                        }
+                    });
+                }
+                let collapsed;
+                let value = match (self.collapse_whitespace && !self.debug_strings)
+                    .then(|| Self::collapse_whitespace(value))
+                    .flatten()
+                {
+                    Some(text) => {
+                        collapsed = text;
+                        EncodedString::raw(&collapsed)
+                    }
+                    None => value,
+                };
                 if !value.is_empty() {
                     fs.add_element(|| {
                         s.reset();
                         s.space();
                     });
-                    s.element(Element::Message, |s| {
-                        s.batch(|buf| {
+                    let mut begin = 0;
+                    let result = s.element(Element::Message, |s| {
+                        begin = s.batch(|buf| buf.len());
+                        if let (Some(template), false) = (&self.message_template, self.debug_strings) {
+                            let mut text = Vec::new();
                             let xsa = match fs.expansion.multiline {
                                 MultilineExpansion::Disabled => ExtendedSpaceAction::Escape,
                                 MultilineExpansion::Standard => ExtendedSpaceAction::Abort,
                                 MultilineExpansion::Inline => ExtendedSpaceAction::Inline,
                             };
-                            let result = self.message_format.format(value, buf, xsa.into()).unwrap();
-                            match result {
-                                string::FormatResult::Ok(_) => Ok(()),
-                                string::FormatResult::Aborted => Err(MessageFormatError::ExpansionNeeded),
-                            }
+                            return match self.message_format.format(value, &mut text, xsa.into()) {
+                                Ok(string::FormatResult::Ok(_)) => {
+                                    self.anonymize_from(&mut text, 0);
+                                    Self::format_message_diff(s, &text, template);
+                                    Ok(())
+                                }
+                                Ok(string::FormatResult::Aborted) => Err(MessageFormatError::ExpansionNeeded),
+                                Err(err) => {
+                                    s.batch(|buf| {
+                                        let begin = buf.len();
+                                        Self::format_malformed_message(buf, value, err.into());
+                                        self.anonymize_from(buf, begin);
+                                    });
+                                    Ok(())
+                                }
+                            };
+                        }
+                        s.batch(|buf| {
+                            let begin = buf.len();
+                            let result = self.format_message_text(buf, value, fs.expansion.multiline);
+                            self.anonymize_from(buf, begin);
+                            result
                         })
-                    })
+                    });
+                    if let (Ok(()), Some(width)) = (&result, self.message_width) {
+                        let width = width.saturating_sub(s.batch(|buf| visible_width(&buf[begin..])));
+                        s.reset();
+                        s.batch(|buf| buf.extend(std::iter::repeat_n(b' ', width)));
+                    }
+                    result
                 } else {
                     Err(MessageFormatError::EmptyMessage)
                 }
@@ -665,15 +3266,158 @@ impl RecordFormatter {
         }
     }
 
+    /// Pushes the message text, or its debug representation if debug strings are enabled.
+    fn format_message_text(
+        &self,
+        buf: &mut Buf,
+        value: EncodedString,
+        multiline: MultilineExpansion,
+    ) -> Result<(), MessageFormatError> {
+        if self.debug_strings {
+            let xsa = ExtendedSpaceAction::Escape;
+            let begin = buf.len();
+            if let Err(err) = string::MessageFormatRaw.format(value, buf, xsa.into()) {
+                buf.truncate(begin);
+                Self::format_malformed_message(buf, value, err.into());
+            }
+            buf.extend(DEBUG_STRING_SOURCE_OPENING.as_bytes());
+            buf.extend(value.source().as_bytes());
+            buf.extend(DEBUG_STRING_SOURCE_CLOSING.as_bytes());
+            return Ok(());
+        }
+        let xsa = match multiline {
+            MultilineExpansion::Disabled => ExtendedSpaceAction::Escape,
+            MultilineExpansion::Standard => ExtendedSpaceAction::Abort,
+            MultilineExpansion::Inline => ExtendedSpaceAction::Inline,
+        };
+        let begin = buf.len();
+        match self.message_format.format(value, buf, xsa.into()) {
+            Ok(string::FormatResult::Ok(_)) => Ok(()),
+            Ok(string::FormatResult::Aborted) => Err(MessageFormatError::ExpansionNeeded),
+            Err(err) => {
+                buf.truncate(begin);
+                Self::format_malformed_message(buf, value, err.into());
+                Ok(())
+            }
+        }
+    }
+
+    /// Masks personal data in the text pushed to `buf` since `begin` if anonymization is enabled.
+    #[inline]
+    fn anonymize_from(&self, buf: &mut Buf, begin: usize) {
+        if !self.anonymize.is_empty() {
+            let text = buf.split_off(begin);
+            anonymize::anonymize(&text, self.anonymize, buf);
+        }
+    }
+
+    /// Pushes the formatted message `text`, highlighting whitespace-separated tokens
+    /// that differ from the corresponding tokens of the `template`, ignoring surrounding quotes.
+    fn format_message_diff<S: StylingPush<Buf>>(s: &mut S, text: &[u8], template: &[String]) {
+        let quote = |c: &u8| matches!(c, b'"' | b'\'' | b'`');
+        let mut tokens = template.iter();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let n = rest.iter().position(|c| !c.is_ascii_whitespace()).unwrap_or(rest.len());
+            if n != 0 {
+                s.batch(|buf| buf.extend(&rest[..n]));
+                rest = &rest[n..];
+                continue;
+            }
+
+            let n = rest.iter().position(|c| c.is_ascii_whitespace()).unwrap_or(rest.len());
+            let token = &rest[..n];
+            let start = token.iter().position(|c| !quote(c)).unwrap_or(n);
+            let end = token.iter().rposition(|c| !quote(c)).map_or(start, |i| i + 1);
+            if tokens.next().is_some_and(|t| t.as_bytes() == &token[start..end]) {
+                s.batch(|buf| buf.extend(token));
+            } else {
+                s.element(Element::Match, |s| s.batch(|buf| buf.extend(token)));
+            }
+            rest = &rest[n..];
+        }
+    }
+
+    /// Parses `value` as an embedded JSON object or array if expanding of embedded JSON is enabled
+    /// and the nesting limit is not reached.
+    fn embedded_json(&self, value: EncodedString, fs: &FormattingStateWithRec) -> Option<Box<json::value::RawValue>> {
+        if !self.expand_embedded_json || fs.embedded_json_depth >= MAX_EMBEDDED_JSON_DEPTH {
+            return None;
+        }
+
+        let mut decoded = Vec::new();
+        value.decode(&mut decoded).ok()?;
+        if !matches!(decoded.iter().find(|c| !c.is_ascii_whitespace()), Some(b'{' | b'[')) {
+            return None;
+        }
+
+        json::value::RawValue::from_string(String::from_utf8(decoded).ok()?).ok()
+    }
+
+    /// Visible width of the level column including separators.
+    #[inline]
+    fn level_width(&self) -> usize {
+        if self.single_char_level {
+            return 1 + self.level_severities.map_or(0, |ls| ls.width());
+        }
+        visible_width(self.punctuation.level_left_separator.as_bytes())
+            + LEVEL_INFO.len()
+            + self.level_severities.map_or(0, |ls| ls.width())
+            + visible_width(self.punctuation.level_right_separator.as_bytes())
+    }
+
     #[inline(always)]
-    fn format_level<S: StylingPush<Buf>>(&self, s: &mut S, fs: &mut FormattingStateWithRec, level: &[u8]) {
+    fn format_level<S: StylingPush<Buf>>(
+        &self,
+        s: &mut S,
+        fs: &mut FormattingStateWithRec,
+        label: &[u8],
+        level: Option<Level>,
+    ) {
         fs.add_element(|| s.space());
+        self.format_level_label(s, label, level);
+    }
+
+    #[inline(always)]
+    fn format_level_label<S: StylingPush<Buf>>(&self, s: &mut S, label: &[u8], level: Option<Level>) {
+        if self.level_style == LevelStyle::Block {
+            let width = self.level_width();
+            s.element(Element::LevelBlock, |s| {
+                s.batch(|buf| buf.extend(std::iter::repeat_n(b' ', width)))
+            });
+            return;
+        }
+        let (left, right, label) = if self.single_char_level {
+            let label = match label {
+                label if label == LEVEL_EXPANDED.as_bytes() => LEVEL_EXPANDED_SHORT.as_bytes(),
+                label if label == LEVEL_UNKNOWN.as_bytes() => LEVEL_UNKNOWN_SHORT.as_bytes(),
+                label => &label[..1.min(label.len())],
+            };
+            ("", "", label)
+        } else {
+            (
+                self.punctuation.level_left_separator.as_str(),
+                self.punctuation.level_right_separator.as_str(),
+                label,
+            )
+        };
         s.element(Element::Level, |s| {
             s.batch(|buf| {
-                buf.extend_from_slice(self.punctuation.level_left_separator.as_bytes());
+                buf.extend_from_slice(left.as_bytes());
+            });
+            s.element(Element::LevelInner, |s| {
+                s.batch(|buf| {
+                    let begin = buf.len();
+                    self.level_case.apply(buf, label);
+                    if let (Some(ls), Some(level)) = (self.level_severities, level) {
+                        ls.format(buf, level);
+                    }
+                    let width = self.level_width() - visible_width(left.as_bytes()) - visible_width(right.as_bytes());
+                    let width = width.saturating_sub(visible_width(&buf[begin..]));
+                    buf.extend(std::iter::repeat_n(b' ', width));
+                })
             });
-            s.element(Element::LevelInner, |s| s.batch(|buf| buf.extend_from_slice(level)));
-            s.batch(|buf| buf.extend_from_slice(self.punctuation.level_right_separator.as_bytes()));
+            s.batch(|buf| buf.extend_from_slice(right.as_bytes()));
         });
     }
 
@@ -735,7 +3479,7 @@ impl RecordFormatter {
         }
 
         if fs.has_level {
-            self.format_level(s, fs, LEVEL_EXPANDED.as_bytes());
+            self.format_level(s, fs, LEVEL_EXPANDED.as_bytes(), None);
             s.reset();
         }
 
@@ -793,8 +3537,21 @@ impl RecordFormatter {
 
 impl RecordWithSourceFormatter for RecordFormatter {
     #[inline(always)]
-    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
-        RecordFormatter::format_record(self, buf, prefix, rec.record)
+    fn format_record(&self, buf: &mut Buf, prefix: Range<usize>, rec: model::RecordWithSource) {
+        RecordFormatter::format_record(self, buf, prefix, rec.record)
+    }
+}
+
+impl RecordWithSkipFormatter for RecordFormatter {
+    #[inline(always)]
+    fn format_record_with_skip(
+        &self,
+        buf: &mut Buf,
+        prefix: Range<usize>,
+        rec: model::RecordWithSource,
+        skip: &FieldSkip,
+    ) {
+        RecordFormatter::format_record_with_skip(self, buf, prefix, rec.record, Some(skip))
     }
 }
 
@@ -856,7 +3613,11 @@ impl<'a> DerefMut for FormattingStateWithRec<'a> {
 #[derive(Default)]
 struct FormattingState<'a> {
     key_prefix: KeyPrefix,
+    key_path: KeyPrefix,
     flatten: bool,
+    collapsed: bool,
+    object_field_separator_pending: bool,
+    wrap_points: Vec<usize>,
     some_nested_fields_hidden: bool,
     has_fields: bool,
     expansion: &'a ExpansionProfile,
@@ -870,6 +3631,15 @@ struct FormattingState<'a> {
     first_line_used: bool,
     some_fields_hidden: bool,
     caller_formatted: bool,
+    secondary_ts_formatted: bool,
+    embedded_json_depth: usize,
+    object_parens: bool,
+    table_key_width: usize,
+    in_array: bool,
+    block: bool,
+    block_item_head: bool,
+    key_segment: KeySegment,
+    value_element: Option<Element>,
     extra_fields: heapless::Vec<(&'a str, RawValue<'a>), 4>,
     fields_to_expand: heapless::Vec<(&'a str, RawValue<'a>), MAX_FIELDS_TO_EXPAND_ON_HOLD>,
     last_expansion_point: Option<usize>,
@@ -879,6 +3649,16 @@ const MAX_FIELDS_TO_EXPAND_ON_HOLD: usize = 32;
 
 // ---
 
+/// Kind of a key joined to a [`KeyPrefix`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum KeySegment {
+    /// Object key, joined with a dot, e.g. `a.b`.
+    #[default]
+    Name,
+    /// Array index pushed by flattening of arrays, joined as is, e.g. `a[0]`.
+    Index,
+}
+
 #[derive(Default)]
 struct KeyPrefix {
     value: OptimizedBuf<u8, 256>,
@@ -897,9 +3677,15 @@ impl KeyPrefix {
     }
 
     #[inline(always)]
-    fn push(&mut self, key: &str, prettify: bool) -> usize {
+    fn equals(&self, value: &[u8]) -> bool {
+        let (head, tail) = self.value.as_slices();
+        value.len() == head.len() + tail.len() && value.starts_with(head) && value.ends_with(tail)
+    }
+
+    #[inline(always)]
+    fn push(&mut self, key: &str, segment: KeySegment, prettify: bool) -> usize {
         let len = self.len();
-        if len != 0 {
+        if len != 0 && segment == KeySegment::Name {
             self.value.push(b'.');
         }
         if prettify {
@@ -923,6 +3709,123 @@ impl KeyPrefix {
     }
 }
 
+/// Returns the element for a number according to its sign.
+#[inline]
+fn number_sign_element(value: &str) -> Element {
+    let mantissa = value.split(['e', 'E']).next().unwrap_or(value);
+    if !mantissa.bytes().any(|c| matches!(c, b'1'..=b'9')) {
+        Element::NumberZero
+    } else if value.starts_with('-') {
+        Element::NumberNegative
+    } else {
+        Element::NumberPositive
+    }
+}
+
+/// Decodes the string into bytes without failing on invalid escape sequences.
+///
+/// Code units of lone surrogates are encoded the same way as valid code points would be, as in WTF-8,
+/// and invalid escape sequences are kept as they are.
+fn decode_lossless(value: EncodedString, out: &mut Vec<u8>) {
+    let source = match value {
+        EncodedString::Json(_) => &value.source()[1..value.source().len() - 1],
+        EncodedString::Raw(_) => {
+            out.extend(value.source().as_bytes());
+            return;
+        }
+    };
+
+    let hex = |s: &str, i: usize| {
+        s.get(i + 2..i + 6)
+            .filter(|_| s[i..].starts_with("\\u"))
+            .and_then(|h| u32::from_str_radix(h, 16).ok())
+    };
+    let mut i = 0;
+    while i < source.len() {
+        let c = source.as_bytes()[i];
+        if c != b'\\' {
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        if let Some(mut n) = hex(source, i) {
+            i += 6;
+            if (0xD800..0xDC00).contains(&n) {
+                if let Some(n2 @ 0xDC00..0xE000) = hex(source, i) {
+                    n = 0x10000 + ((n - 0xD800) << 10) + (n2 - 0xDC00);
+                    i += 6;
+                }
+            }
+            encode_code_point(n, out);
+            continue;
+        }
+        match source.as_bytes().get(i + 1) {
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'b') => out.push(0x08),
+            Some(b'f') => out.push(0x0c),
+            Some(&c @ (b'"' | b'\\' | b'/')) => out.push(c),
+            Some(&c) => out.extend([b'\\', c]),
+            None => out.push(b'\\'),
+        }
+        i += 2;
+    }
+}
+
+/// Encodes a code point in UTF-8, including surrogate code points.
+fn encode_code_point(n: u32, out: &mut Vec<u8>) {
+    match n {
+        0..0x80 => out.push(n as u8),
+        0x80..0x800 => out.extend([0xC0 | (n >> 6) as u8, 0x80 | (n & 0x3F) as u8]),
+        0x800..0x10000 => out.extend([
+            0xE0 | (n >> 12) as u8,
+            0x80 | ((n >> 6) & 0x3F) as u8,
+            0x80 | (n & 0x3F) as u8,
+        ]),
+        _ => out.extend([
+            0xF0 | (n >> 18) as u8,
+            0x80 | ((n >> 12) & 0x3F) as u8,
+            0x80 | ((n >> 6) & 0x3F) as u8,
+            0x80 | (n & 0x3F) as u8,
+        ]),
+    }
+}
+
+/// Returns true if the value is a non-empty object or array that is rendered on separate lines in block style.
+fn is_block_value(value: RawValue) -> bool {
+    match value {
+        RawValue::Object(object) => !object.is_empty() && object.get().trim_start().starts_with('{'),
+        RawValue::Array(array) => !array.is_empty() && array.get().trim_start().starts_with('['),
+        _ => false,
+    }
+}
+
+/// Appends `value` to `buf` escaped for a cell of tab-separated output,
+/// so that tabs, line breaks and backslashes in it cannot break the columns.
+pub(crate) fn tsv_escape(buf: &mut Buf, value: &[u8]) {
+    for &c in value {
+        match c {
+            b'\t' => buf.extend(b"\\t"),
+            b'\n' => buf.extend(b"\\n"),
+            b'\r' => buf.extend(b"\\r"),
+            b'\\' => buf.extend(b"\\\\"),
+            _ => buf.push(c),
+        }
+    }
+}
+
+/// Returns the label of the level shown in the level column.
+fn level_label(level: Level) -> &'static [u8] {
+    match level {
+        Level::Error => LEVEL_ERROR.as_bytes(),
+        Level::Warning => LEVEL_WARNING.as_bytes(),
+        Level::Info => LEVEL_INFO.as_bytes(),
+        Level::Debug => LEVEL_DEBUG.as_bytes(),
+        Level::Trace => LEVEL_TRACE.as_bytes(),
+    }
+}
+
 // ---
 
 struct FieldFormatter<'a> {
@@ -948,6 +3851,8 @@ impl<'a> FieldFormatter<'a> {
         predefined_filter: Option<&ExactIncludeExcludeKeyFilter>,
         predefined_setting: IncludeExcludeSetting,
     ) -> FieldFormatResult {
+        let segment = replace(&mut fs.key_segment, KeySegment::Name);
+
         let (predefined_filter, predefined_setting, predefined_leaf) = match predefined_filter {
             Some(filter) => {
                 let setting = predefined_setting.apply(filter.setting());
@@ -981,30 +3886,102 @@ impl<'a> FieldFormatter<'a> {
             return FieldFormatResult::ExpansionNeeded;
         }
 
-        // For objects with hide_empty_fields or predefined_filter, track buffer position to rollback if empty
+        if let Some(result) = self.format_error_object(
+            s,
+            key,
+            value,
+            fs,
+            filter,
+            setting,
+            predefined_filter,
+            predefined_setting,
+        ) {
+            return result;
+        }
+
+        // For objects with hidden empty or null fields or predefined filter, track position to rollback if empty
         let has_predefined_filter = predefined_filter.is_some();
-        let rollback_pos =
-            if (self.rf.hide_empty_fields || has_predefined_filter) && matches!(value, RawValue::Object(_)) {
-                let mut pos = 0;
-                s.batch(|buf| pos = buf.len());
-                Some(pos)
-            } else {
-                None
-            };
+        let rollback_pos = if (self.rf.hide_empty_fields || self.rf.hide_null_fields || has_predefined_filter)
+            && matches!(value, RawValue::Object(_))
+        {
+            let mut pos = 0;
+            s.batch(|buf| pos = buf.len());
+            Some(pos)
+        } else {
+            None
+        };
+
+        // Full key path is tracked only when needed to match fields by their paths
+        let path = (self.rf.http_status.is_some()
+            || self.rf.elapsed_time_fields.is_some()
+            || self.rf.trace_context.is_some()
+            || !self.rf.number_radix.is_empty()
+            || !self.rf.compact_arrays.is_empty())
+        .then(|| fs.key_path.push(key, segment, false));
+
+        let style = if fs.depth == 0 { self.rf.field_style(key) } else { None };
+        let value_element = style
+            .and_then(|style| style.value)
+            .or_else(|| {
+                (matches!(value, RawValue::String(_)) && self.error_fields(key, fs).is_some())
+                    .then_some(Element::ErrorMessage)
+            })
+            .map(|e| fs.value_element.replace(e));
 
-        let ffv = self.begin(s, key, value, fs);
+        let ffv = self.begin(
+            s,
+            key,
+            segment,
+            value,
+            fs,
+            style.and_then(|style| style.key).unwrap_or(Element::Key),
+        );
 
-        let result = if self.rf.unescape_fields {
-            self.format_value(s, value, fs, filter, predefined_filter, setting, predefined_setting)
+        let hooked = matches!(ffv, FormattedFieldVariant::Normal { .. })
+            && self.rf.field_hook.as_ref().is_some_and(|hook| hook(key, value, s));
+
+        let result = if hooked {
+            ValueFormatResult::Ok
+        } else if self.rf.unescape_fields {
+            let transformed = self
+                .rf
+                .value_transforms
+                .iter()
+                .find(|t| t.matches(key, value))
+                .and_then(|t| t.transform(key, value));
+            match transformed {
+                Some(text) => FieldFormatter::new(self.rf).format_value(
+                    s,
+                    RawValue::String(EncodedString::raw(&text)),
+                    fs,
+                    filter,
+                    predefined_filter,
+                    setting,
+                    predefined_setting,
+                ),
+                None => self.format_value(s, value, fs, filter, predefined_filter, setting, predefined_setting),
+            }
         } else {
             s.element(Element::String, |s| {
-                s.batch(|buf| buf.extend(value.raw_str().as_bytes()))
+                s.batch(|buf| {
+                    let begin = buf.len();
+                    buf.extend(value.raw_str().as_bytes());
+                    self.rf.anonymize_from(buf, begin);
+                })
             });
             ValueFormatResult::Ok
         };
 
         self.end(fs, ffv);
 
+        if let Some(e) = value_element {
+            fs.value_element = e;
+        }
+
+        if let Some(n) = path {
+            fs.key_path.pop(n);
+        }
+
         // If object had no visible content, rollback buffer and state
         if let Some(pos) = rollback_pos {
             if result == ValueFormatResult::Empty {
@@ -1019,6 +3996,81 @@ impl<'a> FieldFormatter<'a> {
         }
     }
 
+    #[inline(always)]
+    fn error_fields(&self, key: &str, fs: &FormattingStateWithRec) -> Option<&'a ErrorFields> {
+        let rf: &'a RecordFormatter = self.rf;
+        rf.error_fields.as_deref().filter(|ef| fs.depth == 0 && ef.matches(key))
+    }
+
+    /// Formats an error field with an object value having a string message, see [`ErrorFields`].
+    /// Returns `None` if the field is not such an error field.
+    #[allow(clippy::too_many_arguments)]
+    fn format_error_object<S: StylingPush<Buf>>(
+        &mut self,
+        s: &mut S,
+        key: &str,
+        value: RawValue<'a>,
+        fs: &mut FormattingStateWithRec,
+        filter: Option<&IncludeExcludeKeyFilter>,
+        setting: IncludeExcludeSetting,
+        predefined_filter: Option<&ExactIncludeExcludeKeyFilter>,
+        predefined_setting: IncludeExcludeSetting,
+    ) -> Option<FieldFormatResult> {
+        let ef = self.error_fields(key, fs)?;
+        let RawValue::Object(object) = value else {
+            return None;
+        };
+        if !self.rf.unescape_fields {
+            return None;
+        }
+        let object = object.parse().ok()?;
+        let message = object
+            .fields
+            .iter()
+            .find(|(k, v)| &**k == ErrorFields::MESSAGE_KEY && matches!(v, RawValue::String(_)))
+            .map(|(_, v)| *v)?;
+
+        let key_element = self
+            .rf
+            .field_style(key)
+            .and_then(|style| style.key)
+            .unwrap_or(Element::Key);
+        let ffv = self.begin(s, key, KeySegment::Name, message, fs, key_element);
+        let value_element = fs.value_element.replace(Element::ErrorMessage);
+        let result = self.format_value(s, message, fs, None, None, setting, predefined_setting);
+        fs.value_element = value_element;
+        self.end(fs, ffv);
+        if result == ValueFormatResult::ExpansionNeeded {
+            return Some(FieldFormatResult::ExpansionNeeded);
+        }
+
+        let n = fs.key_prefix.push(key, KeySegment::Name, self.rf.prettify_field_keys);
+        let flatten = replace(&mut fs.flatten, true);
+        let mut result = FieldFormatResult::Ok;
+        for (k, v) in object.fields.iter() {
+            if &**k == ErrorFields::MESSAGE_KEY {
+                continue;
+            }
+            let hidden = (&**k == ErrorFields::STACK_KEY && !ef.stack)
+                || (self.rf.hide_null_fields && matches!(v, RawValue::Null))
+                || (self.rf.hide_empty_fields && v.is_empty());
+            if hidden {
+                fs.some_fields_hidden = true;
+                continue;
+            }
+            if let FieldFormatResult::ExpansionNeeded =
+                self.format(s, k, *v, fs, filter, setting, predefined_filter, predefined_setting)
+            {
+                result = FieldFormatResult::ExpansionNeeded;
+                break;
+            }
+        }
+        fs.flatten = flatten;
+        fs.key_prefix.pop(n);
+
+        Some(result)
+    }
+
     #[inline(always)]
     #[allow(clippy::too_many_arguments)]
     fn format_value<S: StylingPush<Buf>>(
@@ -1038,49 +4090,189 @@ impl<'a> FieldFormatter<'a> {
 
         match value {
             RawValue::String(value) => {
-                let result = s.element(Element::String, |s| {
+                if self.rf.hex_undecodable && value.decode(&mut Vec::new()).is_err() {
+                    let mut bytes = Vec::new();
+                    decode_lossless(value, &mut bytes);
+                    self.rf.anonymize_from(&mut bytes, 0);
+                    s.element(Element::Binary, |s| {
+                        s.batch(|buf| {
+                            buf.extend(HEX_VALUE_OPENING.as_bytes());
+                            for byte in bytes {
+                                buf.push(HEX_DIGITS[(byte >> 4) as usize]);
+                                buf.push(HEX_DIGITS[(byte & 0xf) as usize]);
+                            }
+                            buf.extend(HEX_VALUE_CLOSING.as_bytes());
+                        })
+                    });
+                    return ValueFormatResult::Ok;
+                }
+                if let Some(tc) = self.rf.trace_context.as_ref() {
+                    if let Some(key) = tc.keys().into_iter().find(|key| fs.key_path.equals(key.as_bytes())) {
+                        let mut id = Vec::new();
+                        if value.decode(&mut id).is_ok() {
+                            self.rf.anonymize_from(&mut id, 0);
+                            let id = std::str::from_utf8(&id).unwrap_or_default();
+                            let mut formatted = false;
+                            s.element(Element::TraceId, |s| s.batch(|buf| formatted = tc.format(buf, key, id)));
+                            if formatted {
+                                return ValueFormatResult::Ok;
+                            }
+                        }
+                    }
+                }
+                if let Some(value) = self.rf.bool_strings.as_ref().and_then(|bs| bs.classify(value)) {
+                    return self.format_value(
+                        s,
+                        RawValue::Boolean(value),
+                        fs,
+                        filter,
+                        predefined_filter,
+                        setting,
+                        predefined_setting,
+                    );
+                }
+                if let Some(json) = self.rf.embedded_json(value, fs) {
+                    fs.embedded_json_depth += 1;
+                    let result = FieldFormatter::new(self.rf).format_value(
+                        s,
+                        RawValue::from(&*json),
+                        fs,
+                        filter,
+                        predefined_filter,
+                        setting,
+                        predefined_setting,
+                    );
+                    fs.embedded_json_depth -= 1;
+                    return result;
+                }
+                let result = s.element(fs.value_element.unwrap_or(Element::String), |s| {
                     s.batch(|buf| {
                         let expand = |buf: &mut Vec<u8>| self.add_prefix(buf, fs);
+                        let block = |buf: &mut Vec<u8>| self.add_block_prefix(buf, fs);
                         let xsa = match (fs.expanded, fs.expansion.multiline) {
                             (true, _) => ExtendedSpaceAction::Expand(&expand),
+                            (false, _) if self.rf.multiline_block_indent.is_some() => {
+                                ExtendedSpaceAction::Expand(&block)
+                            }
                             (false, MultilineExpansion::Inline) => ExtendedSpaceAction::Inline,
                             (false, MultilineExpansion::Disabled) => ExtendedSpaceAction::Escape,
                             (false, MultilineExpansion::Standard) => ExtendedSpaceAction::Abort,
                         };
-                        ValueFormatAuto.format(value, buf, xsa.into()).unwrap()
+                        let begin = buf.len();
+                        let result = ValueFormatAuto.format(value, buf, xsa.into()).ok().or_else(|| {
+                            buf.truncate(begin);
+                            buf.extend(value.source().as_bytes());
+                            None
+                        });
+                        self.rf.anonymize_from(buf, begin);
+                        result
                     })
                 });
                 match result {
-                    string::FormatResult::Ok(_) => {}
-                    string::FormatResult::Aborted => {
+                    Some(string::FormatResult::Ok(_)) => {}
+                    Some(string::FormatResult::Aborted) => {
                         return ValueFormatResult::ExpansionNeeded;
                     }
+                    None => {
+                        if self.rf.field_diagnostics {
+                            s.element(Element::FieldWarning, |s| {
+                                s.batch(|buf| buf.extend(self.rf.punctuation.field_warning_indicator.as_bytes()))
+                            });
+                        }
+                    }
                 }
             }
             RawValue::Number(value) => {
-                s.element(Element::Number, |s| s.batch(|buf| buf.extend(value.as_bytes())));
+                if let Some(text) = self
+                    .rf
+                    .elapsed_time_fields
+                    .as_ref()
+                    .filter(|ef| ef.keys.iter().any(|key| fs.key_path.equals(key.as_bytes())))
+                    .and_then(|ef| ef.format(value))
+                {
+                    let element = fs.value_element.unwrap_or(Element::ElapsedTime);
+                    s.element(element, |s| s.batch(|buf| buf.extend(text.as_bytes())));
+                    return ValueFormatResult::Ok;
+                }
+                if let Some(text) = self
+                    .rf
+                    .number_radix
+                    .iter()
+                    .find(|(key, _)| fs.key_path.equals(key.as_bytes()))
+                    .and_then(|(_, radix)| radix.format(value))
+                {
+                    let element = fs.value_element.unwrap_or(Element::Number);
+                    s.element(element, |s| s.batch(|buf| buf.extend(text.as_bytes())));
+                    return ValueFormatResult::Ok;
+                }
+                let element = fs.value_element.unwrap_or_else(|| {
+                    self.rf
+                        .http_status
+                        .as_ref()
+                        .filter(|hs| hs.keys.iter().any(|key| fs.key_path.equals(key.as_bytes())))
+                        .and_then(|hs| hs.element(value))
+                        .unwrap_or_else(|| {
+                            if self.rf.sign_aware_numbers {
+                                number_sign_element(value)
+                            } else {
+                                Element::Number
+                            }
+                        })
+                });
+                let rounded = self.rf.number_rounding.and_then(|r| r.format(value));
+                let text = rounded.as_deref().unwrap_or(value);
+                s.element(element, |s| s.batch(|buf| buf.extend(text.as_bytes())));
             }
             RawValue::Boolean(true) => {
-                s.element(Element::BooleanTrue, |s| s.batch(|buf| buf.extend(b"true")));
+                s.element(fs.value_element.unwrap_or(Element::BooleanTrue), |s| {
+                    s.batch(|buf| buf.extend(self.rf.boolean_labels.0.as_bytes()))
+                });
             }
             RawValue::Boolean(false) => {
-                s.element(Element::BooleanFalse, |s| s.batch(|buf| buf.extend(b"false")));
+                s.element(fs.value_element.unwrap_or(Element::BooleanFalse), |s| {
+                    s.batch(|buf| buf.extend(self.rf.boolean_labels.1.as_bytes()))
+                });
             }
             RawValue::Null => {
-                s.element(Element::Null, |s| s.batch(|buf| buf.extend(b"null")));
+                s.element(fs.value_element.unwrap_or(Element::Null), |s| {
+                    s.batch(|buf| buf.extend(self.rf.null_label.as_bytes()))
+                });
             }
             RawValue::Object(value) => {
-                let item = value.parse().unwrap();
-                if !fs.flatten && (!fs.expanded || value.is_empty()) {
+                let item = match value.parse() {
+                    Ok(item) => item,
+                    Err(err) => return self.format_malformed(s, value.get(), err.into()),
+                };
+                let parens = replace(&mut fs.object_parens, false);
+                let block = fs.block && !value.is_empty();
+                if parens {
+                    s.element(Element::Object, |s| {
+                        s.batch(|buf| buf.extend(self.rf.punctuation.object_opening_parenthesis.as_bytes()));
+                    });
+                    fs.dirty = false;
+                } else if !fs.flatten && !block && (!fs.expanded || value.is_empty()) {
                     s.element(Element::Object, |s| {
-                        s.batch(|buf| buf.push(b'{'));
+                        s.batch(|buf| buf.extend(self.rf.punctuation.object_opening_brace.as_bytes()));
                     });
                 }
                 let mut some_fields_hidden_by_user = false;
                 let mut any_fields_formatted = false;
-                for (k, v) in item.fields.iter() {
-                    if !self.rf.hide_empty_fields || !v.is_empty() {
-                        match self.format(s, k, *v, fs, filter, setting, predefined_filter, predefined_setting) {
+                let fields = if let Some(cmp) = &self.rf.field_sort {
+                    Either::Left(item.fields.iter().sorted_by(|a, b| cmp(&a.0, &b.0)))
+                } else if self.rf.sort_object_keys {
+                    Either::Left(item.fields.iter().sorted_by(|a, b| (*a.0).cmp(&*b.0)))
+                } else {
+                    Either::Right(item.fields.iter())
+                };
+                for (k, v) in fields {
+                    let hidden = (self.rf.hide_null_fields && matches!(v, RawValue::Null))
+                        || (self.rf.hide_empty_fields && v.is_empty());
+                    if !hidden {
+                        fs.object_field_separator_pending =
+                            any_fields_formatted && !fs.flatten && !fs.expanded && !block;
+                        let result = self.format(s, k, *v, fs, filter, setting, predefined_filter, predefined_setting);
+                        fs.object_field_separator_pending = false;
+                        match result {
                             FieldFormatResult::Ok => {
                                 any_fields_formatted = true;
                             }
@@ -1101,7 +4293,18 @@ impl<'a> FieldFormatter<'a> {
                         if fs.expanded {
                             self.rf.expand(s, fs);
                         }
-                        fs.add_element(|| s.batch(|buf| buf.push(b' ')));
+                        if block {
+                            self.block_line_break(s, fs, fs.depth);
+                        } else {
+                            let separated = any_fields_formatted && !fs.expanded;
+                            fs.add_element(|| {
+                                if separated {
+                                    self.object_field_separator(s)
+                                } else {
+                                    s.batch(|buf| buf.push(b' '))
+                                }
+                            });
+                        }
                         s.element(Element::Ellipsis, |s| {
                             s.batch(|buf| buf.extend(self.rf.punctuation.hidden_fields_indicator.as_bytes()))
                         });
@@ -1109,13 +4312,18 @@ impl<'a> FieldFormatter<'a> {
                         fs.some_fields_hidden = true;
                     }
                 }
-                if !fs.flatten && (!fs.expanded || value.is_empty()) {
+                if parens {
+                    s.element(Element::Object, |s| {
+                        s.batch(|buf| buf.extend(self.rf.punctuation.object_closing_parenthesis.as_bytes()));
+                    });
+                    fs.dirty = true;
+                } else if !fs.flatten && !block && (!fs.expanded || value.is_empty()) {
                     s.element(Element::Object, |s| {
                         s.batch(|buf| {
                             if !item.fields.is_empty() {
-                                buf.push(b' ');
+                                buf.extend(self.rf.punctuation.object_closing_brace_padding.as_bytes());
                             }
-                            buf.push(b'}');
+                            buf.extend(self.rf.punctuation.object_closing_brace.as_bytes());
                         });
                     });
                 }
@@ -1125,7 +4333,69 @@ impl<'a> FieldFormatter<'a> {
                     return ValueFormatResult::Empty;
                 }
             }
+            RawValue::Array(value) if fs.flatten && self.rf.flatten_arrays => {
+                let item = match value.parse::<32>() {
+                    Ok(item) => item,
+                    Err(err) => return self.format_malformed(s, value.get(), err.into()),
+                };
+                let mut key = String::new();
+                for (i, v) in item.iter().enumerate() {
+                    key.clear();
+                    key.push('[');
+                    key.push_str(itoa::Buffer::new().format(i));
+                    key.push(']');
+                    fs.key_segment = KeySegment::Index;
+                    let result = self.format(
+                        s,
+                        &key,
+                        *v,
+                        fs,
+                        None,
+                        IncludeExcludeSetting::Unspecified,
+                        None,
+                        IncludeExcludeSetting::Unspecified,
+                    );
+                    if let FieldFormatResult::ExpansionNeeded = result {
+                        return ValueFormatResult::ExpansionNeeded;
+                    }
+                }
+            }
+            RawValue::Array(value) if fs.block && !value.is_empty() => {
+                let item = match value.parse::<32>() {
+                    Ok(item) => item,
+                    Err(err) => return self.format_malformed(s, value.get(), err.into()),
+                };
+                for v in item.iter() {
+                    if !replace(&mut fs.block_item_head, false) {
+                        self.block_line_break(s, fs, fs.depth);
+                    }
+                    s.element(Element::Array, |s| {
+                        s.batch(|buf| buf.extend(BLOCK_ITEM_HEADER.as_bytes()))
+                    });
+                    let nested = is_block_value(*v);
+                    fs.block_item_head = nested;
+                    fs.depth += nested as usize;
+                    let result = self.format_value(
+                        s,
+                        *v,
+                        fs,
+                        None,
+                        None,
+                        IncludeExcludeSetting::Unspecified,
+                        IncludeExcludeSetting::Unspecified,
+                    );
+                    fs.depth -= nested as usize;
+                    fs.block_item_head = false;
+                    if result == ValueFormatResult::ExpansionNeeded {
+                        return result;
+                    }
+                }
+            }
             RawValue::Array(value) => {
+                let item = match value.parse::<32>() {
+                    Ok(item) => item,
+                    Err(err) => return self.format_malformed(s, value.get(), err.into()),
+                };
                 let xb = replace(&mut fs.expanded, false);
                 let inline = fs.expansion.multiline == MultilineExpansion::Inline;
                 let saved_expansion = replace(
@@ -1136,15 +4406,36 @@ impl<'a> FieldFormatter<'a> {
                         &ExpansionProfile::NEVER
                     },
                 );
-                let item = value.parse::<32>().unwrap();
+                let compact = !fs.in_array
+                    && item.iter().next().is_some()
+                    && self
+                        .rf
+                        .compact_arrays
+                        .iter()
+                        .any(|key| fs.key_path.equals(key.as_bytes()))
+                    && item
+                        .iter()
+                        .all(|v| !matches!(v, RawValue::Object(_) | RawValue::Array(_)));
+                let in_array = replace(&mut fs.in_array, true);
                 s.element(Element::Array, |s| {
-                    s.batch(|buf| buf.push(b'['));
-                    let mut first = true;
-                    for v in item.iter() {
-                        if !first {
-                            s.batch(|buf| buf.extend(self.rf.punctuation.array_separator.as_bytes()));
-                        } else {
-                            first = false;
+                    if !compact {
+                        s.batch(|buf| buf.extend(self.rf.punctuation.array_opening_bracket.as_bytes()));
+                    }
+                    for (i, v) in item.iter().enumerate() {
+                        if i != 0 {
+                            if self.rf.array_indices {
+                                s.space();
+                            } else {
+                                s.batch(|buf| buf.extend(self.rf.punctuation.array_separator.as_bytes()));
+                            }
+                        }
+                        if self.rf.array_indices {
+                            s.element(Element::Key, |s| {
+                                s.batch(|buf| buf.extend(itoa::Buffer::new().format(i).as_bytes()))
+                            });
+                            s.element(Element::Field, |s| {
+                                s.batch(|buf| buf.extend(self.rf.punctuation.field_key_value_separator.as_bytes()))
+                            });
                         }
                         _ = self.format_value(
                             s,
@@ -1156,8 +4447,11 @@ impl<'a> FieldFormatter<'a> {
                             IncludeExcludeSetting::Unspecified,
                         );
                     }
-                    s.batch(|buf| buf.push(b']'));
+                    if !compact {
+                        s.batch(|buf| buf.extend(self.rf.punctuation.array_closing_bracket.as_bytes()));
+                    }
                 });
+                fs.in_array = in_array;
                 fs.expansion = saved_expansion;
                 fs.expanded = xb;
             }
@@ -1177,16 +4471,91 @@ impl<'a> FieldFormatter<'a> {
         buf.len() - l0
     }
 
+    /// Renders the raw source of a value that cannot be formatted according to its type.
+    fn format_malformed<S: StylingPush<Buf>>(&self, s: &mut S, raw: &str, err: FormatError) -> ValueFormatResult {
+        log::debug!("failed to format value {raw:?}: {err}");
+        s.element(Element::FieldWarning, |s| {
+            s.batch(|buf| {
+                let begin = buf.len();
+                buf.extend(raw.as_bytes());
+                self.rf.anonymize_from(buf, begin);
+            })
+        });
+        ValueFormatResult::Ok
+    }
+
+    fn add_block_prefix(&self, buf: &mut Vec<u8>, fs: &FormattingStateWithRec) -> usize {
+        buf.push(b'\n');
+        let l0 = buf.len();
+        buf.extend_from_within(fs.prefix.clone());
+        buf.extend(std::iter::repeat_n(
+            b' ',
+            self.rf.multiline_block_indent.unwrap_or_default(),
+        ));
+        buf.len() - l0
+    }
+
+    /// Starts a new line of a value rendered in block style, indented according to the nesting `level`.
+    fn block_line_break<S: StylingPush<Buf>>(&self, s: &mut S, fs: &FormattingStateWithRec, level: usize) {
+        let indent = match self.rf.object_style {
+            ObjectStyle::Block(indent) => indent,
+            _ => 0,
+        };
+        s.reset();
+        s.batch(|buf| {
+            buf.push(b'\n');
+            buf.extend_from_within(fs.prefix.clone());
+            buf.extend(std::iter::repeat_n(b' ', indent * level));
+        });
+    }
+
+    #[inline]
+    fn object_field_separator<S: StylingPush<Buf>>(&self, s: &mut S) {
+        let sep = self.rf.punctuation.object_field_separator.as_str();
+        if sep == " " {
+            s.space();
+        } else {
+            s.element(Element::Object, |s| s.batch(|buf| buf.extend(sep.as_bytes())));
+        }
+    }
+
     #[inline(always)]
     fn begin<S: StylingPush<Buf>>(
         &mut self,
         s: &mut S,
         key: &str,
+        segment: KeySegment,
         value: RawValue<'a>,
         fs: &mut FormattingStateWithRec,
+        key_element: Element,
     ) -> FormattedFieldVariant {
-        if fs.flatten && matches!(value, RawValue::Object(_)) {
-            return FormattedFieldVariant::Flattened(fs.key_prefix.push(key, self.rf.prettify_field_keys));
+        let separated = replace(&mut fs.object_field_separator_pending, false);
+
+        if let RawValue::Array(array) = value {
+            // Malformed arrays are not flattened to be rendered as they are with their keys
+            let wellformed = array.get().trim_start().starts_with('[');
+            if fs.flatten && !fs.collapsed && self.rf.flatten_arrays && !array.is_empty() && wellformed {
+                return FormattedFieldVariant::Flattened(fs.key_prefix.push(key, segment, self.rf.prettify_field_keys));
+            }
+        }
+
+        if let RawValue::Object(object) = value {
+            // Malformed objects are not flattened to be rendered as they are with their keys
+            if fs.flatten && !fs.collapsed && object.get().trim_start().starts_with('{') {
+                return FormattedFieldVariant::Flattened(fs.key_prefix.push(key, segment, self.rf.prettify_field_keys));
+            }
+
+            // Objects with a single key are merged into the parent key, e.g. `error.message=x`
+            if self.rf.collapse_single_key_objects
+                && self.rf.unescape_fields
+                && object.parse().is_ok_and(|object| object.fields.len() == 1)
+            {
+                return FormattedFieldVariant::Collapsed {
+                    n: fs.key_prefix.push(key, segment, self.rf.prettify_field_keys),
+                    flatten: replace(&mut fs.flatten, true),
+                    collapsed: replace(&mut fs.collapsed, true),
+                };
+            }
         }
 
         if !fs.has_fields {
@@ -1199,21 +4568,54 @@ impl<'a> FieldFormatter<'a> {
             }
         }
 
-        let variant = FormattedFieldVariant::Normal { flatten: fs.flatten };
+        let variant = FormattedFieldVariant::Normal {
+            flatten: fs.flatten,
+            collapsed: fs.collapsed,
+        };
 
         if fs.expanded {
             self.rf.expand(s, fs);
         }
+        let breakable = self.rf.wrap_width.is_some() && fs.depth == 0 && fs.dirty && !fs.expanded;
+        let table = fs.expanded && fs.depth == 0 && fs.table_key_width != 0;
+        let mut key_width = 0;
+        let nested_in_block = fs.block;
+        fs.block |= matches!(self.rf.object_style, ObjectStyle::Block(_))
+            && fs.depth == 0
+            && !fs.expanded
+            && !fs.flatten
+            && self.rf.unescape_fields
+            && is_block_value(value);
         fs.depth += 1;
 
-        fs.add_element(|| s.space());
-        s.element(Element::Key, |s| {
+        if nested_in_block {
+            fs.dirty = true;
+            if !replace(&mut fs.block_item_head, false) {
+                self.block_line_break(s, fs, fs.depth - 1);
+            }
+        } else {
+            fs.add_element(|| {
+                if separated {
+                    self.object_field_separator(s)
+                } else {
+                    s.space()
+                }
+            });
+        }
+        s.element(key_element, |s| {
             s.batch(|buf| {
+                if breakable {
+                    fs.wrap_points.extend(buf.iter().rposition(|&c| c == b' '));
+                }
+                let k0 = buf.len();
                 if fs.flatten {
                     fs.flatten = false;
+                    fs.collapsed = false;
                     if fs.key_prefix.len() != 0 {
                         fs.key_prefix.format(buf);
-                        buf.push(b'.');
+                        if segment == KeySegment::Name {
+                            buf.push(b'.');
+                        }
                     }
                 }
                 if self.rf.prettify_field_keys {
@@ -1221,18 +4623,52 @@ impl<'a> FieldFormatter<'a> {
                 } else {
                     buf.extend_from_slice(key.as_bytes());
                 }
+                self.rf.strip_key_prefix_from(buf, k0);
+                key_width = visible_width(&buf[k0..]);
             });
         });
 
+        if table {
+            let padding = fs.table_key_width.saturating_sub(key_width) + 2;
+            s.batch(|buf| buf.extend(std::iter::repeat_n(b' ', padding)));
+        }
+
+        fs.object_parens = self.rf.object_style == ObjectStyle::Parens
+            && fs.depth == 1
+            && !fs.expanded
+            && !fs.flatten
+            && self.rf.unescape_fields
+            && matches!(value, RawValue::Object(_));
+
         let sep = if fs.expanded && matches!(value, RawValue::Object(o) if !o.is_empty()) {
             EXPANDED_OBJECT_HEADER.as_bytes()
+        } else if fs.object_parens || table {
+            b""
+        } else if fs.block {
+            if is_block_value(value) {
+                BLOCK_KEY_HEADER
+            } else {
+                BLOCK_VALUE_HEADER
+            }
+            .as_bytes()
         } else {
-            self.rf.punctuation.field_key_value_separator.as_bytes()
+            let p = &self.rf.punctuation;
+            match value {
+                RawValue::String(_) => &p.string_key_value_separator,
+                RawValue::Number(_) => &p.number_key_value_separator,
+                RawValue::Boolean(_) => &p.boolean_key_value_separator,
+                RawValue::Null => &p.null_key_value_separator,
+                RawValue::Object(_) => &p.object_key_value_separator,
+                RawValue::Array(_) => &p.array_key_value_separator,
+            }
+            .as_bytes()
         };
 
-        s.element(Element::Field, |s| {
-            s.batch(|buf| buf.extend(sep));
-        });
+        if !sep.is_empty() {
+            s.element(Element::Field, |s| {
+                s.batch(|buf| buf.extend(sep));
+            });
+        }
 
         variant
     }
@@ -1240,13 +4676,21 @@ impl<'a> FieldFormatter<'a> {
     #[inline(always)]
     fn end(&mut self, fs: &mut FormattingStateWithRec, v: FormattedFieldVariant) {
         match v {
-            FormattedFieldVariant::Normal { flatten } => {
+            FormattedFieldVariant::Normal { flatten, collapsed } => {
                 fs.depth -= 1;
+                fs.block &= fs.depth != 0;
+                fs.object_parens = false;
                 fs.flatten = flatten;
+                fs.collapsed = collapsed;
             }
             FormattedFieldVariant::Flattened(n) => {
                 fs.key_prefix.pop(n);
             }
+            FormattedFieldVariant::Collapsed { n, flatten, collapsed } => {
+                fs.key_prefix.pop(n);
+                fs.flatten = flatten;
+                fs.collapsed = collapsed;
+            }
         }
     }
 }
@@ -1332,8 +4776,9 @@ impl KeyPrettify for str {
 // ---
 
 enum FormattedFieldVariant {
-    Normal { flatten: bool },
+    Normal { flatten: bool, collapsed: bool },
     Flattened(usize),
+    Collapsed { n: usize, flatten: bool, collapsed: bool },
 }
 
 // ---
@@ -1530,6 +4975,95 @@ pub mod string {
 
     // ---
 
+    /// Describes how a value must be enclosed to be displayed unambiguously.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum QuoteRequirement {
+        /// Safe to display as is.
+        None,
+        /// Needs enclosing double quotes.
+        DoubleQuotes,
+        /// Needs enclosing single quotes.
+        SingleQuotes,
+        /// Needs enclosing backticks.
+        Backticks,
+        /// Contains tabs or line breaks and can be enclosed in backticks only if they are displayed inline,
+        /// otherwise it has the same requirements as [`QuoteRequirement::Expandable`].
+        BackticksInline,
+        /// Cannot be enclosed in quotes as is, but can be expanded to a multi-line block.
+        Expandable,
+        /// Needs JSON escaping in double quotes.
+        JsonEscaped,
+    }
+
+    /// Classifies a decoded value by the quoting it needs to be displayed unambiguously.
+    pub fn classify(value: &[u8]) -> QuoteRequirement {
+        if value.is_empty() {
+            return QuoteRequirement::DoubleQuotes;
+        }
+
+        let mask = value.analyze().chars;
+
+        const NON_PLAIN: Mask = mask!(
+            Flag::Control
+                | Flag::DoubleQuote
+                | Flag::SingleQuote
+                | Flag::Backslash
+                | Flag::Backtick
+                | Flag::Space
+                | Flag::Tab
+                | Flag::Newline
+                | Flag::EqualSign
+        );
+
+        let confusing = || has_unsafe_beginning(value) || matches!(value, b"true" | b"false" | b"null");
+
+        let like_number = || (mask == mask!(Flag::Digit) && value.len() <= MAX_NUMBER_LEN) || looks_like_number(value);
+
+        if !mask.intersects(NON_PLAIN) && !confusing() && !like_number() {
+            return QuoteRequirement::None;
+        }
+
+        if !mask.intersects(Flag::DoubleQuote | Flag::Control | Flag::Tab | Flag::Newline | Flag::Backslash) {
+            return QuoteRequirement::DoubleQuotes;
+        }
+
+        if !mask.intersects(Flag::SingleQuote | Flag::Control | Flag::Tab | Flag::Newline | Flag::Backslash) {
+            return QuoteRequirement::SingleQuotes;
+        }
+
+        const WS: Mask = mask!(Flag::Newline | Flag::Tab | Flag::Space);
+
+        let has_control = mask.contains(Flag::Control);
+        let has_backtick = mask.contains(Flag::Backtick);
+        let has_extended_space = mask.intersects(Flag::Newline | Flag::Tab);
+        let has_non_whitespace = mask.intersects(!WS);
+
+        if has_control || !has_non_whitespace {
+            QuoteRequirement::JsonEscaped
+        } else if has_backtick {
+            QuoteRequirement::Expandable
+        } else if has_extended_space {
+            QuoteRequirement::BackticksInline
+        } else {
+            QuoteRequirement::Backticks
+        }
+    }
+
+    /// Returns true if a bare value with such beginning could be mistaken for something else,
+    /// e.g. an object, an array or a command line flag, or could lose its leading whitespace.
+    /// A lone `-` is commonly used as a placeholder and is considered safe.
+    #[inline(always)]
+    fn has_unsafe_beginning(value: &[u8]) -> bool {
+        match value {
+            [b'{' | b'[', ..] => true,
+            [b'-', c, ..] => c.is_ascii_alphabetic() || *c == b'-',
+            [c, ..] => c.is_ascii_whitespace(),
+            [] => false,
+        }
+    }
+
+    // ---
+
     #[derive(Default)]
     pub struct ValueFormatAuto;
 
@@ -1552,61 +5086,27 @@ pub mod string {
                 AutoTrimFlag::PreserveWhiteSpaceOnly,
             )?;
 
-            let analysis = buf[begin..].analyze();
-            let mask = analysis.chars;
-
-            const NON_PLAIN: Mask = mask!(
-                Flag::Control
-                    | Flag::DoubleQuote
-                    | Flag::SingleQuote
-                    | Flag::Backslash
-                    | Flag::Backtick
-                    | Flag::Space
-                    | Flag::Tab
-                    | Flag::Newline
-                    | Flag::EqualSign
-            );
-
-            let confusing = || matches!(&buf[begin..], [b'{', ..] | [b'[', ..] | b"true" | b"false" | b"null");
-
-            let like_number = || {
-                (mask == mask!(Flag::Digit) && buf[begin..].len() <= MAX_NUMBER_LEN) || looks_like_number(&buf[begin..])
-            };
-
-            if !mask.intersects(NON_PLAIN) && !confusing() && !like_number() {
-                return Ok(FormatResult::Ok(None));
-            }
-
-            if !mask.intersects(Flag::DoubleQuote | Flag::Control | Flag::Tab | Flag::Newline | Flag::Backslash) {
-                buf.push(b'"');
-                buf.push(b'"');
-                buf[begin..].rotate_right(1);
-                return Ok(FormatResult::Ok(None));
-            }
-
-            if !mask.intersects(Flag::SingleQuote | Flag::Control | Flag::Tab | Flag::Newline | Flag::Backslash) {
-                buf.push(b'\'');
-                buf.push(b'\'');
+            let enclose = |buf: &mut Vec<u8>, quote| {
+                buf.push(quote);
+                buf.push(quote);
                 buf[begin..].rotate_right(1);
-                return Ok(FormatResult::Ok(None));
-            }
-
-            const WS: Mask = mask!(Flag::Newline | Flag::Tab | Flag::Space);
-
-            let has_control = mask.contains(Flag::Control);
-            let has_backtick = mask.contains(Flag::Backtick);
-            let has_extended_space = mask.intersects(Flag::Newline | Flag::Tab);
-            let has_non_whitespace = mask.intersects(!WS);
+                Ok(FormatResult::Ok(None))
+            };
 
-            if !has_control && has_non_whitespace {
-                if !has_backtick && (!has_extended_space || matches!(options.xsa, ExtendedSpaceAction::Inline)) {
-                    buf.push(b'`');
-                    buf.push(b'`');
-                    buf[begin..].rotate_right(1);
-                    return Ok(FormatResult::Ok(None));
+            let requirement = match classify(&buf[begin..]) {
+                QuoteRequirement::BackticksInline if matches!(options.xsa, ExtendedSpaceAction::Inline) => {
+                    QuoteRequirement::Backticks
                 }
+                QuoteRequirement::BackticksInline => QuoteRequirement::Expandable,
+                requirement => requirement,
+            };
 
-                match options.xsa {
+            match requirement {
+                QuoteRequirement::None => return Ok(FormatResult::Ok(None)),
+                QuoteRequirement::DoubleQuotes => return enclose(buf, b'"'),
+                QuoteRequirement::SingleQuotes => return enclose(buf, b'\''),
+                QuoteRequirement::Backticks => return enclose(buf, b'`'),
+                QuoteRequirement::Expandable => match options.xsa {
                     ExtendedSpaceAction::Expand(prefix) => {
                         let l0 = buf.len();
                         let pl = prefix(buf);
@@ -1620,7 +5120,8 @@ pub mod string {
                         return Ok(FormatResult::Aborted);
                     }
                     _ => {}
-                }
+                },
+                _ => {}
             }
 
             buf.truncate(begin);
@@ -0,0 +1,156 @@
+//! Masking of personal data, such as IP addresses and email addresses, in rendered values.
+
+// third-party imports
+use enumset::{EnumSet, EnumSetType};
+
+// ---
+
+/// Kind of personal data masked in string values.
+#[derive(EnumSetType, Debug)]
+pub enum AnonymizeTarget {
+    /// IPv4 and IPv6 addresses, the last octet or group is masked, e.g. `192.168.1.*`.
+    Ip,
+    /// Email addresses, the local part is masked, e.g. `*@example.com`.
+    Email,
+}
+
+pub type AnonymizeTargets = EnumSet<AnonymizeTarget>;
+
+/// Appends `text` to `out`, masking the data of the selected kinds.
+pub fn anonymize(text: &[u8], targets: AnonymizeTargets, out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < text.len() {
+        if i != 0 && is_word(text[i - 1]) {
+            out.push(text[i]);
+            i += 1;
+            continue;
+        }
+
+        if targets.contains(AnonymizeTarget::Email) {
+            if let Some(n) = email_local_part(&text[i..]) {
+                out.push(MASK);
+                i += n;
+                continue;
+            }
+        }
+
+        if targets.contains(AnonymizeTarget::Ip) {
+            // IPv4 addresses may follow a colon, e.g. `from:10.0.0.1` or `::ffff:10.0.0.1`,
+            // while IPv6 addresses may not start in the middle of another colon-separated token
+            let prev = i.checked_sub(1).map(|i| text[i]);
+            let v4 = (prev != Some(b'.')).then(|| ipv4(&text[i..])).flatten();
+            let v6 = || (!matches!(prev, Some(b'.' | b':'))).then(|| ipv6(&text[i..])).flatten();
+            if let Some((keep, n)) = v4.or_else(v6) {
+                out.extend_from_slice(&text[i..i + keep]);
+                out.push(MASK);
+                i += n;
+                continue;
+            }
+        }
+
+        out.push(text[i]);
+        i += 1;
+    }
+}
+
+/// Returns the length of the local part if `text` starts with an email address.
+fn email_local_part(text: &[u8]) -> Option<usize> {
+    let n = text.iter().position(|&c| !is_email_local(c))?;
+    if n == 0 || text[n] != b'@' {
+        return None;
+    }
+
+    let domain = &text[n + 1..];
+    let len = domain
+        .iter()
+        .position(|&c| !(c.is_ascii_alphanumeric() || c == b'-' || c == b'.'))
+        .unwrap_or(domain.len());
+    let domain = domain[..len].strip_suffix(b".").unwrap_or(&domain[..len]);
+    let mut labels = domain.split(|&c| c == b'.');
+    let valid = domain.contains(&b'.') && labels.all(|label| !label.is_empty());
+
+    valid.then_some(n)
+}
+
+/// Returns the length of the part to keep and the total length if `text` starts with an IPv4 address.
+fn ipv4(text: &[u8]) -> Option<(usize, usize)> {
+    let mut i = 0;
+    let mut keep = 0;
+    for octet in 0..4 {
+        if octet != 0 {
+            if text.get(i) != Some(&b'.') {
+                return None;
+            }
+            i += 1;
+            keep = i;
+        }
+        let n = text[i..].iter().take(4).take_while(|c| c.is_ascii_digit()).count();
+        if n == 0 || n > 3 || std::str::from_utf8(&text[i..i + n]).ok()?.parse::<u8>().is_err() {
+            return None;
+        }
+        i += n;
+    }
+
+    match text.get(i) {
+        Some(&c) if is_word(c) => None,
+        Some(b'.') if text.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => None,
+        _ => Some((keep, i)),
+    }
+}
+
+/// Returns the length of the part to keep and the total length if `text` starts with an IPv6 address.
+///
+/// Only full addresses with eight groups or compressed addresses containing a single `::` are recognized,
+/// so that times like `12:30:45` are not mistaken for addresses.
+/// Addresses without decimal digits, like `add::bad`, are not recognized either, as they are more likely identifiers.
+fn ipv6(text: &[u8]) -> Option<(usize, usize)> {
+    let n = text
+        .iter()
+        .position(|&c| !(c.is_ascii_hexdigit() || c == b':'))
+        .unwrap_or(text.len());
+    if text.get(n).is_some_and(|&c| is_word(c) || c == b'.') {
+        return None;
+    }
+
+    let token = &text[..n];
+    if !token.iter().any(|c| c.is_ascii_digit()) || token.windows(3).any(|w| w == b":::") {
+        return None;
+    }
+
+    let groups = token.split(|&c| c == b':').collect::<Vec<_>>();
+    if groups.iter().any(|group| group.len() > 4) {
+        return None;
+    }
+    let valid = match token.windows(2).filter(|w| w == b"::").count() {
+        0 => groups.len() == 8 && groups.iter().all(|group| !group.is_empty()),
+        1 => {
+            let inner = &groups[1..groups.len() - 1];
+            (!groups[0].is_empty() || token.starts_with(b"::"))
+                && (!groups[groups.len() - 1].is_empty() || token.ends_with(b"::"))
+                && inner.iter().filter(|group| group.is_empty()).count() <= 1
+                && groups.iter().filter(|group| !group.is_empty()).count() <= 7
+        }
+        _ => false,
+    };
+    if !valid {
+        return None;
+    }
+
+    let keep = token.iter().rposition(|&c| c == b':')? + 1;
+    Some((keep, n))
+}
+
+#[inline]
+fn is_word(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+#[inline]
+fn is_email_local(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'.' | b'_' | b'%' | b'+' | b'-')
+}
+
+const MASK: u8 = b'*';
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,47 @@
+use super::*;
+
+use rstest::rstest;
+
+fn masked(text: &str, targets: AnonymizeTargets) -> String {
+    let mut out = Vec::new();
+    anonymize(text.as_bytes(), targets, &mut out);
+    String::from_utf8(out).unwrap()
+}
+
+#[rstest]
+#[case::ipv4("192.168.1.42", "192.168.1.*")]
+#[case::ipv4_in_text("from 10.0.0.1:8080, retrying", "from 10.0.0.*:8080, retrying")]
+#[case::ipv4_sentence("connected to 10.0.0.1.", "connected to 10.0.0.*.")]
+#[case::ipv4_after_colon("from:192.168.1.42", "from:192.168.1.*")]
+#[case::ipv4_mapped("::ffff:10.0.0.1", "::ffff:10.0.0.*")]
+#[case::ipv4_after_dot("1.192.168.1.42", "1.192.168.1.42")]
+#[case::ipv4_out_of_range("256.1.1.1", "256.1.1.1")]
+#[case::ipv6_tail("1:2:3:4:5:6:7:8:9", "1:2:3:4:5:6:7:8:9")]
+#[case::version("v1.2.3.4", "v1.2.3.4")]
+#[case::too_many_octets("1.2.3.4.5", "1.2.3.4.5")]
+#[case::ipv6_compressed("2001:db8::1", "2001:db8::*")]
+#[case::ipv6_full("2001:0db8:0000:0000:0000:ff00:0042:8329", "2001:0db8:0000:0000:0000:ff00:0042:*")]
+#[case::ipv6_loopback("[::1]:80", "[::*]:80")]
+#[case::ipv6_trailing_compression("fe80::", "fe80::*")]
+#[case::ipv6_letters_only("add::bad", "add::bad")]
+#[case::ipv6_double_compression("1::2::3", "1::2::3")]
+#[case::ipv6_triple_colon("1:::2", "1:::2")]
+#[case::ipv6_leading_colon(":1:2:3:4:5:6:7", ":1:2:3:4:5:6:7")]
+#[case::ipv6_too_many_groups("1:2:3::4:5:6:7:8", "1:2:3::4:5:6:7:8")]
+#[case::time("12:30:45", "12:30:45")]
+#[case::email("a@b.com", "*@b.com")]
+#[case::email_after_scheme("mailto:a@b.com", "mailto:*@b.com")]
+#[case::email_in_text("mail john.doe+x@example.org now", "mail *@example.org now")]
+#[case::not_email("user@localhost", "user@localhost")]
+#[case::plain("nothing to mask", "nothing to mask")]
+fn test_anonymize(#[case] text: &str, #[case] expected: &str) {
+    assert_eq!(masked(text, AnonymizeTargets::all()), expected);
+}
+
+#[test]
+fn test_anonymize_targets() {
+    let text = "a@b.com 192.168.1.42";
+    assert_eq!(masked(text, AnonymizeTarget::Ip.into()), "a@b.com 192.168.1.*");
+    assert_eq!(masked(text, AnonymizeTarget::Email.into()), "*@b.com 192.168.1.42");
+    assert_eq!(masked(text, AnonymizeTargets::empty()), text);
+}
@@ -0,0 +1,210 @@
+//! Columnar (CSV/TSV) output of selected record fields.
+
+// std imports
+use std::ops::Range;
+
+// workspace imports
+use encstr::AnyEncodedString;
+
+// local imports
+use crate::model::{self, RawValue};
+
+// relative imports
+use super::{Buf, RecordWithSourceFormatter, tsv_escape};
+
+// ---
+
+/// Output format of [`ColumnarFormatter`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColumnarFormat {
+    /// Comma-separated values, quoted as described in RFC 4180.
+    #[default]
+    Csv,
+    /// Tab-separated values, with tabs, newlines and backslashes escaped.
+    Tsv,
+}
+
+impl ColumnarFormat {
+    #[inline]
+    fn delimiter(&self) -> u8 {
+        match self {
+            Self::Csv => b',',
+            Self::Tsv => b'\t',
+        }
+    }
+}
+
+// ---
+
+/// A column of [`ColumnarFormatter`] output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Column {
+    Timestamp,
+    Level,
+    Message,
+    /// Field referenced by a key path, with nested object keys separated by dots.
+    Field(String),
+}
+
+impl Column {
+    /// Name of the column, used in the header row.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Timestamp => "ts",
+            Self::Level => "level",
+            Self::Message => "message",
+            Self::Field(path) => path,
+        }
+    }
+
+    /// Appends the decoded value of the column for the given record to `buf`.
+    /// Missing values produce nothing.
+    pub fn format(&self, buf: &mut Buf, rec: &model::Record) {
+        match self {
+            Self::Timestamp => {
+                if let Some(ts) = &rec.ts {
+                    buf.extend(ts.raw().as_bytes());
+                }
+            }
+            Self::Level => {
+                if let Some(level) = rec.level {
+                    buf.extend(level.as_ref().as_bytes());
+                }
+            }
+            Self::Message => {
+                if let Some(value) = rec.message {
+                    format_value(buf, value);
+                }
+            }
+            Self::Field(path) => {
+                if let Some(value) = find(&mut rec.fields().map(|(k, v)| (*k, *v)), path) {
+                    format_value(buf, value);
+                }
+            }
+        }
+    }
+}
+
+impl From<&str> for Column {
+    fn from(path: &str) -> Self {
+        match path {
+            "ts" => Self::Timestamp,
+            "level" => Self::Level,
+            "message" => Self::Message,
+            _ => Self::Field(path.into()),
+        }
+    }
+}
+
+// ---
+
+/// Formats records as CSV or TSV rows containing the selected columns.
+///
+/// Each row is emitted without a trailing line terminator.
+#[derive(Clone, Debug)]
+pub struct ColumnarFormatter {
+    format: ColumnarFormat,
+    columns: Vec<Column>,
+}
+
+impl ColumnarFormatter {
+    pub fn new<I, C>(format: ColumnarFormat, columns: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<Column>,
+    {
+        Self {
+            format,
+            columns: columns.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Appends the header row with column names to `buf`.
+    pub fn format_header(&self, buf: &mut Buf) {
+        self.format_row(buf, |buf, column| buf.extend(column.name().as_bytes()));
+    }
+
+    /// Appends a row with values of the selected columns of `rec` to `buf`.
+    pub fn format_record(&self, buf: &mut Buf, rec: &model::Record) {
+        self.format_row(buf, |buf, column| column.format(buf, rec));
+    }
+
+    fn format_row<F: Fn(&mut Buf, &Column)>(&self, buf: &mut Buf, f: F) {
+        let mut cell = Vec::new();
+        for (i, column) in self.columns.iter().enumerate() {
+            if i != 0 {
+                buf.push(self.format.delimiter());
+            }
+            cell.clear();
+            f(&mut cell, column);
+            match self.format {
+                ColumnarFormat::Csv => csv_escape(buf, &cell),
+                ColumnarFormat::Tsv => tsv_escape(buf, &cell),
+            }
+        }
+    }
+}
+
+impl RecordWithSourceFormatter for ColumnarFormatter {
+    #[inline]
+    fn format_record(&self, buf: &mut Buf, _: Range<usize>, rec: model::RecordWithSource) {
+        ColumnarFormatter::format_record(self, buf, rec.record)
+    }
+}
+
+// ---
+
+fn find<'a>(fields: &mut dyn Iterator<Item = (&str, RawValue<'a>)>, path: &str) -> Option<RawValue<'a>> {
+    for (key, value) in fields {
+        if key == path {
+            return Some(value);
+        }
+        if let (Some(rest), RawValue::Object(object)) = (path.strip_prefix(key), value) {
+            if let Some(rest) = rest.strip_prefix('.') {
+                if let Ok(object) = object.parse() {
+                    if let Some(value) = find(&mut object.fields.iter().map(|(k, v)| (&**k, *v)), rest) {
+                        return Some(value);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+fn format_value(buf: &mut Buf, value: RawValue) {
+    match value {
+        RawValue::String(s) => {
+            let l0 = buf.len();
+            if s.decode(&mut *buf).is_err() {
+                buf.truncate(l0);
+                buf.extend(s.source().as_bytes());
+            }
+        }
+        RawValue::Null => {}
+        _ => buf.extend(value.raw_str().as_bytes()),
+    }
+}
+
+fn csv_escape(buf: &mut Buf, value: &[u8]) {
+    if !value.iter().any(|&c| matches!(c, b',' | b'"' | b'\r' | b'\n')) {
+        buf.extend(value);
+        return;
+    }
+
+    buf.push(b'"');
+    for &c in value {
+        if c == b'"' {
+            buf.push(b'"');
+        }
+        buf.push(c);
+    }
+    buf.push(b'"');
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,58 @@
+use super::*;
+
+use crate::model::{Parser, ParserSettings, RawRecord};
+
+fn format(formatter: &ColumnarFormatter, input: &str) -> String {
+    let raw = RawRecord::parser().parse(input.as_bytes()).next().unwrap().unwrap();
+    let rec = Parser::new(ParserSettings::default()).parse(&raw.record);
+    let mut buf = Vec::new();
+    formatter.format_record(&mut buf, &rec);
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn test_csv() {
+    let formatter = ColumnarFormatter::new(ColumnarFormat::Csv, ["ts", "level", "message", "a.b", "c", "missing"]);
+
+    let mut buf = Vec::new();
+    formatter.format_header(&mut buf);
+    assert_eq!(String::from_utf8(buf).unwrap(), "ts,level,message,a.b,c,missing");
+
+    let input = r#"{"ts":"2020-01-01T00:00:00Z","level":"info","msg":"hello","a":{"b":42},"c":true}"#;
+    assert_eq!(format(&formatter, input), "2020-01-01T00:00:00Z,info,hello,42,true,");
+}
+
+#[test]
+fn test_csv_quoting() {
+    let formatter = ColumnarFormatter::new(ColumnarFormat::Csv, ["message", "a", "b", "c"]);
+
+    let input = r#"{"msg":"hello, world","a":"say \"hi\"","b":"line1\nline2","c":null}"#;
+    assert_eq!(
+        format(&formatter, input),
+        "\"hello, world\",\"say \"\"hi\"\"\",\"line1\nline2\","
+    );
+}
+
+#[test]
+fn test_tsv() {
+    let formatter = ColumnarFormatter::new(ColumnarFormat::Tsv, ["message", "a", "b"]);
+
+    let input = r#"{"msg":"hello, world","a":"x\ty","b":[1,2]}"#;
+    assert_eq!(format(&formatter, input), "hello, world\tx\\ty\t[1,2]");
+}
+
+#[test]
+fn test_tsv_multiline() {
+    let formatter = ColumnarFormatter::new(ColumnarFormat::Tsv, ["message", "a"]);
+
+    let input = r#"{"msg":"line1\nline2","a":"x\t\ty"}"#;
+    assert_eq!(format(&formatter, input), "line1\\nline2\tx\\t\\ty");
+}
+
+#[test]
+fn test_dotted_key_paths() {
+    let formatter = ColumnarFormatter::new(ColumnarFormat::Csv, ["a.b.c", "x.y", "a.d"]);
+
+    let input = r#"{"a":{"b":{"c":"deep"}},"x.y":"flat"}"#;
+    assert_eq!(format(&formatter, input), "deep,flat,");
+}
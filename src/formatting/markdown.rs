@@ -0,0 +1,107 @@
+//! Markdown table output of selected record fields.
+
+// std imports
+use std::borrow::Borrow;
+
+// local imports
+use crate::model;
+
+// relative imports
+use super::{Buf, columnar::Column};
+
+// ---
+
+/// Formats records as a GitHub-flavored Markdown table containing the selected columns.
+///
+/// All rows are needed to size the columns, so records are formatted as a batch.
+/// Pipes in cell values are escaped and line breaks are replaced with `<br>`.
+#[derive(Clone, Debug)]
+pub struct MarkdownTableFormatter {
+    columns: Vec<Column>,
+}
+
+impl MarkdownTableFormatter {
+    pub fn new<I, C>(columns: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<Column>,
+    {
+        Self {
+            columns: columns.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Appends the table with a header row and a row for each record to `buf`, each row followed by a line break.
+    pub fn format_table<'a, I, R>(&self, buf: &mut Buf, recs: I)
+    where
+        I: IntoIterator<Item = R>,
+        R: Borrow<model::Record<'a>>,
+    {
+        let header = self
+            .columns
+            .iter()
+            .map(|column| escape(column.name().as_bytes()))
+            .collect();
+        let mut rows: Vec<Vec<String>> = vec![header];
+        let mut cell = Vec::new();
+        for rec in recs {
+            let row = self.columns.iter().map(|column| {
+                cell.clear();
+                column.format(&mut cell, rec.borrow());
+                escape(&cell)
+            });
+            rows.push(row.collect());
+        }
+
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|i| rows.iter().map(|row| row[i].chars().count()).max().unwrap_or(0).max(3))
+            .collect();
+
+        for (n, row) in rows.iter().enumerate() {
+            format_row(
+                buf,
+                row.iter().zip(&widths).map(|(cell, &width)| (cell.as_str(), width)),
+                b' ',
+            );
+            if n == 0 {
+                format_row(buf, widths.iter().map(|&width| ("", width)), b'-');
+            }
+        }
+    }
+}
+
+// ---
+
+fn format_row<'a, I: IntoIterator<Item = (&'a str, usize)>>(buf: &mut Buf, cells: I, fill: u8) {
+    buf.push(b'|');
+    for (cell, width) in cells {
+        buf.push(b' ');
+        buf.extend(cell.as_bytes());
+        buf.extend(std::iter::repeat_n(fill, width.saturating_sub(cell.chars().count())));
+        buf.extend(b" |");
+    }
+    buf.push(b'\n');
+}
+
+fn escape(value: &[u8]) -> String {
+    let value = String::from_utf8_lossy(value);
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '|' => result.push_str("\\|"),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => result.push_str("<br>"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests;
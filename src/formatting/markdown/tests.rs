@@ -0,0 +1,36 @@
+use super::*;
+
+use crate::model::{Parser, ParserSettings, RawRecord};
+
+#[test]
+fn test_markdown_table() {
+    let formatter = MarkdownTableFormatter::new(["ts", "level", "message", "a.b", "missing"]);
+
+    let inputs = [
+        r#"{"ts":"2020-01-01T00:00:00Z","level":"info","msg":"hello","a":{"b":42}}"#,
+        r#"{"ts":"2020-01-01T00:00:01Z","level":"error","msg":"x | y\r\nz","a":{"b":"c"}}"#,
+    ];
+    let raw = inputs.map(|input| RawRecord::parser().parse(input.as_bytes()).next().unwrap().unwrap());
+    let parser = Parser::new(ParserSettings::default());
+    let recs = raw.iter().map(|raw| parser.parse(&raw.record)).collect::<Vec<_>>();
+
+    let mut buf = Vec::new();
+    formatter.format_table(&mut buf, &recs);
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        concat!(
+            "| ts                   | level | message     | a.b | missing |\n",
+            "| -------------------- | ----- | ----------- | --- | ------- |\n",
+            "| 2020-01-01T00:00:00Z | info  | hello       | 42  |         |\n",
+            "| 2020-01-01T00:00:01Z | error | x \\| y<br>z | c   |         |\n",
+        )
+    );
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(escape(b"a|b"), r"a\|b");
+    assert_eq!(escape(br"a\|b"), r"a\\\|b");
+    assert_eq!(escape(br"a\b"), r"a\\b");
+    assert_eq!(escape(b"a\r\nb\nc"), "a<br>b<br>c");
+}
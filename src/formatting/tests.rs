@@ -1,4 +1,4 @@
-use chrono::{Offset, Utc};
+use chrono::{FixedOffset, Offset, Utc};
 use rstest::rstest;
 
 use super::{
@@ -8,10 +8,10 @@ use super::{
 use crate::{
     datefmt::LinuxDateFormat,
     model::{
-        Caller, Level, Parser, ParserSettings, RawArray, RawObject, RawRecord, Record, RecordFields,
+        Caller, Level, Parser, ParserSettings, RawArray, RawObject, RawRecord, RawRecordParser, Record, RecordFields,
         RecordWithSourceConstructor,
     },
-    settings::{AsciiMode, MessageFormat, MessageFormatting},
+    settings::{AsciiMode, MessageFormat, MessageFormatting, Punctuation},
     testing::Sample,
     timestamp::Timestamp,
     timezone::Tz,
@@ -19,28 +19,6 @@ use crate::{
 
 use encstr::EncodedString;
 
-trait FormatToVec {
-    fn format_to_vec(&self, rec: &Record) -> Vec<u8>;
-}
-
-trait FormatToString {
-    fn format_to_string(&self, rec: &Record) -> String;
-}
-
-impl FormatToVec for RecordFormatter {
-    fn format_to_vec(&self, rec: &Record) -> Vec<u8> {
-        let mut buf = Vec::new();
-        self.format_record(&mut buf, 0..0, rec);
-        buf
-    }
-}
-
-impl FormatToString for RecordFormatter {
-    fn format_to_string(&self, rec: &Record) -> String {
-        String::from_utf8(self.format_to_vec(rec)).unwrap()
-    }
-}
-
 fn formatter() -> RecordFormatterBuilder {
     RecordFormatterBuilder::sample()
         .with_theme(Sample::sample())
@@ -53,18 +31,24 @@ fn formatter() -> RecordFormatterBuilder {
             expansion: Default::default(),
             message: MessageFormatting {
                 format: MessageFormat::AutoQuoted,
+                template: None,
             },
             punctuation: Sample::sample(),
             prettify_field_keys: None,
+            sort_object_keys: None,
         })
 }
 
 fn format(rec: &Record) -> String {
-    formatter().build().format_to_string(rec)
+    formatter().build().format_to_string(rec).unwrap()
 }
 
 fn format_no_color(rec: &Record) -> String {
-    formatter().with_theme(Default::default()).build().format_to_string(rec)
+    formatter()
+        .with_theme(Default::default())
+        .build()
+        .format_to_string(rec)
+        .unwrap()
 }
 
 fn format_no_color_inline(rec: &Record) -> String {
@@ -75,6 +59,7 @@ fn format_no_color_inline(rec: &Record) -> String {
         })
         .build()
         .format_to_string(rec)
+        .unwrap()
 }
 
 fn format_no_color_expand(rec: &Record) -> String {
@@ -85,6 +70,7 @@ fn format_no_color_expand(rec: &Record) -> String {
         })
         .build()
         .format_to_string(rec)
+        .unwrap()
 }
 
 fn json_raw_value(s: &str) -> Box<json::value::RawValue> {
@@ -123,7 +109,7 @@ fn test_nested_objects() {
     );
 
     assert_eq!(
-        &formatter().with_flatten(true).build().format_to_string(&rec),
+        &formatter().with_flatten(true).build().format_to_string(&rec).unwrap(),
         "\u{1b}[0;2;3m00-01-02 03:04:05.123 \u{1b}[0;36m|\u{1b}[0;95mDBG\u{1b}[0;36m|\u{1b}[0;2;3m \u{1b}[0;2;4mtl:\u{1b}[0m \u{1b}[0;1mtm \u{1b}[0;32mk-a.va.kb\u{1b}[0;2m=\u{1b}[0;94m42 \u{1b}[0;32mk-a.va.kc\u{1b}[0;2m=\u{1b}[0;94m43\u{1b}[0;2;3m -> tc\u{1b}[0m",
     );
 }
@@ -161,7 +147,11 @@ fn test_timestamp_none_always_show() {
     };
 
     assert_eq!(
-        &formatter().with_always_show_time(true).build().format_to_string(&rec),
+        &formatter()
+            .with_always_show_time(true)
+            .build()
+            .format_to_string(&rec)
+            .unwrap(),
         "\u{1b}[0;2;3m##-##-## ##:##:##.###\u{1b}[0m \u{1b}[0;1mtm\u{1b}[0m",
     );
 }
@@ -184,7 +174,11 @@ fn test_level_none_always_show() {
     };
 
     assert_eq!(
-        &formatter().with_always_show_level(true).build().format_to_string(&rec),
+        &formatter()
+            .with_always_show_level(true)
+            .build()
+            .format_to_string(&rec)
+            .unwrap(),
         "\u{1b}[0;36m|###|\u{1b}[0m \u{1b}[0;1mtm\u{1b}[0m"
     );
 }
@@ -537,7 +531,7 @@ fn test_nested_hidden_fields_flatten() {
     }
     .build();
 
-    assert_eq!(&formatter.format_to_string(&rec), "a.b.c.d=1 ...");
+    assert_eq!(&formatter.format_to_string(&rec).unwrap(), "a.b.c.d=1 ...");
 }
 
 #[test]
@@ -556,7 +550,7 @@ fn test_nested_hidden_fields_group_unhide() {
     }
     .build();
 
-    assert_eq!(&formatter.format_to_string(&rec), "a.b.f=3 ...");
+    assert_eq!(&formatter.format_to_string(&rec).unwrap(), "a.b.f=3 ...");
 }
 
 #[test]
@@ -576,7 +570,10 @@ fn test_nested_hidden_fields_no_flatten() {
     }
     .build();
 
-    assert_eq!(&formatter.format_to_string(&rec), "a={ b={ c={ d=1 ... } ... } }");
+    assert_eq!(
+        &formatter.format_to_string(&rec).unwrap(),
+        "a={ b={ c={ d=1 ... } ... } }"
+    );
 }
 
 #[test]
@@ -654,7 +651,7 @@ fn test_delimited_message_with_colors() {
         ..Default::default()
     };
     assert_eq!(
-        formatter.format_to_string(&rec),
+        formatter.format_to_string(&rec).unwrap(),
         "\u{1b}[0;2;3m00-01-02 03:04:05.123\u{1b}[0m \u{1b}[0;2;3m:: \u{1b}[0;32ma\u{1b}[0;2m=\u{1b}[0;94m42\u{1b}[0m"
     );
 }
@@ -671,31 +668,31 @@ fn test_auto_quoted_message() {
         fields: RecordFields::from_slice(&[("a", RawValue::Number("42"))]),
         ..Default::default()
     };
-    assert_eq!(formatter.format_to_string(&rec), "m a=42");
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "m a=42");
 
     rec.fields = Default::default();
-    assert_eq!(formatter.format_to_string(&rec), "m");
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "m");
 
     rec.message = Some(EncodedString::raw("m x=1").into());
-    assert_eq!(formatter.format_to_string(&rec), r#""m x=1""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""m x=1""#);
 
     rec.message = Some(EncodedString::raw("m '1'").into());
-    assert_eq!(formatter.format_to_string(&rec), r#"m '1'"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"m '1'"#);
 
     rec.message = Some(EncodedString::raw(r#"m '1' and "2""#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#"m '1' and "2""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"m '1' and "2""#);
 
     rec.message = Some(EncodedString::raw(r#"m x='1' and y="2""#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#"`m x='1' and y="2"`"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"`m x='1' and y="2"`"#);
 
     rec.message = Some(EncodedString::raw("'m' `1`").into());
-    assert_eq!(formatter.format_to_string(&rec), r#""'m' `1`""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""'m' `1`""#);
 
     rec.message = Some(EncodedString::raw("").into());
-    assert_eq!(formatter.format_to_string(&rec), r#""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""#);
 
     rec.ts = Some(Timestamp::new("2000-01-02T03:04:05.123Z"));
-    assert_eq!(formatter.format_to_string(&rec), r#"00-01-02 03:04:05.123"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"00-01-02 03:04:05.123"#);
 }
 
 #[test]
@@ -710,23 +707,23 @@ fn test_always_quoted_message() {
         fields: RecordFields::from_slice(&[("a", RawValue::Number("42"))]),
         ..Default::default()
     };
-    assert_eq!(formatter.format_to_string(&rec), r#""m" a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""m" a=42"#);
 
     rec.message = Some(EncodedString::raw("m x='1'").into());
-    assert_eq!(formatter.format_to_string(&rec), r#""m x='1'" a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""m x='1'" a=42"#);
 
     rec.message = Some(EncodedString::raw(r#""m" x='1'"#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#"`"m" x='1'` a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"`"m" x='1'` a=42"#);
 
     rec.message = Some(EncodedString::raw(r#"m x="1""#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#"'m x="1"' a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"'m x="1"' a=42"#);
 
     rec.message = Some(EncodedString::raw(r#"m `x`="1"|'2'"#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#""m `x`=\"1\"|'2'" a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""m `x`=\"1\"|'2'" a=42"#);
 
     rec.fields = Default::default();
     rec.message = Some(EncodedString::raw("m").into());
-    assert_eq!(formatter.format_to_string(&rec), r#""m""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""m""#);
 }
 
 #[test]
@@ -741,10 +738,10 @@ fn test_always_double_quoted_message() {
         fields: RecordFields::from_slice(&[("a", RawValue::Number("42"))]),
         ..Default::default()
     };
-    assert_eq!(formatter.format_to_string(&rec), r#""m" a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""m" a=42"#);
 
     rec.fields = Default::default();
-    assert_eq!(formatter.format_to_string(&rec), r#""m""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""m""#);
 }
 
 #[test]
@@ -759,10 +756,10 @@ fn test_raw_message() {
         fields: RecordFields::from_slice(&[("a", RawValue::Number("42"))]),
         ..Default::default()
     };
-    assert_eq!(formatter.format_to_string(&rec), r#"m 1 a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"m 1 a=42"#);
 
     rec.fields = Default::default();
-    assert_eq!(formatter.format_to_string(&rec), r#"m 1"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"m 1"#);
 }
 
 #[test]
@@ -777,25 +774,28 @@ fn test_delimited_message() {
         fields: RecordFields::from_slice(&[("a", RawValue::Number("42"))]),
         ..Default::default()
     };
-    assert_eq!(formatter.format_to_string(&rec), r#""'message' 1" :: a=42"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""'message' 1" :: a=42"#);
 
     rec.message = Some(EncodedString::raw(r#"`'message' "1"`"#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#""`'message' \"1\"`" :: a=42"#);
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        r#""`'message' \"1\"`" :: a=42"#
+    );
 
     rec.fields = Default::default();
-    assert_eq!(formatter.format_to_string(&rec), r#""`'message' \"1\"`""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""`'message' \"1\"`""#);
 
     rec.message = Some(EncodedString::raw("'message' 1").into());
-    assert_eq!(formatter.format_to_string(&rec), r#""'message' 1""#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#""'message' 1""#);
 
     rec.message = Some(EncodedString::raw(r#""message" 1"#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#"'"message" 1'"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"'"message" 1'"#);
 
     rec.message = Some(EncodedString::raw(r#""message" '1'"#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#"`"message" '1'`"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"`"message" '1'`"#);
 
     rec.message = Some(EncodedString::raw(r#"message\twith\ttabs"#).into());
-    assert_eq!(formatter.format_to_string(&rec), r#"message\twith\ttabs"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"message\twith\ttabs"#);
 }
 
 #[test]
@@ -823,8 +823,8 @@ fn test_ascii_mode() {
         .build();
 
     // Get formatted output from both formatters (already without ANSI codes)
-    let ascii_result = formatter_ascii.format_to_string(&rec);
-    let utf8_result = formatter_utf8.format_to_string(&rec);
+    let ascii_result = formatter_ascii.format_to_string(&rec).unwrap();
+    let utf8_result = formatter_utf8.format_to_string(&rec).unwrap();
 
     // Verify ASCII mode uses ASCII arrow
     assert!(ascii_result.contains("-> "), "ASCII mode should use ASCII arrow");
@@ -868,8 +868,8 @@ fn test_punctuation_with_ascii_mode() {
     let rec = Record::sample();
 
     // Format the record with both formatters
-    let ascii_result = ascii_formatter.format_to_string(&rec);
-    let utf8_result = utf8_formatter.format_to_string(&rec);
+    let ascii_result = ascii_formatter.format_to_string(&rec).unwrap();
+    let utf8_result = utf8_formatter.format_to_string(&rec).unwrap();
 
     // ASCII result should contain the ASCII arrow
     assert!(ascii_result.contains("-> "), "ASCII result missing expected arrow");
@@ -921,8 +921,8 @@ fn test_hide_empty_fields_nested_flatten() {
     }
     .build();
 
-    let result_hide = formatter_hide.format_to_string(&rec);
-    let result_show = formatter_show.format_to_string(&rec);
+    let result_hide = formatter_hide.format_to_string(&rec).unwrap();
+    let result_show = formatter_show.format_to_string(&rec).unwrap();
 
     // When hiding empty fields, should only show non-empty nested field and ellipsis
     assert_eq!(&result_hide, "data.nested.nonempty=value ...");
@@ -959,8 +959,8 @@ fn test_hide_empty_fields_nested_no_flatten() {
     }
     .build();
 
-    let result_hide = formatter_hide.format_to_string(&rec);
-    let result_show = formatter_show.format_to_string(&rec);
+    let result_hide = formatter_hide.format_to_string(&rec).unwrap();
+    let result_show = formatter_show.format_to_string(&rec).unwrap();
 
     // When hiding empty fields, should only show non-empty nested field and ellipsis
     assert_eq!(&result_hide, "data={ nested={ nonempty=value ... } ... }");
@@ -987,7 +987,7 @@ fn test_hide_empty_fields_no_ellipsis_when_no_empty_fields() {
     }
     .build();
 
-    let result_hide = formatter_hide.format_to_string(&rec);
+    let result_hide = formatter_hide.format_to_string(&rec).unwrap();
 
     // When no empty fields exist, should not show ellipsis
     assert_eq!(
@@ -1037,7 +1037,7 @@ fn test_expand_with_hidden() {
         ..Default::default()
     };
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert_eq!(
         &result,
         "m\n  > a=1\n  > c:\n    > x=10\n    > y=20\n    > ...\n  > d=4\n  > ..."
@@ -1070,7 +1070,7 @@ fn test_expand_with_hidden_and_flatten() {
         ..Default::default()
     };
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert_eq!(&result, "m\n  > a=1\n  > b=2\n  > c.x=10\n  > c.y=20\n  > d=4\n  > ...");
 }
 
@@ -1096,7 +1096,7 @@ fn test_expand_object() {
         ..Default::default()
     };
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert_eq!(
         &result,
         "m\n  > a=1\n  > b=2\n  > c:\n    > x=10\n    > y=|=>\n       \tsome\n       \tmultiline\n       \tvalue\n    > z=30\n  > d=4"
@@ -1139,7 +1139,7 @@ fn test_expand_no_filter() {
     }
     .build();
 
-    assert_eq!(formatter.format_to_string(&rec), r#"m a=1 b=2 c=3"#);
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), r#"m a=1 b=2 c=3"#);
 }
 
 #[test]
@@ -1167,8 +1167,8 @@ fn test_hide_empty_objects_flatten() {
     }
     .build();
 
-    let result_hide = formatter_hide.format_to_string(&rec);
-    let result_show = formatter_show.format_to_string(&rec);
+    let result_hide = formatter_hide.format_to_string(&rec).unwrap();
+    let result_show = formatter_show.format_to_string(&rec).unwrap();
 
     // When hiding empty fields, empty objects and objects with all empty fields should be hidden
     assert_eq!(&result_hide, "data.has-value.b=value ...");
@@ -1205,8 +1205,8 @@ fn test_hide_empty_objects_no_flatten() {
     }
     .build();
 
-    let result_hide = formatter_hide.format_to_string(&rec);
-    let result_show = formatter_show.format_to_string(&rec);
+    let result_hide = formatter_hide.format_to_string(&rec).unwrap();
+    let result_show = formatter_show.format_to_string(&rec).unwrap();
 
     // When hiding empty fields, empty objects and objects with all empty fields should be hidden
     assert_eq!(&result_hide, "data={ has-value={ b=value ... } ... }");
@@ -1232,7 +1232,7 @@ fn test_hide_deeply_nested_empty_objects() {
     }
     .build();
 
-    let result_hide = formatter_hide.format_to_string(&rec);
+    let result_hide = formatter_hide.format_to_string(&rec).unwrap();
 
     // Deeply nested objects with only empty fields should be completely hidden
     assert_eq!(&result_hide, "...");
@@ -1255,7 +1255,7 @@ fn test_expand_multiline_message_always() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     // With ExpansionMode::Always, multiline message should be formatted as a field
     // msg=|=> followed by properly indented lines
@@ -1293,7 +1293,7 @@ fn test_expand_multiline_message_always_with_level_delimited() {
     .with_message_format(new_message_format(MessageFormat::Delimited, "›"))
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     // With ExpansionMode::Always and Delimited message format, multiline message
     // should be formatted as a field msg=|=> followed by properly indented lines.
@@ -1335,7 +1335,7 @@ fn test_expand_multiline_message_always_with_level() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     // With ExpansionMode::Always, multiline message should be formatted as a field
     // msg=|=> followed by properly indented lines, even when level is present
@@ -1381,11 +1381,11 @@ fn test_expand_without_message() {
     .build();
 
     assert_eq!(
-        formatter.format_to_string(&rec("1", None)),
+        formatter.format_to_string(&rec("1", None)).unwrap(),
         format!("{mh}\n  > a=1", mh = EXPANDED_MESSAGE_HEADER)
     );
     assert_eq!(
-        formatter.format_to_string(&rec("1", Some(ts))),
+        formatter.format_to_string(&rec("1", Some(ts))).unwrap(),
         format!(
             concat!("00-01-02 03:04:05.123 {mh}\n", "                        > a=1"),
             mh = EXPANDED_MESSAGE_HEADER
@@ -1476,11 +1476,11 @@ fn test_expand_mode_inline() {
         .build();
 
     assert_eq!(
-        formatter.format_to_string(&rec("some single-line message")),
+        formatter.format_to_string(&rec("some single-line message")).unwrap(),
         r#"a="some single-line message""#
     );
     assert_eq!(
-        formatter.format_to_string(&rec("some\nmultiline\nmessage")),
+        formatter.format_to_string(&rec("some\nmultiline\nmessage")).unwrap(),
         "a=`some\nmultiline\nmessage`"
     );
 }
@@ -1578,7 +1578,7 @@ fn test_message_formats_with_expansion(
         ..Default::default()
     };
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert_eq!(result, expected);
 }
 
@@ -1632,7 +1632,8 @@ mod string {
 
     use crate::formatting::string::{
         ExtendedSpaceAction, Format, MessageFormatAlwaysQuoted, MessageFormatAutoQuoted, MessageFormatDelimited,
-        MessageFormatDoubleQuoted, MessageFormatRaw, Result, ValueFormatAuto, ValueFormatDoubleQuoted, ValueFormatRaw,
+        MessageFormatDoubleQuoted, MessageFormatRaw, QuoteRequirement, Result, ValueFormatAuto,
+        ValueFormatDoubleQuoted, ValueFormatRaw, classify,
     };
     use encstr::{EncodedString, json::JsonEncodedString, raw::RawString};
 
@@ -1860,6 +1861,21 @@ mod string {
         assert_eq!(format(&ValueFormatAuto, input), expected);
     }
 
+    #[rstest]
+    #[case::short_flag("-v", r#""-v""#)]
+    #[case::long_flag("--verbose", r#""--verbose""#)]
+    #[case::just_minus("-", "-")]
+    #[case::double_minus("--", r#""--""#)]
+    #[case::negative_number("-5", r#""-5""#)]
+    #[case::inner_minus("a-v", "a-v")]
+    #[case::spaces_only("   ", r#""   ""#)]
+    #[case::leading_space(" a", r#"" a""#)]
+    #[case::not_json_object("{not json", r#""{not json""#)]
+    #[case::not_json_array("[not json", r#""[not json""#)]
+    fn test_value_format_auto_unsafe_beginning(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(format(&ValueFormatAuto, input), expected);
+    }
+
     // ---
     // Test 10: ValueFormatRaw - passthrough without modification
     // ---
@@ -2403,6 +2419,30 @@ mod string {
         let formatter = MessageFormatAutoQuoted.rtrim(1);
         assert_eq!(format(&formatter, "key=value"), r#""key=value"#); // Trim closing quote
     }
+
+    // ---
+    // classify
+    // ---
+
+    #[rstest]
+    #[case::empty("", QuoteRequirement::DoubleQuotes)]
+    #[case::plain("hello", QuoteRequirement::None)]
+    #[case::number("42", QuoteRequirement::DoubleQuotes)]
+    #[case::keyword("null", QuoteRequirement::DoubleQuotes)]
+    #[case::object_like("{a", QuoteRequirement::DoubleQuotes)]
+    #[case::space("two words", QuoteRequirement::DoubleQuotes)]
+    #[case::equal_sign("a=b", QuoteRequirement::DoubleQuotes)]
+    #[case::double_quote(r#"say "hi""#, QuoteRequirement::SingleQuotes)]
+    #[case::both_quotes(r#"it's "hi""#, QuoteRequirement::Backticks)]
+    #[case::backslash(r"a\b", QuoteRequirement::Backticks)]
+    #[case::backslash_and_backtick(r"a\`b", QuoteRequirement::Expandable)]
+    #[case::newline("a\nb", QuoteRequirement::BackticksInline)]
+    #[case::all_quotes(r#"`it's "hi"`"#, QuoteRequirement::Expandable)]
+    #[case::control("a\x01b", QuoteRequirement::JsonEscaped)]
+    #[case::whitespace_only("\t\n", QuoteRequirement::JsonEscaped)]
+    fn test_classify(#[case] input: &str, #[case] expected: QuoteRequirement) {
+        assert_eq!(classify(input.as_bytes()), expected);
+    }
 }
 
 #[test]
@@ -2423,7 +2463,8 @@ fn test_array_of_objects() {
             mode: ExpansionMode::Always,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     // The array should contain the objects, not be empty
     assert!(
@@ -2466,7 +2507,8 @@ fn test_expansion_mode_never_simple() {
             mode: ExpansionMode::Never,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(!output.contains('\n') || output.lines().count() == 1);
     assert!(output.contains("simple message"));
@@ -2489,7 +2531,8 @@ fn test_expansion_mode_never_multiline() {
             mode: ExpansionMode::Never,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("line1"));
     assert!(output.contains("error="));
@@ -2511,7 +2554,8 @@ fn test_expansion_mode_inline_simple() {
             mode: ExpansionMode::Inline,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("simple message"));
     assert!(output.contains("key=value"));
@@ -2533,7 +2577,8 @@ fn test_expansion_mode_inline_multiline() {
             mode: ExpansionMode::Inline,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("line1"));
     assert!(output.contains("line2"));
@@ -2557,7 +2602,8 @@ fn test_expansion_mode_auto_simple() {
             mode: ExpansionMode::Auto,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("simple message"));
     assert!(output.contains("key=value"));
@@ -2579,7 +2625,8 @@ fn test_expansion_mode_auto_multiline_message() {
             mode: ExpansionMode::Auto,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("line1"));
     assert!(output.contains("line2"));
@@ -2606,7 +2653,8 @@ fn test_expansion_mode_auto_multiline_field() {
             mode: ExpansionMode::Auto,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("simple message"));
     assert!(output.contains("error="));
@@ -2634,7 +2682,8 @@ fn test_expansion_mode_auto_tab_in_field() {
             mode: ExpansionMode::Auto,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("simple message"));
     assert!(output.contains("error="));
@@ -2659,7 +2708,8 @@ fn test_expansion_mode_always_simple() {
             mode: ExpansionMode::Always,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("simple message"));
     assert!(output.contains("key=value"));
@@ -2681,7 +2731,8 @@ fn test_expansion_mode_always_multiline() {
             mode: ExpansionMode::Always,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("line1"));
     assert!(output.contains("line2"));
@@ -2711,7 +2762,8 @@ fn test_expansion_mode_auto_with_objects() {
             mode: ExpansionMode::Auto,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("test message"));
     assert!(output.contains("simple=text"));
@@ -2737,7 +2789,8 @@ fn test_expansion_mode_auto_only_expands_multiline() {
             mode: ExpansionMode::Auto,
         })
         .build()
-        .format_to_string(&rec);
+        .format_to_string(&rec)
+        .unwrap();
 
     assert!(output.contains("single line"));
     assert!(output.contains("field1=value1"));
@@ -2769,7 +2822,7 @@ fn test_expansion_mode_always_with_hidden_no_double_space() {
         ..Default::default()
     };
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     assert!(
         !result.contains("  ..."),
@@ -2791,7 +2844,7 @@ fn test_arc_record_formatter() {
 
     let formatter = Arc::new(formatter().with_theme(Default::default()).build());
 
-    let output = formatter.format_to_string(&rec);
+    let output = formatter.format_to_string(&rec).unwrap();
 
     assert!(output.contains("test message"));
     assert!(output.contains("key=value"));
@@ -2843,7 +2896,7 @@ fn test_expand_many_fields_overflow() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     let expected_lines: Vec<_> = std::iter::once("msg".to_string())
         .chain((0..MAX_FIELDS + 5).map(|i| format!("  > field{:02}=value", i)))
@@ -2869,7 +2922,7 @@ fn test_expand_nested_object_with_multiline_string() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     assert_eq!(
         result,
@@ -2893,7 +2946,7 @@ fn test_array_with_multiline_string_in_object_auto_mode() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     assert_eq!(result, r#"msg arr=[{ multiline="a\nb\nc" }]"#);
 }
@@ -2914,7 +2967,7 @@ fn test_array_with_multiline_string_in_object_inline_mode() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     assert_eq!(result, "msg arr=[{ multiline=`a\nb\nc` }]");
 }
@@ -2935,7 +2988,7 @@ fn test_multiline_message_in_never_mode() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     assert_eq!(result, r#""line 1\nline 2\nline 3\n" :: key=value"#);
 }
@@ -2955,7 +3008,7 @@ fn test_multiline_message_in_inline_mode() {
     }
     .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
 
     assert_eq!(result, "`line1\nline2\nline3` key=value");
 }
@@ -2976,13 +3029,15 @@ fn test_prettify_field_keys_disabled() {
             expansion: Default::default(),
             message: MessageFormatting {
                 format: MessageFormat::AutoQuoted,
+                template: None,
             },
             punctuation: Sample::sample(),
             prettify_field_keys: Some(false),
+            sort_object_keys: None,
         })
         .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert!(
         result.contains("k_a="),
         "Expected k_a (with underscore) but got: {}",
@@ -3006,13 +3061,15 @@ fn test_prettify_field_keys_enabled_default() {
             expansion: Default::default(),
             message: MessageFormatting {
                 format: MessageFormat::AutoQuoted,
+                template: None,
             },
             punctuation: Sample::sample(),
             prettify_field_keys: Some(true),
+            sort_object_keys: None,
         })
         .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert!(
         result.contains("k-a="),
         "Expected k-a (with hyphen) but got: {}",
@@ -3038,13 +3095,15 @@ fn test_prettify_field_keys_flattened() {
             expansion: Default::default(),
             message: MessageFormatting {
                 format: MessageFormat::AutoQuoted,
+                template: None,
             },
             punctuation: Sample::sample(),
             prettify_field_keys: Some(false),
+            sort_object_keys: None,
         })
         .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert!(
         result.contains("k_a.va.k_b="),
         "Expected k_a.va.k_b (with underscores) but got: {}",
@@ -3069,13 +3128,15 @@ fn test_prettify_field_keys_raw_fields_override() {
             expansion: Default::default(),
             message: MessageFormatting {
                 format: MessageFormat::AutoQuoted,
+                template: None,
             },
             punctuation: Sample::sample(),
             prettify_field_keys: Some(true),
+            sort_object_keys: None,
         })
         .build();
 
-    let result = formatter.format_to_string(&rec);
+    let result = formatter.format_to_string(&rec).unwrap();
     assert!(
         result.contains("k_a="),
         "Expected k_a (raw_fields should override prettify) but got: {}",
@@ -3095,3 +3156,2854 @@ fn test_nested_object_key_with_escaped_quotes() {
 
     format_no_color(&rec);
 }
+
+#[test]
+fn test_collapse_single_key_objects() {
+    let v1 = json_raw_value(r#"{"message":"x"}"#);
+    let v2 = json_raw_value(r#"{"a":{"b":{"c":1,"d":2}}}"#);
+    let rec = Record::from_fields(&[
+        ("error", RawObject::Json(&v1).into()),
+        ("k", RawObject::Json(&v2).into()),
+    ]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_single_key_objects_collapsing(true)
+        .build();
+
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "error.message=x k.a.b={ c=1 d=2 }"
+    );
+}
+
+#[test]
+fn test_collapse_single_key_objects_multi_key_unaffected() {
+    let v1 = json_raw_value(r#"{"a":1,"b":2}"#);
+    let v2 = json_raw_value(r#"{}"#);
+    let rec = Record::from_fields(&[("k", RawObject::Json(&v1).into()), ("e", RawObject::Json(&v2).into())]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_single_key_objects_collapsing(true)
+        .build();
+
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "k={ a=1 b=2 } e={}");
+}
+
+#[test]
+fn test_key_value_separator_per_value_type() {
+    let v = json_raw_value(r#"{"b":true}"#);
+    let rec = Record::from_fields(&[
+        ("s", EncodedString::raw("x").into()),
+        ("n", RawValue::Number("42")),
+        ("o", RawObject::Json(&v).into()),
+    ]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Punctuation {
+                string_key_value_separator: Some(": ".into()),
+                number_key_value_separator: Some("=".into()),
+                ..Sample::sample()
+            },
+            ..Default::default()
+        })
+        .build();
+
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "s: x n=42 o={ b=true }");
+}
+
+#[test]
+fn test_key_value_separator_per_value_type_fallback() {
+    let rec = Record::from_fields(&[("s", EncodedString::raw("x").into()), ("n", RawValue::Number("42"))]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Punctuation {
+                field_key_value_separator: ":".into(),
+                number_key_value_separator: Some("=".into()),
+                ..Sample::sample()
+            },
+            ..Default::default()
+        })
+        .build();
+
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "s:x n=42");
+}
+
+#[test]
+fn test_array_indices() {
+    let v = json_raw_value(r#"["a","b","c"]"#);
+    let rec = Record::from_fields(&[("k", RawArray::Json(&v).into())]);
+
+    let indexed = formatter()
+        .with_theme(Default::default())
+        .with_array_indices(true)
+        .build();
+    assert_eq!(indexed.format_to_string(&rec).unwrap(), "k=[0=a 1=b 2=c]");
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(plain.format_to_string(&rec).unwrap(), "k=[a, b, c]");
+}
+
+#[test]
+fn test_custom_brackets() {
+    let v1 = json_raw_value(r#"{"a":1,"b":[2,3],"c":{}}"#);
+    let v2 = json_raw_value(r#"[]"#);
+    let rec = Record::from_fields(&[("o", RawObject::Json(&v1).into()), ("e", RawArray::Json(&v2).into())]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Punctuation {
+                array_opening_bracket: "⟨".into(),
+                array_closing_bracket: "⟩".into(),
+                object_opening_brace: "(".into(),
+                object_closing_brace: ")".into(),
+                object_closing_brace_padding: "".into(),
+                ..Sample::sample()
+            },
+            ..Default::default()
+        })
+        .build();
+
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "o=( a=1 b=⟨2, 3⟩ c=()) e=⟨⟩");
+}
+
+#[test]
+fn test_custom_brackets_removed() {
+    let v = json_raw_value(r#"{"a":[1,2]}"#);
+    let rec = Record::from_fields(&[("o", RawObject::Json(&v).into())]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Punctuation {
+                array_opening_bracket: "".into(),
+                array_closing_bracket: "".into(),
+                object_opening_brace: "".into(),
+                object_closing_brace: "".into(),
+                object_closing_brace_padding: "".into(),
+                ..Sample::sample()
+            },
+            ..Default::default()
+        })
+        .build();
+
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "o= a=1, 2");
+}
+
+#[test]
+fn test_object_field_separator() {
+    let v = json_raw_value(r#"{"a":1,"b":{"c":2,"d":3}}"#);
+    let rec = Record::from_fields(&[("x", RawValue::Number("0")), ("o", RawObject::Json(&v).into())]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Punctuation {
+                object_field_separator: ", ".into(),
+                ..Sample::sample()
+            },
+            ..Default::default()
+        })
+        .build();
+
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "x=0 o={ a=1, b={ c=2, d=3 } }"
+    );
+}
+
+#[test]
+fn test_clone() {
+    let format = |formatter: &RecordFormatter| {
+        let v = json_raw_value(r#"{"a":1,"b":[2,3]}"#);
+        let rec = Record {
+            ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+            level: Some(Level::Info),
+            message: Some(RawValue::String(EncodedString::json(r#""hello""#))),
+            fields: RecordFields::from_slice(&[("o", RawObject::Json(&v).into())]),
+            ..Default::default()
+        };
+        formatter.format_to_string(&rec).unwrap()
+    };
+
+    let formatter = formatter().with_flatten(true).build();
+    let cloned = formatter.clone();
+
+    let expected = format(&formatter);
+    let actual = std::thread::spawn(move || format(&cloned)).join().unwrap();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_field_hook() {
+    let rec = Record::from_fields(&[
+        ("status", RawValue::Number("404")),
+        ("code", RawValue::Number("404")),
+        ("status", EncodedString::raw("ok").into()),
+    ]);
+
+    let hook: FieldHook = Arc::new(|key, value, out| match (key, value) {
+        ("status", RawValue::Number(n)) => {
+            out.push(Element::Number, format!("<{n}>").as_bytes());
+            true
+        }
+        _ => false,
+    });
+
+    let formatter = formatter().with_theme(Default::default()).with_field_hook(hook).build();
+
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "status=<404> code=404 status=ok"
+    );
+}
+
+#[rstest]
+#[case::none(LineTerminator::None, "a=1")]
+#[case::lf(LineTerminator::Lf, "a=1\n")]
+#[case::crlf(LineTerminator::CrLf, "a=1\r\n")]
+fn test_line_terminator(#[case] terminator: LineTerminator, #[case] expected: &str) {
+    let rec = Record::from_fields(&[("a", RawValue::Number("1"))]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_line_terminator(terminator)
+        .build();
+
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_line_terminator_after_styles() {
+    let rec = Record::from_fields(&[("a", RawValue::Number("1"))]);
+
+    let formatter = formatter().with_line_terminator(LineTerminator::CrLf).build();
+    let result = formatter.format_to_string(&rec).unwrap();
+
+    assert!(result.ends_with("\x1b[0m\r\n"), "{result:?}");
+    assert_eq!(result.matches("\r\n").count(), 1);
+}
+
+#[test]
+fn test_fallback_formatter() {
+    let parser = Parser::new(ParserSettings::default());
+    let theme: Arc<Theme> = Sample::sample();
+    let inner = formatter().with_theme(Default::default()).build();
+    let formatter = FallbackFormatter::new(inner, RawRecordParser::new(), parser, theme.clone(), "\n".into());
+
+    let mut buf = Vec::new();
+    formatter.format_source(&mut buf, 0..0, br#"{"msg":"hello","a":1}"#);
+    assert_eq!(String::from_utf8(buf).unwrap(), "hello a=1");
+
+    let mut buf = Vec::new();
+    formatter.format_source(&mut buf, 0..0, br#"{"msg":"hello","#);
+    assert_eq!(String::from_utf8(buf).unwrap(), "\x1b[0;2m{\"msg\":\"hello\",\x1b[0m");
+
+    let parser = Parser::new(ParserSettings::default());
+    let inner = self::formatter().with_theme(Default::default()).build();
+    let raw_parser = RawRecordParser::new().allow_prefix(true).relaxed_json(true);
+    let formatter = FallbackFormatter::new(inner, raw_parser, parser, theme, "\n".into());
+
+    let mut buf = Vec::new();
+    formatter.format_source(&mut buf, 0..0, b"prefix {msg: 'hello', a: 1,}");
+    assert_eq!(String::from_utf8(buf).unwrap(), "hello a=1");
+}
+
+#[rstest]
+#[case::as_is(false, "a.z=1 a.y.q=1 a.y.p=2 a.x=3 c=0 b.k=1 b.j=2")]
+#[case::sorted(true, "a.x=3 a.y.p=2 a.y.q=1 a.z=1 c=0 b.j=2 b.k=1")]
+fn test_flattened_keys_grouping(#[case] sort: bool, #[case] expected: &str) {
+    let v1 = json_raw_value(r#"{"z":1,"y":{"q":1,"p":2},"x":3}"#);
+    let v2 = json_raw_value(r#"{"k":1,"j":2}"#);
+    let rec = Record::from_fields(&[
+        ("a", RawObject::Json(&v1).into()),
+        ("c", RawValue::Number("0")),
+        ("b", RawObject::Json(&v2).into()),
+    ]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_flatten(true)
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Sample::sample(),
+            sort_object_keys: Some(sort),
+            ..Default::default()
+        })
+        .build();
+
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_sort_object_keys_nested() {
+    let v = json_raw_value(r#"{"z":1,"y":{"q":1,"p":2},"x":3}"#);
+    let rec = Record::from_fields(&[("b", RawValue::Number("0")), ("a", RawObject::Json(&v).into())]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Sample::sample(),
+            sort_object_keys: Some(true),
+            ..Default::default()
+        })
+        .build();
+
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "b=0 a={ x=3 y={ p=2 q=1 } z=1 }"
+    );
+}
+
+#[test]
+fn test_wrap_width() {
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Number("1111")),
+        ("b", EncodedString::raw("two words").into()),
+        ("c", RawValue::Number("3333")),
+        ("d", EncodedString::raw("a b c d e f g h").into()),
+        ("e", RawValue::Number("5555")),
+    ]);
+
+    let wrapped = formatter()
+        .with_theme(Default::default())
+        .with_wrap_width(Some(24))
+        .build();
+
+    assert_eq!(
+        wrapped.format_to_string(&rec).unwrap(),
+        r#"a=1111 b="two words"
+  c=3333
+  d="a b c d e f g h"
+  e=5555"#
+    );
+
+    let unwrapped = formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        unwrapped.format_to_string(&rec).unwrap(),
+        r#"a=1111 b="two words" c=3333 d="a b c d e f g h" e=5555"#
+    );
+}
+
+#[test]
+fn test_wrap_width_with_styles() {
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Number("1111")),
+        ("b", RawValue::Number("2222")),
+        ("c", RawValue::Number("3333")),
+    ]);
+
+    let formatter = formatter().with_wrap_width(Some(14)).build();
+    let result = formatter.format_to_vec(&rec);
+
+    let lines: Vec<_> = result.split(|&c| c == b'\n').collect();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        assert!(visible_width(line) <= 14);
+        for chunk in crate::eseq::chunks(line) {
+            if let crate::eseq::Chunk::Escape(sequence) = chunk {
+                assert!(sequence.ends_with(b"m"), "{:?}", String::from_utf8_lossy(line));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_strip_key_prefix() {
+    let v = json_raw_value(r#"{"request_id":"r1","user":{"name":"u"}}"#);
+    let rec = Record::from_fields(&[
+        ("ctx.user_id", RawValue::Number("42")),
+        ("ctx.session", EncodedString::raw("s").into()),
+        ("ctx", RawObject::Json(&v).into()),
+        ("ctx.", RawValue::Number("1")),
+        ("other", RawValue::Number("2")),
+    ]);
+
+    let flat = formatter()
+        .with_theme(Default::default())
+        .with_flatten(true)
+        .with_strip_key_prefix(Some("ctx.".into()))
+        .build();
+    assert_eq!(
+        flat.format_to_string(&rec).unwrap(),
+        "user-id=42 session=s request-id=r1 user.name=u ctx.=1 other=2"
+    );
+
+    let nested = formatter()
+        .with_theme(Default::default())
+        .with_strip_key_prefix(Some("ctx.".into()))
+        .build();
+    assert_eq!(
+        nested.format_to_string(&rec).unwrap(),
+        "user-id=42 session=s ctx={ request-id=r1 user={ name=u } } ctx.=1 other=2"
+    );
+}
+
+#[test]
+fn test_strip_key_prefix_prettified() {
+    let rec = Record::from_fields(&[("my_ctx.user_id", RawValue::Number("42"))]);
+
+    let prettified = formatter()
+        .with_theme(Default::default())
+        .with_strip_key_prefix(Some("my_ctx.".into()))
+        .build();
+    assert_eq!(prettified.format_to_string(&rec).unwrap(), "user-id=42");
+
+    let raw = formatter()
+        .with_theme(Default::default())
+        .with_raw_fields(true)
+        .with_strip_key_prefix(Some("my_ctx.".into()))
+        .build();
+    assert_eq!(raw.format_to_string(&rec).unwrap(), "user_id=42");
+}
+
+#[test]
+fn test_strip_key_prefix_filter_uses_original_keys() {
+    let rec = Record::from_fields(&[("ctx.a", RawValue::Number("1")), ("ctx.b", RawValue::Number("2"))]);
+    let mut fields = IncludeExcludeKeyFilter::default();
+    fields.entry("ctx.a").exclude();
+
+    let filtered = RecordFormatterBuilder {
+        theme: Some(Default::default()),
+        fields: Some(fields.into()),
+        ..formatter()
+    }
+    .with_strip_key_prefix(Some("ctx.".into()))
+    .build();
+    assert_eq!(filtered.format_to_string(&rec).unwrap(), "b=2 ...");
+}
+
+#[test]
+fn test_debug_strings() {
+    let rec = Record {
+        message: Some(RawValue::String(EncodedString::json(r#""say \"hi\"\tnow \u00e9""#))),
+        ..Default::default()
+    };
+
+    let debug = formatter()
+        .with_theme(Default::default())
+        .with_debug_strings(true)
+        .build();
+    assert_eq!(
+        debug.format_to_string(&rec).unwrap(),
+        "say \"hi\"\tnow é ⟨\"say \\\"hi\\\"\\tnow \\u00e9\"⟩"
+    );
+
+    let normal = formatter().with_theme(Default::default()).build();
+    assert_eq!(normal.format_to_string(&rec).unwrap(), "say \"hi\"\tnow é");
+}
+
+#[test]
+fn test_null_label() {
+    let v = json_raw_value(r#"{"b":null}"#);
+    let arr = json_raw_value("[1,null]");
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Null),
+        ("o", RawObject::Json(&v).into()),
+        ("c", RawArray::Json(&arr).into()),
+    ]);
+
+    let labeled = formatter()
+        .with_theme(Default::default())
+        .with_null_label(Some("∅".into()))
+        .build();
+    assert_eq!(labeled.format_to_string(&rec).unwrap(), "a=∅ o={ b=∅ } c=[1, ∅]");
+
+    let styled = formatter().with_null_label(Some("∅".into())).build();
+    let default = formatter().build();
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        default.format_to_string(&rec).unwrap().replace("null", "∅")
+    );
+}
+
+#[test]
+fn test_boolean_labels() {
+    let v = json_raw_value(r#"{"b":false}"#);
+    let arr = json_raw_value("[true,false]");
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Boolean(true)),
+        ("o", RawObject::Json(&v).into()),
+        ("c", RawArray::Json(&arr).into()),
+    ]);
+
+    let labeled = formatter()
+        .with_theme(Default::default())
+        .with_boolean_labels(Some(("T".into(), "F".into())))
+        .build();
+    assert_eq!(labeled.format_to_string(&rec).unwrap(), "a=T o={ b=F } c=[T, F]");
+
+    let dots = formatter().with_boolean_labels(Some(("●".into(), "●".into()))).build();
+    let default = formatter().build();
+    assert_eq!(
+        dots.format_to_string(&rec).unwrap(),
+        default
+            .format_to_string(&rec)
+            .unwrap()
+            .replace("true", "●")
+            .replace("false", "●")
+    );
+}
+
+#[test]
+fn test_null_label_empty() {
+    let rec = Record::from_fields(&[("a", RawValue::Null), ("b", RawValue::Number("1"))]);
+
+    let blank = formatter()
+        .with_theme(Default::default())
+        .with_null_label(Some(String::new()))
+        .build();
+    assert_eq!(blank.format_to_string(&rec).unwrap(), "a= b=1");
+}
+
+#[test]
+fn test_hide_null_fields() {
+    let v = json_raw_value(r#"{"b":null,"c":1}"#);
+    let w = json_raw_value(r#"{"e":null}"#);
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Null),
+        ("o", RawObject::Json(&v).into()),
+        ("d", RawObject::Json(&w).into()),
+        ("x", EncodedString::raw("").into()),
+    ]);
+
+    let hiding = formatter()
+        .with_theme(Default::default())
+        .with_null_fields_hiding(true)
+        .build();
+    assert_eq!(hiding.format_to_string(&rec).unwrap(), r#"o={ c=1 ... } x="" ..."#);
+
+    let flat = formatter()
+        .with_theme(Default::default())
+        .with_null_fields_hiding(true)
+        .with_flatten(true)
+        .build();
+    assert_eq!(flat.format_to_string(&rec).unwrap(), r#"o.c=1 x="" ..."#);
+
+    let showing = formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        showing.format_to_string(&rec).unwrap(),
+        r#"a=null o={ b=null c=1 } d={ e=null } x="""#
+    );
+}
+
+#[test]
+fn test_hide_null_fields_only_nulls() {
+    let rec = Record::from_fields(&[("a", RawValue::Null), ("b", RawValue::Number("1"))]);
+
+    let hiding = formatter()
+        .with_theme(Default::default())
+        .with_null_fields_hiding(true)
+        .build();
+    assert_eq!(hiding.format_to_string(&rec).unwrap(), "b=1 ...");
+}
+
+#[test]
+fn test_dim_fields() {
+    let rec = Record {
+        level: Some(Level::Info),
+        message: Some(RawValue::String(EncodedString::json(r#""hello""#))),
+        fields: RecordFields::from_slice(&[("a", RawValue::Number("1")), ("b", RawValue::Boolean(true))]),
+        ..Default::default()
+    };
+
+    let dimmed = formatter()
+        .with_dim_fields(true)
+        .build()
+        .format_to_string(&rec)
+        .unwrap();
+    let normal = formatter().build().format_to_string(&rec).unwrap();
+    assert_eq!(
+        dimmed,
+        concat!(
+            "\u{1b}[0;36m|INF|\u{1b}[0m \u{1b}[0;1mhello ",
+            "\u{1b}[0;32m\u{1b}[2ma\u{1b}[0;2m\u{1b}[2m=\u{1b}[0;94m\u{1b}[2m1 ",
+            "\u{1b}[0;32m\u{1b}[2mb\u{1b}[0;2m\u{1b}[2m=\u{1b}[0;92m\u{1b}[2mtrue\u{1b}[0m",
+        )
+    );
+    assert_eq!(dimmed.replace("\u{1b}[2m", ""), normal);
+
+    let plain = formatter()
+        .with_theme(Default::default())
+        .with_dim_fields(true)
+        .build()
+        .format_to_string(&rec)
+        .unwrap();
+    assert_eq!(plain, "|INF| hello a=1 b=true");
+}
+
+#[test]
+fn test_secondary_timestamp() {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        message: Some(RawValue::String(EncodedString::json(r#""tm""#))),
+        level: Some(Level::Info),
+        fields: RecordFields::from_slice(&[
+            ("received_at", EncodedString::json(r#""2000-01-02T03:04:06.5Z""#).into()),
+            ("a", RawValue::Number("1")),
+        ]),
+        ..Default::default()
+    };
+
+    let secondary = formatter()
+        .with_theme(Default::default())
+        .with_secondary_timestamp_key(Some("received_at".into()))
+        .build();
+    assert_eq!(
+        secondary.format_to_string(&rec).unwrap(),
+        "00-01-02 03:04:05.123 00-01-02 03:04:06.500 |INF| tm a=1"
+    );
+
+    let styled = formatter()
+        .with_secondary_timestamp_key(Some("received_at".into()))
+        .build();
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        "\u{1b}[0;2;3m00-01-02 03:04:05.123 00-01-02 03:04:06.500 \u{1b}[0;36m|INF|\u{1b}[0m \u{1b}[0;1mtm \u{1b}[0;32ma\u{1b}[0;2m=\u{1b}[0;94m1\u{1b}[0m"
+    );
+}
+
+#[test]
+fn test_secondary_timestamp_skew() {
+    let rec = |received_at: Option<&'static str>| Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.200Z")),
+        message: Some(EncodedString::raw("tm").into()),
+        fields: RecordFields::from_slice(
+            &received_at
+                .map(|ts| ("received_at", EncodedString::raw(ts).into()))
+                .into_iter()
+                .collect_vec(),
+        ),
+        ..Default::default()
+    };
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_secondary_timestamp_key(Some("received_at".into()))
+        .with_secondary_timestamp_skew(true)
+        .build();
+    assert_eq!(
+        formatter
+            .format_to_string(&rec(Some("2000-01-02T03:04:06.5Z")))
+            .unwrap(),
+        "00-01-02 03:04:05.200 00-01-02 03:04:06.500 +1.3s   tm"
+    );
+    assert_eq!(
+        formatter.format_to_string(&rec(Some("2000-01-02T03:04:05Z"))).unwrap(),
+        "00-01-02 03:04:05.200 00-01-02 03:04:05.000 -200ms  tm"
+    );
+    assert_eq!(
+        formatter.format_to_string(&rec(None)).unwrap(),
+        "00-01-02 03:04:05.200                               tm"
+    );
+    assert_eq!(
+        formatter.format_to_string(&rec(Some("soon"))).unwrap(),
+        "00-01-02 03:04:05.200                               tm received-at=soon"
+    );
+}
+
+#[test]
+fn test_secondary_timestamp_missing_or_invalid() {
+    let missing = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        message: Some(RawValue::String(EncodedString::json(r#""tm""#))),
+        ..Default::default()
+    };
+    let invalid = Record {
+        fields: RecordFields::from_slice(&[("received_at", EncodedString::raw("soon").into())]),
+        ..Record {
+            ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+            message: Some(RawValue::String(EncodedString::json(r#""tm""#))),
+            ..Default::default()
+        }
+    };
+    let numeric = Record {
+        fields: RecordFields::from_slice(&[("received_at", RawValue::Number("946782246"))]),
+        ..Default::default()
+    };
+
+    let secondary = formatter()
+        .with_theme(Default::default())
+        .with_secondary_timestamp_key(Some("received_at".into()))
+        .build();
+    assert_eq!(
+        secondary.format_to_string(&missing).unwrap(),
+        "00-01-02 03:04:05.123                       tm"
+    );
+    assert_eq!(
+        secondary.format_to_string(&invalid).unwrap(),
+        "00-01-02 03:04:05.123                       tm received-at=soon"
+    );
+    assert_eq!(secondary.format_to_string(&numeric).unwrap(), "received-at=946782246");
+}
+
+#[test]
+fn test_delta_formatter() {
+    let inner = formatter().with_theme(Default::default()).build();
+    let formatter = DeltaFormatter::new(inner, Default::default());
+
+    let format = |ts: Option<&str>| {
+        let rec = Record {
+            ts: ts.map(Timestamp::new),
+            message: Some(RawValue::String(EncodedString::raw("m"))),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        formatter.format_record(&mut buf, 0..0, model::RecordWithSource::new(&rec, b""));
+        String::from_utf8(buf).unwrap()
+    };
+
+    assert_eq!(
+        format(Some("2000-01-02T03:04:05.000Z")),
+        "        00-01-02 03:04:05.000 m"
+    );
+    assert_eq!(
+        format(Some("2000-01-02T03:04:05.250Z")),
+        "+250ms  00-01-02 03:04:05.250 m"
+    );
+    assert_eq!(
+        format(Some("2000-01-02T03:06:08.250Z")),
+        "+2m03s  00-01-02 03:06:08.250 m"
+    );
+    assert_eq!(format(None), "        m");
+    assert_eq!(
+        format(Some("2000-01-02T03:06:06.950Z")),
+        "-1.3s   00-01-02 03:06:06.950 m"
+    );
+    assert_eq!(
+        format(Some("2000-01-02T05:10:06.950Z")),
+        "+2h04m  00-01-02 05:10:06.950 m"
+    );
+}
+
+#[test]
+fn test_changed_fields_formatter() {
+    let formatter = ChangedFieldsFormatter::new(formatter().with_theme(Default::default()).build());
+    let format = |input: &str| {
+        let raw = RawRecord::parser().parse(input.as_bytes()).next().unwrap().unwrap();
+        let rec = Parser::new(ParserSettings::default()).parse(&raw.record);
+        let mut buf = Vec::new();
+        formatter.format_record(&mut buf, 0..0, model::RecordWithSource::new(&rec, b""));
+        String::from_utf8(buf).unwrap()
+    };
+
+    let first = r#"{"msg":"tick","state":"idle","node":"a","count":1}"#;
+    let second = r#"{"msg":"tick","state":"busy","node":"a","count":1}"#;
+
+    assert_eq!(format(first), "tick state=idle node=a count=1");
+    assert_eq!(format(second), "tick state=busy ...");
+    assert_eq!(format(second), "tick ...");
+
+    formatter.reset();
+    assert_eq!(format(second), "tick state=busy node=a count=1");
+}
+
+#[test]
+fn test_group_separator_formatter() {
+    let inner = formatter().with_theme(Default::default()).build();
+    let formatter =
+        GroupSeparatorFormatter::new(inner, Default::default(), GroupBy::Logger, "----".into(), "\n".into());
+
+    let mut buf = Vec::new();
+    for (logger, message) in [
+        (Some("db"), "a"),
+        (Some("db"), "b"),
+        (Some("api"), "c"),
+        (Some("db"), "d"),
+        (None, "e"),
+        (None, "f"),
+    ] {
+        let rec = Record {
+            logger,
+            message: Some(EncodedString::raw(message).into()),
+            ..Default::default()
+        };
+        buf.extend(b">");
+        formatter.format_record(&mut buf, 0..1, model::RecordWithSource::new(&rec, b""));
+        buf.push(b'\n');
+    }
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        concat!(
+            ">db: a\n",
+            ">db: b\n",
+            ">----\n>api: c\n",
+            ">----\n>db: d\n",
+            ">----\n>e\n",
+            ">f\n",
+        )
+    );
+
+    let inner = self::formatter().with_theme(Default::default()).build();
+    let formatter = GroupSeparatorFormatter::new(inner, Sample::sample(), GroupBy::Logger, "----".into(), "\0".into());
+    let mut buf = Vec::new();
+    for logger in ["db", "api"] {
+        let rec = Record {
+            logger: Some(logger),
+            ..Default::default()
+        };
+        formatter.format_record(&mut buf, 0..0, model::RecordWithSource::new(&rec, b""));
+    }
+    assert_eq!(String::from_utf8(buf).unwrap(), "db\x1b[0;2m----\x1b[0m\0api");
+}
+
+#[test]
+fn test_tee_formatter() {
+    let human = formatter().with_theme(Default::default()).build();
+    let raw = RawRecordFormatter { delimiter: "\n".into() };
+    let formatter = TeeFormatter::new(
+        [Arc::new(human) as DynRecordWithSourceFormatter, Arc::new(raw)],
+        "\n".into(),
+    );
+
+    let source = br#"{"level":"info","msg":"hello","a":1}"#;
+    let raw = RawRecord::parser().parse(source).next().unwrap().unwrap();
+    let rec = Parser::new(ParserSettings::default()).parse(&raw.record);
+
+    let mut buf = b">".to_vec();
+    formatter.format_record(&mut buf, 0..1, model::RecordWithSource::new(&rec, source));
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        concat!(">|INF| hello a=1\n", r#">{"level":"info","msg":"hello","a":1}"#)
+    );
+
+    let formatter = TeeFormatter::new([], " | ".into());
+    let mut buf = Vec::new();
+    formatter.format_record(&mut buf, 0..0, model::RecordWithSource::new(&rec, source));
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn test_delta_formatter_styled() {
+    let inner = formatter().with_theme(Default::default()).build();
+    let formatter = DeltaFormatter::new(inner, Sample::sample());
+
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.000Z")),
+        ..Default::default()
+    };
+    let mut buf = Vec::new();
+    formatter.format_record(&mut buf, 0..0, model::RecordWithSource::new(&rec, b""));
+    formatter.format_record(&mut buf, 0..0, model::RecordWithSource::new(&rec, b""));
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "\x1b[0;2;3m        \x1b[0m00-01-02 03:04:05.000\x1b[0;2;3m+0ms    \x1b[0m00-01-02 03:04:05.000"
+    );
+}
+
+#[rstest]
+#[case::success("200", "\x1b[0m\x1b[0;32mstatus\x1b[0;2m=\x1b[0;32m200\x1b[0m")]
+#[case::client_error("404", "\x1b[0m\x1b[0;32mstatus\x1b[0;2m=\x1b[0;33m404\x1b[0m")]
+#[case::server_error("503", "\x1b[0m\x1b[0;32mstatus\x1b[0;2m=\x1b[0;31m503\x1b[0m")]
+#[case::out_of_range("42", "\x1b[0m\x1b[0;32mstatus\x1b[0;2m=\x1b[0;94m42\x1b[0m")]
+fn test_http_status_styling(#[case] status: &str, #[case] expected: &str) {
+    let rec = Record::from_fields(&[("status", RawValue::Number(status))]);
+
+    let styled = formatter()
+        .with_http_status_styling(Some(HttpStatusStyling::default()))
+        .build();
+    assert_eq!(styled.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_http_status_styling_key_paths() {
+    let http = json_raw_value(r#"{"status":301}"#);
+    let rec = Record::from_fields(&[
+        ("http", RawObject::Json(&http).into()),
+        ("code", RawValue::Number("500")),
+        ("other", RawValue::Number("500")),
+    ]);
+
+    let styling = HttpStatusStyling::new(["http.status", "code"]).with_class(5, None);
+    let styled = formatter()
+        .with_flatten(true)
+        .with_http_status_styling(Some(styling))
+        .build();
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32mhttp.status\x1b[0;2m=\x1b[0;36m301 \x1b[0;32mcode\x1b[0;2m=\x1b[0;94m500 \x1b[0;32mother\x1b[0;2m=\x1b[0;94m500\x1b[0m"
+    );
+}
+
+#[test]
+fn test_write_record_buffered() {
+    struct ChunkRecorder {
+        data: Vec<u8>,
+        max_chunk: usize,
+    }
+
+    impl std::io::Write for ChunkRecorder {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.max_chunk = self.max_chunk.max(buf.len());
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let small = Record::from_fields(&[("a", RawValue::Number("1"))]);
+    let long = "x".repeat(WRITE_CHUNK_SIZE * 2 + 100);
+    let large = Record {
+        message: Some(RawValue::String(EncodedString::raw(&long))),
+        ..Default::default()
+    };
+    let formatter = formatter().build();
+
+    let mut out = Vec::new();
+    formatter.write_record_buffered(&mut out, &small).unwrap();
+    assert_eq!(out, formatter.format_to_vec(&small));
+
+    let mut out = ChunkRecorder {
+        data: Vec::new(),
+        max_chunk: 0,
+    };
+    formatter.write_record_buffered(&mut out, &large).unwrap();
+    formatter.write_record_buffered(&mut out, &small).unwrap();
+    assert_eq!(out.max_chunk, WRITE_CHUNK_SIZE);
+    assert_eq!(
+        out.data,
+        [formatter.format_to_vec(&large), formatter.format_to_vec(&small)].concat()
+    );
+
+    struct NestedWriter<'a> {
+        formatter: &'a RecordFormatter,
+        record: &'a Record<'a>,
+        data: Vec<u8>,
+    }
+
+    impl std::io::Write for NestedWriter<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.formatter.write_record_buffered(&mut self.data, self.record)?;
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut out = NestedWriter {
+        formatter: &formatter,
+        record: &small,
+        data: Vec::new(),
+    };
+    formatter.write_record_buffered(&mut out, &small).unwrap();
+    assert_eq!(out.data, formatter.format_to_vec(&small).repeat(2));
+}
+
+#[test]
+fn test_max_line_bytes() {
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Number("1")),
+        ("b", RawValue::String(EncodedString::raw("some long value"))),
+    ]);
+
+    let styled = formatter().with_max_line_bytes(Some(52)).build();
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32ma\x1b[0;2m=\x1b[0;94m1 \x1b[0;32mb\x1b[0;2m=\x1b[0m\"some\x1b[0m\x1b[0;2m...\x1b[0m"
+    );
+
+    let styled = formatter().with_max_line_bytes(Some(38)).build();
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32ma\x1b[0;2m=\x1b[0;94m1 \x1b[0;32mb\x1b[0m\x1b[0;2m...\x1b[0m"
+    );
+
+    let plain = formatter().with_theme(Default::default()).with_max_line_bytes(Some(10));
+    assert_eq!(plain.clone().build().format_to_string(&rec).unwrap(), "a=1 b=\"som...");
+    assert_eq!(
+        plain
+            .with_max_line_bytes(Some(100))
+            .build()
+            .format_to_string(&rec)
+            .unwrap(),
+        "a=1 b=\"some long value\""
+    );
+}
+
+#[test]
+fn test_max_line_bytes_utf8() {
+    let rec = Record::from_fields(&[("a", RawValue::String(EncodedString::raw("привет")))]);
+
+    let plain = formatter().with_theme(Default::default()).with_max_line_bytes(Some(5));
+    assert_eq!(plain.build().format_to_string(&rec).unwrap(), "a=п...");
+}
+
+#[test]
+fn test_expand_embedded_json() {
+    let rec = Record {
+        message: Some(RawValue::String(EncodedString::json(r#""{\"a\":1}""#))),
+        fields: RecordFields::from_slice(&[
+            ("b", RawValue::String(EncodedString::json(r#""[1,{\"c\":true}]""#))),
+            ("d", RawValue::String(EncodedString::json(r#""{not json}""#))),
+        ]),
+        ..Default::default()
+    };
+
+    let plain = formatter().with_theme(Default::default());
+    assert_eq!(
+        plain.clone().build().format_to_string(&rec).unwrap(),
+        r#"{"a":1} b='[1,{"c":true}]' d="{not json}""#
+    );
+    assert_eq!(
+        plain
+            .with_expand_embedded_json(true)
+            .build()
+            .format_to_string(&rec)
+            .unwrap(),
+        r#"{ a=1 } b=[1, { c=true }] d="{not json}""#
+    );
+}
+
+#[test]
+fn test_expand_embedded_json_depth_limit() {
+    let mut value = r#""x""#.to_string();
+    for _ in 0..MAX_EMBEDDED_JSON_DEPTH + 1 {
+        value = json::to_string(&format!(r#"{{"a":{value}}}"#)).unwrap();
+    }
+    let rec = Record::from_fields(&[("v", RawValue::String(EncodedString::json(&value)))]);
+
+    let result = formatter()
+        .with_theme(Default::default())
+        .with_expand_embedded_json(true)
+        .build()
+        .format_to_string(&rec)
+        .unwrap();
+    assert_eq!(result, r#"v={ a={ a={ a={ a='{"a":"x"}' } } } }"#);
+}
+
+#[rstest]
+#[case::offset(
+    Tz::FixedOffset(FixedOffset::east_opt(2 * 3600).unwrap()),
+    ZoneSuffix::Offset,
+    "00-01-02 05:04:05.123 +02:00 m",
+    "##-##-## ##:##:##.### +02:00 m"
+)]
+#[case::utc(
+    Tz::IANA(chrono_tz::UTC),
+    ZoneSuffix::Abbreviation,
+    "00-01-02 03:04:05.123 UTC m",
+    "##-##-## ##:##:##.### UTC m"
+)]
+fn test_timestamp_zone_suffix(#[case] tz: Tz, #[case] suffix: ZoneSuffix, #[case] expected: &str, #[case] stub: &str) {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        message: Some(RawValue::String(EncodedString::raw("m"))),
+        ..Default::default()
+    };
+
+    let builder = formatter()
+        .with_theme(Default::default())
+        .with_timestamp_formatter(DateTimeFormatter::new(
+            LinuxDateFormat::new("%y-%m-%d %T.%3N").compile(),
+            tz,
+        ))
+        .with_timestamp_zone_suffix(Some(suffix));
+    assert_eq!(builder.clone().build().format_to_string(&rec).unwrap(), expected);
+
+    let missing = Record {
+        message: Some(RawValue::String(EncodedString::raw("m"))),
+        ..Default::default()
+    };
+    let stubbed = builder.with_always_show_time(true).build();
+    assert_eq!(stubbed.format_to_string(&missing).unwrap(), stub);
+}
+
+#[rstest]
+#[case::zero("0", "uptime-ns=00:00:00.000")]
+#[case::hms("3723004500000", "uptime-ns=01:02:03.004")]
+#[case::over_day("90000000000000", "uptime-ns=25:00:00.000")]
+#[case::negative("-5", "uptime-ns=-5")]
+fn test_elapsed_time_fields(#[case] value: &str, #[case] expected: &str) {
+    let rec = Record::from_fields(&[("uptime_ns", RawValue::Number(value))]);
+
+    let elapsed = formatter()
+        .with_theme(Default::default())
+        .with_elapsed_time_fields(Some(ElapsedTimeFields::default()))
+        .build();
+    assert_eq!(elapsed.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_elapsed_time_fields_styled() {
+    let clock = json_raw_value(r#"{"mono":1.5}"#);
+    let rec = Record::from_fields(&[
+        ("clock", RawObject::Json(&clock).into()),
+        ("other", RawValue::Number("1.5")),
+    ]);
+
+    let fields = ElapsedTimeFields::new(["clock.mono"], ElapsedTimeUnit::Seconds);
+    let elapsed = formatter()
+        .with_flatten(true)
+        .with_elapsed_time_fields(Some(fields))
+        .build();
+    assert_eq!(
+        elapsed.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32mclock.mono\x1b[0;2m=\x1b[0;35m00:00:01.500 \x1b[0;32mother\x1b[0;2m=\x1b[0;94m1.5\x1b[0m"
+    );
+}
+
+#[test]
+fn test_message_template() {
+    let formatter = |template: Option<&str>| {
+        formatter()
+            .with_options(Formatting {
+                message: MessageFormatting {
+                    format: MessageFormat::AutoQuoted,
+                    template: template.map(Into::into),
+                },
+                punctuation: Sample::sample(),
+                ..Default::default()
+            })
+            .build()
+    };
+    let rec = Record {
+        message: Some(RawValue::String(EncodedString::raw("user bob logged in"))),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        formatter(None).format_to_string(&rec).unwrap(),
+        "\x1b[0;1muser bob logged in\x1b[0m"
+    );
+    assert_eq!(
+        formatter(Some("user alice logged in")).format_to_string(&rec).unwrap(),
+        "\x1b[0;1muser \x1b[0;7mbob\x1b[0;1m logged in\x1b[0m"
+    );
+    assert_eq!(
+        formatter(Some("user bob logged")).format_to_string(&rec).unwrap(),
+        "\x1b[0;1muser bob logged \x1b[0;7min\x1b[0m"
+    );
+}
+
+#[test]
+fn test_message_template_quoted() {
+    let rec = Record {
+        message: Some(RawValue::String(EncodedString::raw("a=b c"))),
+        ..Default::default()
+    };
+
+    let formatter = formatter()
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AlwaysDoubleQuoted,
+                template: Some("a=b d".into()),
+            },
+            ..Default::default()
+        })
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "\x1b[0;1m\"a=b \x1b[0;7mc\"\x1b[0m"
+    );
+}
+
+#[rstest]
+#[case::prod("prod", StyleRuleMode::FirstMatch, "| msg=hello env=prod")]
+#[case::prod_all("prod", StyleRuleMode::All, "|* msg=hello env=prod")]
+#[case::dev("dev", StyleRuleMode::FirstMatch, "msg=hello env=dev")]
+#[case::dev_all("dev", StyleRuleMode::All, "msg=hello env=dev")]
+fn test_style_rules(#[case] env: &str, #[case] mode: StyleRuleMode, #[case] expected: &str) {
+    let env = format!(r#""{}""#, env);
+    let rec = Record::from_fields(&[
+        ("msg", EncodedString::json(r#""hello""#).into()),
+        ("env", EncodedString::json(&env).into()),
+    ]);
+
+    let rules = vec![
+        StyleRule::new("env=prod", "|").unwrap(),
+        StyleRule::new("env~=pr", "*").unwrap(),
+    ];
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_style_rules(rules, mode)
+        .build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_style_rules_styled() {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        message: Some(EncodedString::raw("hello").into()),
+        fields: RecordFields::from_slice(&[("env", EncodedString::raw("prod").into())]),
+        ..Default::default()
+    };
+
+    let rules = vec![
+        StyleRule::new("env=prod", "|").unwrap(),
+        StyleRule::new("env=prod", "!")
+            .unwrap()
+            .with_element(Element::HttpStatusClientError),
+    ];
+    let formatter = formatter().with_style_rules(rules, StyleRuleMode::All).build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "\x1b[0;31m|\x1b[0;33m! \x1b[0;2;3m00-01-02 03:04:05.123\x1b[0m \x1b[0;1mhello \x1b[0;32menv\x1b[0;2m=\x1b[0mprod\x1b[0m"
+    );
+}
+
+#[test]
+fn test_style_rule_invalid_condition() {
+    assert!(StyleRule::new("env", "|").is_err());
+}
+
+#[rstest]
+#[case::error(Some(Level::Error), "! |ERR| hello")]
+#[case::warning(Some(Level::Warning), "* |WRN| hello")]
+#[case::info(Some(Level::Info), "+ |INF| hello")]
+#[case::debug(Some(Level::Debug), "- |DBG| hello")]
+#[case::trace(Some(Level::Trace), ". |TRC| hello")]
+#[case::none(None, "  hello")]
+fn test_gutter(#[case] level: Option<Level>, #[case] expected: &str) {
+    let rec = Record {
+        level,
+        message: Some(EncodedString::raw("hello").into()),
+        ..Default::default()
+    };
+
+    let formatter = formatter().with_theme(Default::default()).with_gutter(true).build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_gutter_styled() {
+    let rec = Record {
+        level: Some(Level::Warning),
+        message: Some(EncodedString::raw("hello").into()),
+        ..Default::default()
+    };
+
+    let formatter = formatter().with_gutter(true).build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "\x1b[0;7;33m*\x1b[0m \x1b[0;7;33m|WRN|\x1b[0m \x1b[0;1mhello\x1b[0m"
+    );
+}
+
+#[rstest]
+#[case::no_flatten(false, "items={ list=[{ id=1 name=a }, { id=2 tags=[x, y] }] empty=[] }")]
+#[case::flatten(
+    true,
+    "items.list[0].id=1 items.list[0].name=a items.list[1].id=2 items.list[1].tags[0]=x items.list[1].tags[1]=y items.empty=[]"
+)]
+fn test_flatten_arrays(#[case] flatten: bool, #[case] expected: &str) {
+    let items = json_raw_value(r#"{"list":[{"id":1,"name":"a"},{"id":2,"tags":["x","y"]}],"empty":[]}"#);
+    let rec = Record::from_fields(&[("items", RawObject::Json(&items).into())]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_flatten(flatten)
+        .with_flatten_arrays(true)
+        .build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_flatten_arrays_nested() {
+    let matrix = json_raw_value(r#"[[1,2],[3]]"#);
+    let rec = Record::from_fields(&[("m", RawValue::Array(RawArray::Json(&matrix)))]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_flatten(true)
+        .with_flatten_arrays(true)
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "m[0][0]=1 m[0][1]=2 m[1][0]=3"
+    );
+}
+
+#[test]
+fn test_flatten_bracketed_keys() {
+    let value = json_raw_value(r#"{"[tag]":{"[x]":1},"list":[{"[y]":2}]}"#);
+    let rec = Record::from_fields(&[("a", RawObject::Json(&value).into())]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_flatten(true)
+        .with_flatten_arrays(true)
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "a.[tag].[x]=1 a.list[0].[y]=2"
+    );
+}
+
+#[rstest]
+#[case::enabled(true, r#"a=x b="bad \ud800 value"! c=42"#)]
+#[case::disabled(false, r#"a=x b="bad \ud800 value" c=42"#)]
+fn test_field_diagnostics(#[case] enabled: bool, #[case] expected: &str) {
+    let rec = Record::from_fields(&[
+        ("a", EncodedString::json(r#""x""#).into()),
+        ("b", EncodedString::json(r#""bad \ud800 value""#).into()),
+        ("c", RawValue::Number("42")),
+    ]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_field_diagnostics(enabled)
+        .build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_field_diagnostics_styled() {
+    let rec = Record::from_fields(&[("b", EncodedString::json(r#""\ud800""#).into())]);
+
+    let formatter = formatter()
+        .with_ascii(AsciiMode::Off)
+        .with_field_diagnostics(true)
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32mb\x1b[0;2m=\x1b[0m\"\\ud800\"\x1b[0;33m⚠\x1b[0m"
+    );
+}
+
+#[rstest]
+#[case::info(
+    Level::Info,
+    "\x1b[0;2;3m00-01-02 03:04:05.123 \x1b[0;46m     \x1b[0m \x1b[0;1mhello\x1b[0m"
+)]
+#[case::error(
+    Level::Error,
+    "\x1b[0;91m00-01-02 03:04:05.123 \x1b[0;101m     \x1b[0m \x1b[0;1mhello\x1b[0m"
+)]
+fn test_level_style_block(#[case] level: Level, #[case] expected: &str) {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        level: Some(level),
+        message: Some(EncodedString::raw("hello").into()),
+        ..Default::default()
+    };
+
+    let styled = formatter().with_level_style(LevelStyle::Block).build();
+    assert_eq!(styled.format_to_string(&rec).unwrap(), expected);
+
+    let plain = formatter().with_theme(Default::default());
+    let text = plain.clone().build();
+    let block = plain.with_level_style(LevelStyle::Block).build();
+    assert_eq!(
+        block.format_to_string(&rec).unwrap().len(),
+        text.format_to_string(&rec).unwrap().len()
+    );
+}
+
+#[test]
+fn test_single_char_level() {
+    let rec = |level| Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        level: Some(level),
+        message: Some(EncodedString::raw("hello").into()),
+        ..Default::default()
+    };
+
+    let plain = formatter().with_theme(Default::default());
+    let default = plain.clone().build();
+    let single = plain.clone().with_single_char_level(true).build();
+    for (level, label) in [
+        (Level::Error, "ERR"),
+        (Level::Warning, "WRN"),
+        (Level::Info, "INF"),
+        (Level::Debug, "DBG"),
+    ] {
+        assert_eq!(
+            default.format_to_string(&rec(level)).unwrap(),
+            format!("00-01-02 03:04:05.123 |{label}| hello")
+        );
+        assert_eq!(
+            single.format_to_string(&rec(level)).unwrap(),
+            format!("00-01-02 03:04:05.123 {} hello", &label[..1])
+        );
+    }
+
+    let block = plain
+        .with_single_char_level(true)
+        .with_level_style(LevelStyle::Block)
+        .build();
+    assert_eq!(
+        block.format_to_string(&rec(Level::Error)).unwrap(),
+        "00-01-02 03:04:05.123   hello"
+    );
+
+    let rec = Record {
+        fields: RecordFields::from_slice(&[("a", RawValue::Number("1"))]),
+        ..rec(Level::Info)
+    };
+    let expanded = formatter()
+        .with_theme(Default::default())
+        .with_single_char_level(true)
+        .with_expansion(ExpansionMode::Always.into())
+        .build();
+    assert_eq!(
+        expanded.format_to_string(&rec).unwrap(),
+        "00-01-02 03:04:05.123 I hello\n                      ~   > a=1"
+    );
+
+    let unknown = formatter()
+        .with_theme(Default::default())
+        .with_single_char_level(true)
+        .with_always_show_level(true)
+        .build();
+    let rec = Record { level: None, ..rec };
+    assert_eq!(
+        unknown.format_to_string(&rec).unwrap(),
+        "00-01-02 03:04:05.123 # hello a=1"
+    );
+}
+
+#[rstest]
+#[case::double_spaces(r#""user  logged   in""#, "user logged in")]
+#[case::newline(r#""first line\n  second line""#, "first line second line")]
+#[case::tabs(r#""a\t\tb""#, "a b")]
+#[case::unchanged(r#""nothing to collapse""#, "nothing to collapse")]
+fn test_collapse_whitespace(#[case] message: &str, #[case] expected: &str) {
+    let rec = Record {
+        message: Some(EncodedString::json(message).into()),
+        ..Default::default()
+    };
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_collapse_whitespace(true)
+        .build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_collapse_whitespace_disabled() {
+    let rec = Record {
+        message: Some(EncodedString::json(r#""a  b""#).into()),
+        ..Default::default()
+    };
+
+    let formatter = formatter().with_theme(Default::default()).build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "a  b");
+}
+
+#[rstest]
+#[case::hex("flags", "31", "flags=0x1f")]
+#[case::binary("mask", "5", "mask=0b101")]
+#[case::octal("mode", "420", "mode=0o644")]
+#[case::negative("flags", "-31", "flags=-0x1f")]
+#[case::float("flags", "1.5", "flags=1.5")]
+#[case::other_key("other", "31", "other=31")]
+fn test_number_radix(#[case] key: &str, #[case] value: &str, #[case] expected: &str) {
+    let rec = Record::from_fields(&[(key, RawValue::Number(value))]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_number_radix([
+            ("flags", Radix::Hexadecimal),
+            ("mask", Radix::Binary),
+            ("mode", Radix::Octal),
+        ])
+        .build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_number_radix_styled() {
+    let ctx = json_raw_value(r#"{"flags":31}"#);
+    let rec = Record::from_fields(&[("ctx", RawObject::Json(&ctx).into()), ("flags", RawValue::Number("31"))]);
+
+    let formatter = formatter()
+        .with_flatten(true)
+        .with_number_radix([("ctx.flags", Radix::Hexadecimal)])
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32mctx.flags\x1b[0;2m=\x1b[0;94m0x1f \x1b[0;32mflags\x1b[0;2m=\x1b[0;94m31\x1b[0m"
+    );
+}
+
+#[rstest]
+#[case::negative("-5", "\x1b[0;31m-5")]
+#[case::zero("0", "\x1b[0;94m0")]
+#[case::negative_zero("-0.0", "\x1b[0;94m-0.0")]
+#[case::zero_exponent("0e10", "\x1b[0;94m0e10")]
+#[case::positive("42", "\x1b[0;32m42")]
+#[case::positive_fraction("0.5", "\x1b[0;32m0.5")]
+fn test_sign_aware_numbers(#[case] value: &str, #[case] expected: &str) {
+    let rec = Record::from_fields(&[("n", RawValue::Number(value))]);
+
+    let signed = formatter().with_sign_aware_numbers(true).build();
+    assert_eq!(
+        signed.format_to_string(&rec).unwrap(),
+        format!("\x1b[0m\x1b[0;32mn\x1b[0;2m={expected}\x1b[0m")
+    );
+
+    let unsigned = formatter().build();
+    assert_eq!(
+        unsigned.format_to_string(&rec).unwrap(),
+        format!("\x1b[0m\x1b[0;32mn\x1b[0;2m=\x1b[0;94m{value}\x1b[0m")
+    );
+}
+
+#[rstest]
+#[case::braces(ObjectStyle::Braces, "a=1 parent={ child1=1 child2={ x=y } } empty={} b=2")]
+#[case::parens(ObjectStyle::Parens, "a=1 parent(child1=1 child2={ x=y }) empty() b=2")]
+#[case::flatten(ObjectStyle::Flatten, "a=1 parent.child1=1 parent.child2.x=y b=2")]
+fn test_object_style(#[case] style: ObjectStyle, #[case] expected: &str) {
+    let parent = json_raw_value(r#"{"child1":1,"child2":{"x":"y"}}"#);
+    let empty = json_raw_value("{}");
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Number("1")),
+        ("parent", RawObject::Json(&parent).into()),
+        ("empty", RawObject::Json(&empty).into()),
+        ("b", RawValue::Number("2")),
+    ]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_object_style(style)
+        .build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_object_style_parens_styled() {
+    let parent = json_raw_value(r#"{"child":1}"#);
+    let rec = Record::from_fields(&[("parent", RawObject::Json(&parent).into())]);
+
+    let formatter = formatter()
+        .with_options(Formatting {
+            message: MessageFormatting {
+                format: MessageFormat::AutoQuoted,
+                template: None,
+            },
+            punctuation: Punctuation {
+                object_opening_parenthesis: "<".into(),
+                object_closing_parenthesis: ">".into(),
+                ..Sample::sample()
+            },
+            ..Default::default()
+        })
+        .with_object_style(ObjectStyle::Parens)
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32mparent\x1b[0;33m<\x1b[0;32mchild\x1b[0;2m=\x1b[0;94m1\x1b[0;33m>\x1b[0m"
+    );
+}
+
+#[test]
+fn test_level_message_position() {
+    let rec = |level| Record {
+        level: Some(level),
+        message: Some(EncodedString::raw("failed").into()),
+        fields: RecordFields::from_slice(&[
+            ("code", RawValue::Number("42")),
+            ("stack", EncodedString::raw("x").into()),
+        ]),
+        ..Default::default()
+    };
+
+    let errors_last = formatter()
+        .with_theme(Default::default())
+        .with_level_message_position(Level::Error, Some(MessagePosition::AfterFields))
+        .build();
+    assert_eq!(
+        errors_last.format_to_string(&rec(Level::Error)).unwrap(),
+        "|ERR| code=42 stack=x failed"
+    );
+    assert_eq!(
+        errors_last.format_to_string(&rec(Level::Info)).unwrap(),
+        "|INF| failed code=42 stack=x"
+    );
+
+    let info_first = formatter()
+        .with_theme(Default::default())
+        .with_message_position(MessagePosition::AfterFields)
+        .with_level_message_position(Level::Info, Some(MessagePosition::BeforeFields))
+        .build();
+    assert_eq!(
+        info_first.format_to_string(&rec(Level::Warning)).unwrap(),
+        "|WRN| code=42 stack=x failed"
+    );
+    assert_eq!(
+        info_first.format_to_string(&rec(Level::Info)).unwrap(),
+        "|INF| failed code=42 stack=x"
+    );
+}
+
+#[test]
+fn test_message_after_fields_expanded() {
+    let rec = Record {
+        level: Some(Level::Error),
+        message: Some(EncodedString::raw("line1\nline2").into()),
+        fields: RecordFields::from_slice(&[("code", RawValue::Number("42"))]),
+        ..Default::default()
+    };
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_message_position(MessagePosition::AfterFields)
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "|ERR| code=42\n| ~ |   > msg=|=>\n| ~ |      \tline1\n| ~ |      \tline2"
+    );
+}
+
+#[test]
+fn test_format_aligned_batch() {
+    let rec = |level, logger, message| Record {
+        level,
+        logger,
+        message: Some(EncodedString::raw(message).into()),
+        ..Default::default()
+    };
+    let recs = [
+        rec(Some(Level::Info), Some("db"), "connected"),
+        rec(Some(Level::Warning), Some("http.server"), "slow request"),
+        rec(None, None, "no logger"),
+        rec(Some(Level::Error), Some("app"), "failed"),
+    ];
+
+    let plain = formatter().with_theme(Default::default()).build();
+    let lines = plain
+        .format_aligned_batch(&recs)
+        .into_iter()
+        .map(|buf| String::from_utf8(buf).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "|INF| db:          connected",
+            "|WRN| http.server: slow request",
+            "                   no logger",
+            "|ERR| app:         failed",
+        ]
+    );
+
+    assert_eq!(plain.format_to_string(&recs[0]).unwrap(), "|INF| db: connected");
+    assert!(plain.format_aligned_batch(&[]).is_empty());
+}
+
+#[test]
+fn test_logger_separator_suppressed() {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        level: Some(Level::Info),
+        logger: Some("db"),
+        ..Default::default()
+    };
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(plain.format_to_string(&rec).unwrap(), "00-01-02 03:04:05.123 |INF| db");
+
+    let rec = Record {
+        message: Some(EncodedString::raw("connected").into()),
+        ..rec
+    };
+    assert_eq!(
+        plain.format_to_string(&rec).unwrap(),
+        "00-01-02 03:04:05.123 |INF| db: connected"
+    );
+}
+
+#[test]
+fn test_logger_separator_suppressed_styled() {
+    let rec = Record {
+        level: Some(Level::Info),
+        logger: Some("db"),
+        ..Default::default()
+    };
+
+    let styled = formatter().build();
+    let result = styled.format_to_string(&rec).unwrap();
+    assert!(result.contains("db"), "{result:?}");
+    assert!(!result.contains(':'), "{result:?}");
+}
+
+#[test]
+fn test_message_fallback_keys() {
+    let rec = Record::from_fields(&[
+        ("code", RawValue::Number("42")),
+        ("event", EncodedString::raw("user created").into()),
+        ("error", EncodedString::raw("none").into()),
+    ]);
+
+    let fallback = formatter()
+        .with_theme(Default::default())
+        .with_message_fallback_keys(["message", "event", "error"])
+        .build();
+    assert_eq!(
+        fallback.format_to_string(&rec).unwrap(),
+        "user created code=42 error=none"
+    );
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        plain.format_to_string(&rec).unwrap(),
+        r#"code=42 event="user created" error=none"#
+    );
+}
+
+#[test]
+fn test_message_fallback_keys_message_present() {
+    let rec = Record {
+        message: Some(EncodedString::raw("hello").into()),
+        fields: RecordFields::from_slice(&[("event", EncodedString::raw("user created").into())]),
+        ..Default::default()
+    };
+
+    let fallback = formatter()
+        .with_theme(Default::default())
+        .with_message_fallback_keys(["event"])
+        .build();
+    assert_eq!(
+        fallback.format_to_string(&rec).unwrap(),
+        r#"hello event="user created""#
+    );
+}
+
+#[rstest]
+#[case::full(CallerPath::Full, "/home/user/project/src/main.rs:42")]
+#[case::basename(CallerPath::Basename, "main.rs:42")]
+#[case::last_two(CallerPath::LastN(2), "src/main.rs:42")]
+#[case::last_many(CallerPath::LastN(10), "/home/user/project/src/main.rs:42")]
+fn test_caller_path(#[case] caller_path: CallerPath, #[case] expected: &str) {
+    let rec = Record {
+        message: Some(EncodedString::raw("hello").into()),
+        caller: Caller::with_file_line("/home/user/project/src/main.rs", "42"),
+        ..Default::default()
+    };
+
+    let truncated = formatter()
+        .with_theme(Default::default())
+        .with_caller_path(caller_path)
+        .build();
+    assert_eq!(
+        truncated.format_to_string(&rec).unwrap(),
+        format!("hello -> {expected}")
+    );
+}
+
+#[test]
+fn test_caller_path_windows() {
+    assert_eq!(CallerPath::Basename.apply(r"C:\project\src\main.rs"), "main.rs");
+    assert_eq!(CallerPath::LastN(2).apply(r"C:\project\src\main.rs"), r"src\main.rs");
+    assert_eq!(CallerPath::Basename.apply("main.rs"), "main.rs");
+}
+
+#[test]
+fn test_expand_table() {
+    let rec = Record {
+        level: Some(Level::Info),
+        message: Some(EncodedString::raw("request").into()),
+        fields: RecordFields::from_slice(&[
+            ("method", EncodedString::raw("GET").into()),
+            ("status", RawValue::Number("200")),
+            ("id", RawValue::Number("7")),
+        ]),
+        ..Default::default()
+    };
+
+    let table = formatter()
+        .with_theme(Default::default())
+        .with_expand_table(true)
+        .build();
+    assert_eq!(
+        table.format_to_string(&rec).unwrap(),
+        "|INF| request\n| ~ |   > method  GET\n| ~ |   > status  200\n| ~ |   > id      7"
+    );
+
+    let styled = formatter().with_expand_table(true).build();
+    let result = styled.format_to_string(&rec).unwrap();
+    assert!(
+        result.ends_with("\x1b[0;32mid\x1b[0;2m      \x1b[0;94m7\x1b[0m"),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_expand_table_hidden_keys() {
+    let rec = Record {
+        message: Some(EncodedString::raw("request").into()),
+        fields: RecordFields::from_slice(&[
+            ("method", EncodedString::raw("GET").into()),
+            ("very_long_hidden_key", RawValue::Number("1")),
+            ("another_long_null_key", RawValue::Null),
+            ("ctx.status", RawValue::Number("200")),
+        ]),
+        ..Default::default()
+    };
+    let mut fields = IncludeExcludeKeyFilter::default();
+    fields.entry("very_long_hidden_key").exclude();
+
+    let table = formatter()
+        .with_theme(Default::default())
+        .with_field_filter(fields.into())
+        .with_null_fields_hiding(true)
+        .with_strip_key_prefix(Some("ctx.".into()))
+        .with_expand_table(true)
+        .build();
+    assert_eq!(
+        table.format_to_string(&rec).unwrap(),
+        "request\n  > method  GET\n  > status  200\n  > ..."
+    );
+}
+
+#[rstest]
+#[case::strings("tags", r#"["a","b","c"]"#, "tags=a, b, c")]
+#[case::numbers("tags", "[1,2]", "tags=1, 2")]
+#[case::mixed("tags", r#"["a",{"b":1}]"#, "tags=[a, { b=1 }]")]
+#[case::nested("tags", r#"["a",["b"]]"#, "tags=[a, [b]]")]
+#[case::empty("tags", "[]", "tags=[]")]
+#[case::other_key("other", r#"["a","b"]"#, "other=[a, b]")]
+fn test_compact_arrays(#[case] key: &str, #[case] value: &str, #[case] expected: &str) {
+    let arr = json_raw_value(value);
+    let rec = Record::from_fields(&[(key, RawArray::Json(&arr).into())]);
+
+    let compact = formatter()
+        .with_theme(Default::default())
+        .with_compact_arrays(["tags"])
+        .build();
+    assert_eq!(compact.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_message_width() {
+    let rec = |message| Record {
+        message: Some(EncodedString::raw(message).into()),
+        fields: RecordFields::from_slice(&[("code", RawValue::Number("42"))]),
+        ..Default::default()
+    };
+
+    let padded = formatter()
+        .with_theme(Default::default())
+        .with_message_width(Some(12))
+        .build();
+    assert_eq!(padded.format_to_string(&rec("short")).unwrap(), "short        code=42");
+    assert_eq!(padded.format_to_string(&rec("résumé")).unwrap(), "résumé       code=42");
+    assert_eq!(
+        padded.format_to_string(&rec("a much longer message")).unwrap(),
+        "a much longer message code=42"
+    );
+
+    let styled = formatter().with_message_width(Some(8)).build();
+    assert_eq!(
+        styled.format_to_string(&rec("short")).unwrap(),
+        "\x1b[0;1mshort\x1b[0m    \x1b[0;32mcode\x1b[0;2m=\x1b[0;94m42\x1b[0m"
+    );
+}
+
+#[rstest]
+#[case::upper(LevelCase::Upper, Level::Debug, "|DBG| hello")]
+#[case::lower(LevelCase::Lower, Level::Debug, "|dbg| hello")]
+#[case::title(LevelCase::Title, Level::Debug, "|Dbg| hello")]
+#[case::title_warning(LevelCase::Title, Level::Warning, "|Wrn| hello")]
+fn test_level_case(#[case] level_case: LevelCase, #[case] level: Level, #[case] expected: &str) {
+    let rec = Record {
+        level: Some(level),
+        message: Some(EncodedString::raw("hello").into()),
+        ..Default::default()
+    };
+
+    let cased = formatter()
+        .with_theme(Default::default())
+        .with_level_case(level_case)
+        .build();
+    assert_eq!(cased.format_to_string(&rec).unwrap(), expected);
+}
+
+struct Base64Decode;
+
+impl ValueTransform for Base64Decode {
+    fn matches(&self, key: &str, value: RawValue) -> bool {
+        key == "data" && matches!(value, RawValue::String(_))
+    }
+
+    fn transform(&self, _: &str, value: RawValue) -> Option<String> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let RawValue::String(value) = value else { return None };
+        let mut encoded = Vec::new();
+        value.decode(&mut encoded).ok()?;
+
+        let mut decoded = Vec::new();
+        let mut acc = 0u32;
+        let mut bits = 0;
+        for c in encoded.iter().take_while(|&&c| c != b'=') {
+            acc = acc << 6 | ALPHABET.iter().position(|a| a == c)? as u32;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                decoded.push((acc >> bits) as u8);
+            }
+        }
+        String::from_utf8(decoded).ok()
+    }
+}
+
+struct Lowercase;
+
+impl ValueTransform for Lowercase {
+    fn matches(&self, _: &str, value: RawValue) -> bool {
+        matches!(value, RawValue::String(_))
+    }
+
+    fn transform(&self, _: &str, value: RawValue) -> Option<String> {
+        let RawValue::String(value) = value else { return None };
+        let mut decoded = Vec::new();
+        value.decode(&mut decoded).ok()?;
+        Some(String::from_utf8(decoded).ok()?.to_lowercase())
+    }
+}
+
+#[test]
+fn test_value_transforms() {
+    let rec = Record::from_fields(&[
+        ("data", EncodedString::json(r#""aGVsbG8gd29ybGQ=""#).into()),
+        ("name", EncodedString::json(r#""ALICE""#).into()),
+        ("size", EncodedString::json(r#""NDI=""#).into()),
+        ("code", RawValue::Number("42")),
+    ]);
+
+    let transformed = formatter()
+        .with_theme(Default::default())
+        .with_value_transforms(vec![Arc::new(Base64Decode), Arc::new(Lowercase)])
+        .build();
+    assert_eq!(
+        transformed.format_to_string(&rec).unwrap(),
+        r#"data="hello world" name=alice size="ndi=" code=42"#
+    );
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        plain.format_to_string(&rec).unwrap(),
+        r#"data="aGVsbG8gd29ybGQ=" name=ALICE size="NDI=" code=42"#
+    );
+}
+
+#[rstest]
+#[case::true_string(r#""true""#, "a=true")]
+#[case::no_string(r#""no""#, "a=false")]
+#[case::truthy(r#""truthy""#, "a=truthy")]
+#[case::escaped(r#""n\u006f""#, "a=false")]
+#[case::case_sensitive(r#""YES""#, "a=YES")]
+fn test_coerce_bool_strings(#[case] value: &str, #[case] expected: &str) {
+    let rec = Record::from_fields(&[("a", EncodedString::json(value).into())]);
+
+    let coerced = formatter()
+        .with_theme(Default::default())
+        .with_coerce_bool_strings(Some(BoolStrings::default()))
+        .build();
+    assert_eq!(coerced.format_to_string(&rec).unwrap(), expected);
+}
+
+#[test]
+fn test_coerce_bool_strings_styled() {
+    let rec = Record::from_fields(&[
+        ("a", EncodedString::json(r#""on""#).into()),
+        ("b", EncodedString::json(r#""true""#).into()),
+    ]);
+
+    let coerced = formatter()
+        .with_coerce_bool_strings(Some(BoolStrings::new(["on"], ["off"])))
+        .with_boolean_labels(Some(("yes".into(), "no".into())))
+        .build();
+    assert_eq!(
+        coerced.format_to_string(&rec).unwrap(),
+        "\x1b[0m\x1b[0;32ma\x1b[0;2m=\x1b[0;92myes \x1b[0;32mb\x1b[0;2m=\x1b[0m\"true\"\x1b[0m"
+    );
+}
+
+#[test]
+fn test_anonymize() {
+    let rec = Record::from_fields(&[
+        ("ip", EncodedString::json(r#""192.168.1.42""#).into()),
+        ("email", EncodedString::json(r#""a@b.com""#).into()),
+        ("note", EncodedString::json(r#""sent to a@b.com from 10.0.0.7""#).into()),
+    ]);
+
+    let masked = formatter()
+        .with_theme(Default::default())
+        .with_anonymize(AnonymizeTargets::all())
+        .build();
+    assert_eq!(
+        masked.format_to_string(&rec).unwrap(),
+        r#"ip=192.168.1.* email=*@b.com note="sent to *@b.com from 10.0.0.*""#
+    );
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        plain.format_to_string(&rec).unwrap(),
+        r#"ip=192.168.1.42 email=a@b.com note="sent to a@b.com from 10.0.0.7""#
+    );
+}
+
+#[test]
+fn test_anonymize_everywhere() {
+    let rec = Record {
+        message: Some(EncodedString::json(r#""connected to 10.0.0.7""#).into()),
+        ..Record::from_fields(&[
+            ("bin", EncodedString::json(r#""10.0.0.7\ud800""#).into()),
+            ("json", EncodedString::json(r#""{\"peer\":\"10.0.0.7\"}""#).into()),
+        ])
+    };
+    let masked = || {
+        formatter()
+            .with_theme(Default::default())
+            .with_anonymize(AnonymizeTargets::all())
+    };
+
+    assert_eq!(
+        masked()
+            .with_hex_undecodable(true)
+            .with_expand_embedded_json(true)
+            .build()
+            .format_to_string(&rec)
+            .unwrap(),
+        "connected to 10.0.0.* bin=<hex:31302e302e302e2aeda080> json={ peer=10.0.0.* }"
+    );
+
+    assert_eq!(
+        masked().with_raw_fields(true).build().format_to_string(&rec).unwrap(),
+        r#"connected to 10.0.0.* bin="10.0.0.*\ud800" json="{\"peer\":\"10.0.0.*\"}""#
+    );
+
+    let rec = Record::from_fields(&[("trace_id", EncodedString::json(r#""::ffff:10.0.0.1""#).into())]);
+    assert_eq!(
+        masked()
+            .with_trace_context(Some(TraceContext::default()))
+            .build()
+            .format_to_string(&rec)
+            .unwrap(),
+        "trace-id=::ffff:10.0.0.*"
+    );
+}
+
+#[test]
+fn test_trace_context() {
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Number("1")),
+        ("span_id", EncodedString::json(r#""00f067aa0ba902b7""#).into()),
+        (
+            "trace_id",
+            EncodedString::json(r#""4bf92f3577b34da6a3ce929d0e0e4736""#).into(),
+        ),
+    ]);
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        plain.format_to_string(&rec).unwrap(),
+        "a=1 span-id=00f067aa0ba902b7 trace-id=4bf92f3577b34da6a3ce929d0e0e4736"
+    );
+
+    let pinned = formatter()
+        .with_theme(Default::default())
+        .with_trace_context(Some(TraceContext::default()))
+        .build();
+    assert_eq!(
+        pinned.format_to_string(&rec).unwrap(),
+        "trace-id=4bf92f35 span-id=00f067aa a=1"
+    );
+
+    let full = formatter()
+        .with_theme(Default::default())
+        .with_trace_context(Some(TraceContext::default().with_short_len(None)))
+        .build();
+    assert_eq!(
+        full.format_to_string(&rec).unwrap(),
+        "trace-id=4bf92f3577b34da6a3ce929d0e0e4736 span-id=00f067aa0ba902b7 a=1"
+    );
+}
+
+#[test]
+fn test_trace_link_template() {
+    let rec = Record::from_fields(&[
+        (
+            "trace_id",
+            EncodedString::json(r#""4bf92f3577b34da6a3ce929d0e0e4736""#).into(),
+        ),
+        ("span_id", EncodedString::json(r#""00f067aa0ba902b7""#).into()),
+    ]);
+
+    let tc =
+        TraceContext::default().with_trace_link_template(Some("https://tracing.example.com/trace/{trace_id}".into()));
+    let linked = formatter()
+        .with_theme(Default::default())
+        .with_trace_context(Some(tc))
+        .build();
+    assert_eq!(
+        linked.format_to_string(&rec).unwrap(),
+        concat!(
+            "trace-id=\x1b]8;;https://tracing.example.com/trace/4bf92f3577b34da6a3ce929d0e0e4736\x1b\\",
+            "4bf92f35\x1b]8;;\x1b\\ span-id=00f067aa",
+        )
+    );
+
+    let rec = Record::from_fields(&[("trace_id", EncodedString::json(r#""not a trace id""#).into())]);
+    assert_eq!(linked.format_to_string(&rec).unwrap(), r#"trace-id="not a trace id""#);
+}
+
+#[test]
+fn test_empty_record_placeholder() {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        ..Default::default()
+    };
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(plain.format_to_string(&rec).unwrap(), "00-01-02 03:04:05.123");
+
+    let placeholder = formatter()
+        .with_theme(Default::default())
+        .with_empty_record_placeholder(Some("(no message)".into()))
+        .build();
+    assert_eq!(
+        placeholder.format_to_string(&rec).unwrap(),
+        "00-01-02 03:04:05.123 (no message)"
+    );
+
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        fields: RecordFields::from_slice(&[("a", RawValue::Number("1"))]),
+        ..Default::default()
+    };
+    assert_eq!(placeholder.format_to_string(&rec).unwrap(), "00-01-02 03:04:05.123 a=1");
+
+    let styled = formatter()
+        .with_empty_record_placeholder(Some("(no message)".into()))
+        .build();
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        ..Default::default()
+    };
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        "\u{1b}[0;2;3m00-01-02 03:04:05.123\u{1b}[0m \u{1b}[0;2m(no message)\u{1b}[0m"
+    );
+}
+
+#[test]
+fn test_multiline_block_indent() {
+    let rec = Record {
+        message: Some(RawValue::String(EncodedString::json(r#""failed""#))),
+        fields: RecordFields::from_slice(&[
+            (
+                "stack",
+                EncodedString::json(
+                    r#""java.lang.IllegalStateException: boom\n\tat com.example.Main.run(Main.java:42)""#,
+                )
+                .into(),
+            ),
+            ("a", RawValue::Number("1")),
+        ]),
+        ..Default::default()
+    };
+
+    let block = formatter()
+        .with_theme(Default::default())
+        .with_multiline_block_indent(Some(4))
+        .build();
+    assert_eq!(
+        block.format_to_string(&rec).unwrap(),
+        concat!(
+            "failed stack=\n",
+            "    java.lang.IllegalStateException: boom\n",
+            "    \tat com.example.Main.run(Main.java:42) a=1",
+        )
+    );
+
+    let rec = Record {
+        fields: RecordFields::from_slice(&[("stack", EncodedString::json(r#""single line""#).into())]),
+        ..Default::default()
+    };
+    assert_eq!(block.format_to_string(&rec).unwrap(), r#"stack="single line""#);
+}
+
+#[test]
+fn test_field_styles() {
+    let rec = Record::from_fields(&[
+        ("error", EncodedString::json(r#""boom""#).into()),
+        ("error_code", RawValue::Number("42")),
+        ("a", RawValue::Number("1")),
+    ]);
+
+    let styles = HashMap::from([
+        (
+            "error".to_owned(),
+            FieldStyle {
+                key: Some(Element::LevelInner),
+                value: Some(Element::HttpStatusServerError),
+            },
+        ),
+        (
+            "error_code".to_owned(),
+            FieldStyle {
+                key: None,
+                value: Some(Element::HttpStatusServerError),
+            },
+        ),
+    ]);
+    let styled = formatter().with_field_styles(styles).build();
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        concat!(
+            "\u{1b}[0m\u{1b}[0;36merror\u{1b}[0;2m=\u{1b}[0;31mboom ",
+            "\u{1b}[0;32merror-code\u{1b}[0;2m=\u{1b}[0;31m42 ",
+            "\u{1b}[0;32ma\u{1b}[0;2m=\u{1b}[0;94m1\u{1b}[0m",
+        )
+    );
+}
+
+#[test]
+fn test_time_position_trailing() {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        message: Some(RawValue::String(EncodedString::json(r#""tm""#))),
+        level: Some(Level::Info),
+        fields: RecordFields::from_slice(&[("a", RawValue::Number("1"))]),
+        ..Default::default()
+    };
+
+    let trailing = formatter()
+        .with_theme(Default::default())
+        .with_time_position(TimePosition::Trailing)
+        .build();
+    assert_eq!(
+        trailing.format_to_string(&rec).unwrap(),
+        "|INF| tm a=1 00-01-02 03:04:05.123"
+    );
+
+    let aligned = formatter()
+        .with_theme(Default::default())
+        .with_time_position(TimePosition::TrailingAligned(40))
+        .build();
+    assert_eq!(
+        aligned.format_to_string(&rec).unwrap(),
+        "|INF| tm a=1       00-01-02 03:04:05.123"
+    );
+
+    let narrow = formatter()
+        .with_theme(Default::default())
+        .with_time_position(TimePosition::TrailingAligned(20))
+        .build();
+    assert_eq!(
+        narrow.format_to_string(&rec).unwrap(),
+        "|INF| tm a=1 00-01-02 03:04:05.123"
+    );
+}
+
+#[test]
+fn test_time_position_trailing_styled() {
+    let rec = Record {
+        ts: Some(Timestamp::new("2000-01-02T03:04:05.123Z")),
+        message: Some(RawValue::String(EncodedString::json(r#""tm""#))),
+        level: Some(Level::Info),
+        ..Default::default()
+    };
+
+    let trailing = formatter().with_time_position(TimePosition::Trailing).build();
+    assert_eq!(
+        trailing.format_to_string(&rec).unwrap(),
+        "\u{1b}[0;36m|INF|\u{1b}[0m \u{1b}[0;1mtm\u{1b}[0m \u{1b}[0;2;3m00-01-02 03:04:05.123\u{1b}[0m"
+    );
+
+    let rec = Record {
+        ts: Some(Timestamp::new("invalid")),
+        message: Some(RawValue::String(EncodedString::json(r#""tm""#))),
+        ..Default::default()
+    };
+    let trailing = formatter()
+        .with_theme(Default::default())
+        .with_time_position(TimePosition::Trailing)
+        .build();
+    assert_eq!(trailing.format_to_string(&rec).unwrap(), "tm ts=invalid");
+}
+
+#[test]
+fn test_malformed_nested_values() {
+    let obj = json_raw_value("[1,2]");
+    let arr = json_raw_value(r#"{"a":1}"#);
+    let rec = Record::from_fields(&[
+        ("obj", RawValue::Object(RawObject::Json(&obj))),
+        ("arr", RawArray::Json(&arr).into()),
+        ("b", RawValue::Number("1")),
+    ]);
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(plain.format_to_string(&rec).unwrap(), r#"obj=[1,2] arr={"a":1} b=1"#);
+
+    let flatten = formatter()
+        .with_theme(Default::default())
+        .with_flatten(true)
+        .with_flatten_arrays(true)
+        .build();
+    assert_eq!(flatten.format_to_string(&rec).unwrap(), r#"obj=[1,2] arr={"a":1} b=1"#);
+
+    let styled = formatter().build();
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        concat!(
+            "\u{1b}[0m\u{1b}[0;32mobj\u{1b}[0;2m=\u{1b}[0;33m[1,2] ",
+            "\u{1b}[0;32marr\u{1b}[0;2m=\u{1b}[0;33m{\"a\":1} ",
+            "\u{1b}[0;32mb\u{1b}[0;2m=\u{1b}[0;94m1\u{1b}[0m",
+        )
+    );
+}
+
+#[test]
+fn test_malformed_message() {
+    let rec = Record {
+        message: Some(RawValue::String(EncodedString::json(r#""bad \u12 escape""#))),
+        fields: RecordFields::from_slice(&[("a", RawValue::Number("1"))]),
+        ..Default::default()
+    };
+
+    let plain = formatter().with_theme(Default::default()).build();
+    assert_eq!(plain.format_to_string(&rec).unwrap(), r#""bad \u12 escape" a=1"#);
+}
+
+#[test]
+fn test_max_fields() {
+    let keys = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+    let values = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+    let fields = keys
+        .iter()
+        .zip(values)
+        .map(|(k, v)| (*k, RawValue::Number(v)))
+        .collect_vec();
+    let rec = Record::from_fields(&fields);
+
+    let capped = formatter()
+        .with_theme(Default::default())
+        .with_max_fields(Some(3))
+        .build();
+    assert_eq!(capped.format_to_string(&rec).unwrap(), "a=0 b=1 c=2 ...+7");
+
+    let mut filter = IncludeExcludeKeyFilter::default();
+    filter.entry("b").exclude();
+    filter.entry("i").exclude();
+    let capped = formatter()
+        .with_theme(Default::default())
+        .with_max_fields(Some(3))
+        .with_field_filter(Arc::new(filter))
+        .build();
+    assert_eq!(capped.format_to_string(&rec).unwrap(), "a=0 c=2 d=3 ...+5");
+
+    let uncapped = formatter()
+        .with_theme(Default::default())
+        .with_max_fields(Some(10))
+        .build();
+    assert_eq!(
+        uncapped.format_to_string(&rec).unwrap(),
+        "a=0 b=1 c=2 d=3 e=4 f=5 g=6 h=7 i=8 j=9"
+    );
+}
+
+#[test]
+fn test_logger_colors() {
+    let colors = LoggerColors::default();
+    let (db, api) = (colors.color("db").unwrap(), colors.color("api").unwrap());
+    assert_ne!(db, api);
+    assert_eq!(colors.color("db"), Some(db));
+    assert_eq!(LoggerColors::new([]).color("db"), None);
+
+    let rec = |logger, message| Record {
+        logger: Some(logger),
+        message: Some(EncodedString::raw(message).into()),
+        ..Default::default()
+    };
+    let colored = |logger| format!("\x1b[38;5;{}m{}", colors.color(logger).unwrap(), logger);
+
+    let styled = formatter().with_logger_colors(Some(colors.clone())).build();
+    let first = styled.format_to_string(&rec("db", "first")).unwrap();
+    let second = styled.format_to_string(&rec("db", "second")).unwrap();
+    let other = styled.format_to_string(&rec("api", "third")).unwrap();
+    assert!(first.contains(&colored("db")), "{first:?}");
+    assert!(second.contains(&colored("db")), "{second:?}");
+    assert!(other.contains(&colored("api")), "{other:?}");
+    assert!(!other.contains(&format!("38;5;{db}m")), "{other:?}");
+
+    let plain = formatter()
+        .with_theme(Default::default())
+        .with_logger_colors(Some(colors))
+        .build();
+    assert_eq!(plain.format_to_string(&rec("db", "first")).unwrap(), "db: first");
+}
+
+#[test]
+fn test_object_style_block() {
+    let parent = json_raw_value(r#"{"child1":1,"child2":{"x":"y"},"items":[1,{"k":"v","n":null},[2,3]],"empty":[]}"#);
+    let rec = Record::from_fields(&[
+        ("a", RawValue::Number("1")),
+        ("parent", RawObject::Json(&parent).into()),
+        ("b", RawValue::Number("2")),
+    ]);
+
+    let inline = formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        inline.format_to_string(&rec).unwrap(),
+        "a=1 parent={ child1=1 child2={ x=y } items=[1, { k=v n=null }, [2, 3]] empty=[] } b=2"
+    );
+
+    let block = formatter()
+        .with_theme(Default::default())
+        .with_object_style(ObjectStyle::Block(2))
+        .build();
+    assert_eq!(
+        block.format_to_string(&rec).unwrap(),
+        concat!(
+            "a=1 parent:\n",
+            "  child1: 1\n",
+            "  child2:\n",
+            "    x: y\n",
+            "  items:\n",
+            "    - 1\n",
+            "    - k: v\n",
+            "      n: null\n",
+            "    - - 2\n",
+            "      - 3\n",
+            "  empty: [] b=2",
+        )
+    );
+
+    let styled = formatter().with_object_style(ObjectStyle::Block(4)).build();
+    let parent = json_raw_value(r#"{"x":[1]}"#);
+    let rec = Record::from_fields(&[("parent", RawObject::Json(&parent).into())]);
+    assert_eq!(
+        styled.format_to_string(&rec).unwrap(),
+        concat!(
+            "\x1b[0m\x1b[0;32mparent\x1b[0;2m:\x1b[0m\n",
+            "    \x1b[0;32mx\x1b[0;2m:\x1b[0m\n",
+            "        \x1b[0;93m- \x1b[0;94m1\x1b[0m",
+        )
+    );
+}
+
+#[test]
+fn test_field_sort() {
+    let nested = json_raw_value(r#"{"y":1,"x":2}"#);
+    let rec = Record::from_fields(&[
+        ("b", RawValue::Number("1")),
+        ("event", EncodedString::raw("created").into()),
+        ("c", RawValue::Object(RawObject::Json(&nested))),
+        ("a", RawValue::Number("3")),
+    ]);
+
+    let reversed = formatter()
+        .with_theme(Default::default())
+        .with_field_sort(Some(Arc::new(|a: &str, b: &str| b.cmp(a))))
+        .build();
+    assert_eq!(
+        reversed.format_to_string(&rec).unwrap(),
+        "event=created c={ y=1 x=2 } b=1 a=3"
+    );
+
+    let message_last = formatter()
+        .with_theme(Default::default())
+        .with_field_sort(Some(keys_last_field_sort(["event", "b"])))
+        .build();
+    assert_eq!(
+        message_last.format_to_string(&rec).unwrap(),
+        "c={ y=1 x=2 } a=3 event=created b=1"
+    );
+}
+
+#[test]
+fn test_hex_undecodable() {
+    let rec = Record::from_fields(&[
+        ("a", EncodedString::json(r#""A\ud800\n""#).into()),
+        ("b", EncodedString::json(r#""😀\udc00""#).into()),
+        ("c", EncodedString::json(r#""ok""#).into()),
+    ]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_hex_undecodable(true)
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "a=<hex:41eda0800a> b=<hex:f09f9880edb080> c=ok"
+    );
+
+    let formatter = self::formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        r#"a="A\ud800\n" b="😀\udc00" c=ok"#
+    );
+}
+
+#[test]
+fn test_compact_timestamp() {
+    let rec = |ts| Record {
+        ts: Some(Timestamp::new(ts)),
+        message: Some(EncodedString::raw("m").into()),
+        ..Default::default()
+    };
+
+    let formatter = CompactTimestampFormatter::new(formatter().with_theme(Default::default()).build());
+    let lines = [
+        "2000-01-01T23:59:58.000Z",
+        "2000-01-01T23:59:59.000Z",
+        "2000-01-02T00:00:00.000Z",
+        "2000-01-02T00:00:01.000Z",
+        "2000-01-01T23:59:59.500Z",
+    ]
+    .map(|ts| {
+        let mut buf = Vec::new();
+        formatter.format_record(&mut buf, 0..0, model::RecordWithSource::new(&rec(ts), b""));
+        String::from_utf8(buf).unwrap()
+    });
+    assert_eq!(
+        lines,
+        [
+            "00-01-01 23:59:58.000 m",
+            "23:59:59.000          m",
+            "00-01-02 00:00:00.000 m",
+            "00:00:01.000          m",
+            "00-01-01 23:59:59.500 m",
+        ]
+    );
+
+    let formatter = self::formatter().with_theme(Default::default()).build();
+    assert_eq!(
+        formatter.format_to_string(&rec("2000-01-01T23:59:59.000Z")).unwrap(),
+        "00-01-01 23:59:59.000 m"
+    );
+}
+
+#[test]
+fn test_format_iter() {
+    let records = || {
+        ["first", "second", "third"].map(|message| Record {
+            level: Some(Level::Info),
+            message: Some(EncodedString::raw(message).into()),
+            ..Default::default()
+        })
+    };
+    let recs = records();
+
+    let formatter = formatter().build();
+    let expected = recs.iter().map(|rec| formatter.format_to_vec(rec)).collect_vec();
+    assert_eq!(formatter.format_iter(&recs).collect_vec(), expected);
+    assert_eq!(formatter.format_iter(records()).collect_vec(), expected);
+    assert_eq!(
+        formatter
+            .format_iter(
+                recs.iter()
+                    .filter(|rec| rec.message.is_some_and(|m| m.raw_str() != "second"))
+            )
+            .count(),
+        2
+    );
+}
+
+#[rstest]
+#[case::decimal_places(NumberRounding::DecimalPlaces(3), "0.123456789", "0.123")]
+#[case::decimal_places_integer(NumberRounding::DecimalPlaces(3), "42", "42")]
+#[case::decimal_places_padded(NumberRounding::DecimalPlaces(3), "1.5", "1.500")]
+#[case::decimal_places_negative_zero(NumberRounding::DecimalPlaces(2), "-0.0001", "0.00")]
+#[case::decimal_places_exponent(NumberRounding::DecimalPlaces(2), "1.2345e-7", "1.23e-7")]
+#[case::significant_digits(NumberRounding::SignificantDigits(3), "0.000123456", "0.000123")]
+#[case::significant_digits_large(NumberRounding::SignificantDigits(3), "12345.678", "12346")]
+#[case::significant_digits_integer(NumberRounding::SignificantDigits(1), "12345", "12345")]
+#[case::significant_digits_exponent(NumberRounding::SignificantDigits(3), "1.2345E+10", "1.23e10")]
+#[case::decimal_places_large(NumberRounding::DecimalPlaces(1), "12345678901234567.5", "12345678901234567.5")]
+#[case::decimal_places_large_rounded(NumberRounding::DecimalPlaces(0), "12345678901234567.5", "12345678901234568")]
+#[case::decimal_places_many(NumberRounding::DecimalPlaces(20), "0.1", "0.10000000000000000000")]
+#[case::decimal_places_carry(NumberRounding::DecimalPlaces(2), "-9.999", "-10.00")]
+#[case::significant_digits_carry(NumberRounding::SignificantDigits(3), "9.9996", "10.00")]
+#[case::significant_digits_zero(NumberRounding::SignificantDigits(3), "0.0", "0.00")]
+#[case::exponent_zero(NumberRounding::DecimalPlaces(2), "0e5", "0.00e0")]
+fn test_number_rounding(#[case] rounding: NumberRounding, #[case] value: &str, #[case] expected: &str) {
+    let rec = Record::from_fields(&[("n", RawValue::Number(value))]);
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_number_rounding(Some(rounding))
+        .build();
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), format!("n={expected}"));
+}
+
+#[test]
+fn test_format_header() {
+    let formatter = formatter().with_theme(Default::default()).build();
+    let header = String::from_utf8(formatter.format_header()).unwrap();
+    assert_eq!(
+        header,
+        concat!(
+            "time                  level logger: message key=value\n",
+            "|ERR| error  |WRN| warning  |INF| info  |DBG| debug  |TRC| trace",
+        )
+    );
+
+    let formatter = self::formatter().build();
+    let header = String::from_utf8(formatter.format_header()).unwrap();
+    let legend = header.lines().nth(1).unwrap();
+    assert_eq!(
+        legend,
+        concat!(
+            "\x1b[0;7;91m|ERR| \x1b[0;1merror\x1b[0m  ",
+            "\x1b[0;7;33m|WRN| \x1b[0;1mwarning\x1b[0m  ",
+            "\x1b[0;36m|INF| \x1b[0;1minfo\x1b[0m  ",
+            "\x1b[0;36m|\x1b[0;95mDBG\x1b[0;36m| \x1b[0;1mdebug\x1b[0m  ",
+            "\x1b[0;36m|\x1b[0;2mTRC\x1b[0;36m| \x1b[0;1mtrace\x1b[0m",
+        )
+    );
+}
+
+#[test]
+fn test_level_severities() {
+    let rec = |level| Record {
+        level: Some(level),
+        message: Some(EncodedString::raw("m").into()),
+        ..Default::default()
+    };
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_level_severities(Some(LevelSeverities::syslog()))
+        .build();
+    assert_eq!(formatter.format_to_string(&rec(Level::Info)).unwrap(), "|INF(6)| m");
+    assert_eq!(formatter.format_to_string(&rec(Level::Error)).unwrap(), "|ERR(3)| m");
+
+    let formatter = self::formatter()
+        .with_theme(Default::default())
+        .with_level_severities(Some(LevelSeverities::new(enum_map::enum_map! {
+            Level::Error => 50,
+            Level::Warning => 40,
+            Level::Info => 30,
+            Level::Debug => 20,
+            Level::Trace => 10,
+        })))
+        .with_level_case(LevelCase::Lower)
+        .build();
+    assert_eq!(formatter.format_to_string(&rec(Level::Warning)).unwrap(), "|wrn(40)| m");
+
+    let formatter = self::formatter()
+        .with_theme(Default::default())
+        .with_level_severities(Some(LevelSeverities::new(enum_map::enum_map! {
+            Level::Error => 3,
+            Level::Warning => 4,
+            Level::Info => 100,
+            Level::Debug => 7,
+            Level::Trace => 8,
+        })))
+        .build();
+    assert_eq!(formatter.format_to_string(&rec(Level::Error)).unwrap(), "|ERR(3)  | m");
+    assert_eq!(formatter.format_to_string(&rec(Level::Info)).unwrap(), "|INF(100)| m");
+
+    let formatter = self::formatter().build();
+    assert_eq!(
+        formatter.format_to_string(&rec(Level::Info)).unwrap(),
+        "\x1b[0;36m|INF|\x1b[0m \x1b[0;1mm\x1b[0m"
+    );
+}
+
+#[test]
+fn test_level_severities_aligned() {
+    let rec = Record {
+        level: Some(Level::Info),
+        message: Some(EncodedString::raw("m").into()),
+        fields: RecordFields::from_slice(&[("a", RawValue::Number("1"))]),
+        ..Default::default()
+    };
+
+    let formatter = formatter()
+        .with_theme(Default::default())
+        .with_level_severities(Some(LevelSeverities::syslog()))
+        .with_expansion(ExpansionMode::Always.into())
+        .build();
+    assert_eq!(
+        formatter.format_to_string(&rec).unwrap(),
+        "|INF(6)| m\n| ~    |   > a=1"
+    );
+
+    let formatter = self::formatter()
+        .with_theme(Default::default())
+        .with_level_severities(Some(LevelSeverities::syslog()))
+        .with_always_show_level(true)
+        .build();
+    let rec = Record { level: None, ..rec };
+    assert_eq!(formatter.format_to_string(&rec).unwrap(), "|###   | m a=1");
+}
+
+#[rstest]
+#[case::plain("hello world", "hello world")]
+#[case::tabs("a\tb\t", "a\\tb\\t")]
+#[case::newlines("line1\nline2\r\n", "line1\\nline2\\r\\n")]
+#[case::backslash("C:\\temp\\n", "C:\\\\temp\\\\n")]
+fn test_tsv_escape(#[case] value: &str, #[case] expected: &str) {
+    let mut buf = Vec::new();
+    tsv_escape(&mut buf, value.as_bytes());
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+}
+
+#[test]
+fn test_key_color_is_stable_across_value_types() {
+    let obj = json_raw_value(r#"{"n":{"k":"v"}}"#);
+    let arr = json_raw_value(r#"[1,"a"]"#);
+    let rec = Record::from_fields(&[
+        ("s", EncodedString::raw("text").into()),
+        ("i", RawValue::Number("42")),
+        ("b", RawValue::Boolean(true)),
+        ("z", RawValue::Null),
+        ("o", RawValue::from(RawObject::Json(&obj))),
+        ("a", RawValue::from(RawArray::Json(&arr))),
+    ]);
+
+    let mut cfg = crate::themecfg::testing::theme().unwrap();
+    cfg.elements.insert(
+        Element::Key,
+        crate::themecfg::Style::new().foreground(Some(crate::themecfg::Color::Palette(208))),
+    );
+    let formatter = self::formatter().with_theme(Arc::new(Theme::from(&cfg))).build();
+    let result = formatter.format_to_string(&rec).unwrap();
+
+    let key = "\x1b[0;38;5;208m";
+    for k in ["s", "i", "b", "z", "o", "n", "k", "a"] {
+        assert!(
+            result.contains(&format!("{key}{k}\x1b[0;2m=")),
+            "key {k:?} in {result:?}"
+        );
+    }
+    assert_eq!(result.matches(key).count(), 8, "{result:?}");
+}
+
+#[test]
+fn test_error_fields() {
+    let format = |formatter: &RecordFormatter, input: &str| {
+        let raw = RawRecord::parser().parse(input.as_bytes()).next().unwrap().unwrap();
+        let rec = Parser::new(ParserSettings::default()).parse(&raw.record);
+        formatter.format_to_string(&rec).unwrap()
+    };
+    let plain = self::formatter().with_theme(Default::default());
+
+    let input = r#"{"msg":"m","err":"connection refused","x":1}"#;
+    let formatter = plain.clone().with_error_fields(Some(ErrorFields::default())).build();
+    assert_eq!(format(&formatter, input), "m err=\"connection refused\" x=1");
+    let formatter = self::formatter()
+        .with_error_fields(Some(ErrorFields::default()))
+        .build();
+    assert_eq!(
+        format(&formatter, input),
+        "\x1b[0;1mm \x1b[0;32merr\x1b[0;2m=\x1b[0;91m\"connection refused\" \x1b[0;32mx\x1b[0;2m=\x1b[0;94m1\x1b[0m"
+    );
+
+    let input = r#"{"msg":"m","error":{"message":"failed","code":42,"stack":"at main\nat start"}}"#;
+    let formatter = plain.clone().build();
+    assert_eq!(
+        format(&formatter, input),
+        "m\n  > error:\n    > message=failed\n    > code=42\n    > stack=|=>\n       \tat main\n       \tat start"
+    );
+    let formatter = plain.clone().with_error_fields(Some(ErrorFields::default())).build();
+    assert_eq!(format(&formatter, input), "m error=failed error.code=42 ...");
+    let formatter = plain
+        .with_error_fields(Some(ErrorFields::default().with_stack(true)))
+        .build();
+    assert_eq!(
+        format(&formatter, input),
+        "m\n  > error=failed\n  > error.code=42\n  > error.stack=|=>\n     \tat main\n     \tat start"
+    );
+}
+
+#[test]
+fn test_dedup_message_field() {
+    let format = |formatter: &RecordFormatter, input: &str| {
+        let raw = RawRecord::parser().parse(input.as_bytes()).next().unwrap().unwrap();
+        let rec = Parser::new(ParserSettings::default()).parse(&raw.record);
+        formatter.format_to_string(&rec).unwrap()
+    };
+    let plain = self::formatter().with_theme(Default::default());
+    let input = r#"{"msg":"user logged in","event":"user logged in","alt":"user logged in","n":1,"other":"x"}"#;
+
+    let formatter = plain.clone().build();
+    assert_eq!(
+        format(&formatter, input),
+        r#"user logged in event="user logged in" alt="user logged in" n=1 other=x"#
+    );
+
+    let formatter = plain.with_dedup_message_field(true).build();
+    assert_eq!(format(&formatter, input), "user logged in n=1 other=x");
+    assert_eq!(
+        format(&formatter, r#"{"msg":"done","event":"Done","status":"done "}"#),
+        "done event=Done status=done"
+    );
+}
+
+#[test]
+fn test_time_shift() {
+    let rec = |ts| Record {
+        ts: Some(Timestamp::new(ts)),
+        message: Some(EncodedString::raw("m").into()),
+        ..Default::default()
+    };
+    let plain = self::formatter().with_theme(Default::default());
+
+    let formatter = plain.clone().with_time_shift(chrono::Duration::hours(2)).build();
+    assert_eq!(
+        formatter.format_to_string(&rec("2000-01-02T03:04:05.123Z")).unwrap(),
+        "00-01-02 05:04:05.123 m"
+    );
+    assert_eq!(
+        formatter.format_to_string(&rec("2000-01-02T23:30:00Z")).unwrap(),
+        "00-01-03 01:30:00.000 m"
+    );
+    assert_eq!(
+        formatter.format_to_string(&rec("946782245")).unwrap(),
+        "00-01-02 05:04:05.000 m"
+    );
+
+    let formatter = plain.clone().with_time_shift(chrono::Duration::minutes(-30)).build();
+    assert_eq!(
+        formatter.format_to_string(&rec("2000-01-02T03:04:05.123Z")).unwrap(),
+        "00-01-02 02:34:05.123 m"
+    );
+    assert_eq!(
+        formatter.format_to_string(&rec("2000-01-01T00:10:00+00:00")).unwrap(),
+        "99-12-31 23:40:00.000 m"
+    );
+
+    let formatter = plain.with_time_shift(chrono::Duration::zero()).build();
+    assert_eq!(
+        formatter.format_to_string(&rec("2000-01-02T03:04:05.123Z")).unwrap(),
+        "00-01-02 03:04:05.123 m"
+    );
+}
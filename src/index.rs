@@ -166,6 +166,7 @@ pub struct IndexerSettings<'a, FS: FileSystem> {
     pub fields: &'a PredefinedFields,
     pub delimiter: Delimiter,
     pub allow_prefix: bool,
+    pub relaxed_json: bool,
     pub unix_ts_unit: Option<UnixTimestampUnit>,
     pub format: Option<InputFormat>,
 }
@@ -185,6 +186,7 @@ impl<'a, FS: FileSystem> IndexerSettings<'a, FS> {
             fields: Default::default(),
             delimiter: Delimiter::default(),
             allow_prefix: false,
+            relaxed_json: false,
             unix_ts_unit: None,
             format: None,
         }
@@ -199,6 +201,7 @@ impl<'a, FS: FileSystem> IndexerSettings<'a, FS> {
             &self.fields,
             &self.delimiter,
             &self.allow_prefix,
+            &self.relaxed_json,
             &self.unix_ts_unit,
             &self.format,
         );
@@ -286,6 +289,7 @@ pub struct Indexer<FS = LocalFileSystem> {
     parser: Parser,
     delimiter: Delimiter,
     allow_prefix: bool,
+    relaxed_json: bool,
     format: Option<InputFormat>,
 }
 
@@ -304,6 +308,7 @@ where
             parser: Parser::new(ParserSettings::new(settings.fields, empty(), settings.unix_ts_unit)),
             delimiter: settings.delimiter,
             allow_prefix: settings.allow_prefix,
+            relaxed_json: settings.relaxed_json,
             format: settings.format,
         }
     }
@@ -516,13 +521,19 @@ where
             let mut ts = None;
             let mut rel = 0;
             if !data.is_empty() {
-                let mut stream = RawRecord::parser()
+                let parser = RawRecord::parser()
                     .allow_prefix(self.allow_prefix)
-                    .format(self.format)
-                    .parse(data);
+                    .relaxed_json(self.relaxed_json)
+                    .format(self.format);
+                let relaxed = parser.relax(data);
+                let mut stream = parser.parse(relaxed.as_deref().unwrap_or(data));
                 while let Some(item) = stream.next() {
                     match item {
-                        Ok(ar) => {
+                        Ok(mut ar) => {
+                            if relaxed.is_some() {
+                                // the relaxed data holds a single record, so it covers the rest of the original data
+                                ar.offsets.end = data.len();
+                            }
                             let rec = self.parser.parse(&ar.record);
                             let mut flags = 0;
                             if let Some(level) = rec.level {
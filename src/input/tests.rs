@@ -3,6 +3,7 @@ use std::io::{self, Cursor, Read};
 use assert_matches::assert_matches;
 use itertools::Itertools;
 use nonzero_ext::nonzero;
+use rstest::rstest;
 
 use super::*;
 use crate::{
@@ -168,6 +169,30 @@ fn test_input_gzip() {
     assert_eq!(buf, b"test\n");
 }
 
+#[rstest]
+#[case::gzip(
+    // gzip -cn lines.jsonl | xxd -p | sed 's/\(..\)/\\x\1/g'
+    b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03\xab\x56\xca\x49\x2d\x4b\xcd\x51\xb2\x52\xca\xcc\x4b\xcb\x57\xd2\x51\xca\x2d\x4e\x07\x72\x12\x95\x6a\xb9\xaa\xe1\x52\xe5\x89\x45\x79\x70\xa9\x24\xa0\x14\x00\xb8\xc9\x48\x70\x36\x00\x00\x00"
+)]
+#[case::zstd(
+    // zstd -qc lines.jsonl | xxd -p | sed 's/\(..\)/\\x\1/g'
+    b"\x28\xb5\x2f\xfd\x24\x36\x65\x01\x00\x34\x02\x7b\x22\x6c\x65\x76\x65\x6c\x22\x3a\x22\x69\x6e\x66\x6f\x22\x2c\x22\x6d\x73\x67\x22\x3a\x22\x61\x22\x7d\x0a\x77\x61\x72\x6e\x62\x22\x7d\x0a\x02\x00\xa0\x90\x7d\x9b\x63\x85\x20\x3d\x9e"
+)]
+fn test_input_compressed_json_lines(#[case] data: &'static [u8]) {
+    for stream in [
+        Stream::Sequential(Box::new(Cursor::new(data))),
+        Stream::RandomAccess(Box::new(Cursor::new(data).with_metadata(None))),
+    ] {
+        let mut stream = stream.verified().decoded();
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        assert_eq!(
+            buf.lines().map(|line| line.unwrap()).collect_vec(),
+            [r#"{"level":"info","msg":"a"}"#, r#"{"level":"warn","msg":"b"}"#]
+        );
+    }
+}
+
 #[test]
 fn test_indexed_input_stdin() {
     let data = br#"{"ts":"2024-10-01T01:02:03Z","level":"info","msg":"some test message"}\n"#;
@@ -54,9 +54,13 @@ pub mod signal;
 // public uses
 pub use app::{App, FieldOptions, Options, SegmentProcessor};
 pub use datefmt::{DateTimeFormatter, LinuxDateFormat};
+pub use eseq::strip_ansi;
 pub use filtering::DefaultNormalizing;
 pub use formatting::RecordFormatter;
-pub use model::{FieldFilterSet, Filter, Level, Parser, ParserSettings, RecordFilter};
+pub use model::{
+    FieldFilterSet, Filter, FilterSet, FilterSetMode, Level, LevelSetFilter, NotFilter, Parser, ParserSettings,
+    RawRecord, RawValue, Record, RecordFilter, SamplingFilter, SamplingMode,
+};
 pub use query::Query;
 pub use scanning::{Delimit, Delimiter, SearchExt};
 pub use settings::Settings;
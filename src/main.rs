@@ -348,6 +348,7 @@ fn run() -> Result<()> {
         raw: opt.raw,
         raw_fields: opt.raw_fields,
         allow_prefix: opt.allow_prefix,
+        relaxed_json: opt.relaxed_json,
         time_format,
         buffer_size,
         max_message_size,
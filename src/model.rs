@@ -8,11 +8,13 @@ use std::{
     marker::PhantomData,
     ops::Range,
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 // third-party imports
 use chrono::{DateTime, Utc};
+use enum_map::EnumMap;
 use enumset::{EnumSet, EnumSetType, enum_set};
 use regex::Regex;
 use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
@@ -30,7 +32,7 @@ use crate::{
     level::{self},
     serdex::StreamDeserializerWithOffsets,
     settings::PredefinedFields,
-    timestamp::Timestamp,
+    timestamp::{FallbackFormats, Timestamp},
     types::FieldKind,
 };
 
@@ -450,6 +452,14 @@ pub trait RecordFilter {
     {
         RecordFilterOr { lhs: self, rhs }
     }
+
+    #[inline]
+    fn not(self) -> NotFilter<Self>
+    where
+        Self: Sized,
+    {
+        NotFilter::new(self)
+    }
 }
 
 impl<T: RecordFilter + ?Sized> RecordFilter for Box<T> {
@@ -521,6 +531,99 @@ impl<L: RecordFilter, R: RecordFilter> RecordFilter for RecordFilterOr<L, R> {
 
 // ---
 
+/// Inverts the decision of the wrapped filter.
+#[derive(Clone, Debug, Default)]
+pub struct NotFilter<F: RecordFilter> {
+    inner: F,
+}
+
+impl<F: RecordFilter> NotFilter<F> {
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<F: RecordFilter> RecordFilter for NotFilter<F> {
+    #[inline]
+    fn apply<'a>(&self, record: &Record<'a>) -> bool {
+        !self.inner.apply(record)
+    }
+}
+
+// ---
+
+/// Sampling mode of [`SamplingFilter`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SamplingMode {
+    /// Keeps the first record and then every n-th record after it.
+    EveryNth(usize),
+    /// Keeps at most `count` records within each `interval` of record timestamps.
+    /// An interval starts at the timestamp of the first record that does not fit into the previous one.
+    MaxPerInterval { count: usize, interval: Duration },
+}
+
+/// Keeps a sample of records passing through it, see [`SamplingMode`].
+///
+/// The filter is stateful, so it must only be used on a sequential stream of records, applied in the order
+/// they are shown. It must not be used as a record filter of the app, which applies filters in parallel
+/// segment workers, as the sample would then depend on thread scheduling.
+/// In [`SamplingMode::MaxPerInterval`] mode, records without a valid timestamp are always kept.
+#[derive(Debug)]
+pub struct SamplingFilter {
+    mode: SamplingMode,
+    state: Mutex<SamplingState>,
+}
+
+impl SamplingFilter {
+    pub fn new(mode: SamplingMode) -> Self {
+        Self {
+            mode,
+            state: Mutex::new(SamplingState::default()),
+        }
+    }
+
+    pub fn mode(&self) -> SamplingMode {
+        self.mode
+    }
+}
+
+impl RecordFilter for SamplingFilter {
+    fn apply<'a>(&self, record: &Record<'a>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match self.mode {
+            SamplingMode::EveryNth(n) => {
+                let keep = state.count % n.max(1) == 0;
+                state.count += 1;
+                keep
+            }
+            SamplingMode::MaxPerInterval { count, interval } => {
+                let Some(ts) = record.ts.as_ref().and_then(|ts| ts.parse()).map(|ts| ts.to_utc()) else {
+                    return true;
+                };
+                let interval = chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::MAX);
+                match state.start {
+                    Some(start) if ts >= start && ts - start < interval => {}
+                    _ => {
+                        state.start = Some(ts);
+                        state.count = 0;
+                    }
+                }
+                let keep = state.count < count;
+                state.count += keep as usize;
+                keep
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SamplingState {
+    count: usize,
+    start: Option<DateTime<Utc>>,
+}
+
+// ---
+
 pub struct RecordFilterNone;
 
 impl RecordFilter for RecordFilterNone {
@@ -532,8 +635,148 @@ impl RecordFilter for RecordFilterNone {
 
 // ---
 
+/// Keeps records whose level is one of the selected levels.
+#[derive(Clone, Debug, Default)]
+pub struct LevelSetFilter {
+    levels: EnumMap<Level, bool>,
+    keep_unknown: bool,
+}
+
+impl LevelSetFilter {
+    pub fn new<I: IntoIterator<Item = Level>>(levels: I) -> Self {
+        let mut result = Self::default();
+        for level in levels {
+            result.levels[level] = true;
+        }
+        result
+    }
+
+    /// Sets whether records with a missing or unrecognized level are kept, they are dropped by default.
+    pub fn with_unknown(self, keep_unknown: bool) -> Self {
+        Self { keep_unknown, ..self }
+    }
+}
+
+impl RecordFilter for LevelSetFilter {
+    #[inline]
+    fn apply<'a>(&self, record: &Record<'a>) -> bool {
+        record.level.map_or(self.keep_unknown, |level| self.levels[level])
+    }
+}
+
+// ---
+
+/// Mode of combining active filters of a [`FilterSet`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FilterSetMode {
+    /// Keeps records matching any of the active filters.
+    #[default]
+    Any,
+    /// Keeps records matching all of the active filters.
+    All,
+}
+
+/// Named filters, e.g. saved filter profiles, combined according to [`FilterSetMode`].
+///
+/// All added filters are active until a subset is selected with [`FilterSet::select`].
+/// If no filters are active, all records are kept.
+#[derive(Clone, Default)]
+pub struct FilterSet {
+    mode: FilterSetMode,
+    filters: Vec<(String, Arc<dyn RecordFilter + Send + Sync>)>,
+    active: Vec<bool>,
+}
+
+impl FilterSet {
+    pub fn new(mode: FilterSetMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// Adds an active filter with the given name, replacing an existing one with the same name.
+    pub fn with_filter<F>(mut self, name: impl Into<String>, filter: F) -> Self
+    where
+        F: RecordFilter + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let filter = Arc::new(filter);
+        if let Some(i) = self.position(&name) {
+            self.filters[i].1 = filter;
+            self.active[i] = true;
+        } else {
+            self.filters.push((name, filter));
+            self.active.push(true);
+        }
+        self
+    }
+
+    pub fn mode(&self) -> FilterSetMode {
+        self.mode
+    }
+
+    /// Returns names of all filters in the order they were added.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.filters.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns names of the active filters.
+    pub fn active(&self) -> impl Iterator<Item = &str> {
+        self.names()
+            .zip(self.active.iter())
+            .filter(|(_, a)| **a)
+            .map(|(name, _)| name)
+    }
+
+    /// Makes only the named filters active.
+    ///
+    /// Fails without changing the active set if any of the names is unknown.
+    pub fn select<I, S>(&mut self, names: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut active = vec![false; self.filters.len()];
+        for name in names {
+            let name = name.as_ref();
+            let i = self.position(name).ok_or_else(|| Error::UnknownFilter(name.into()))?;
+            active[i] = true;
+        }
+        self.active = active;
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.filters.iter().position(|(n, _)| n == name)
+    }
+}
+
+impl RecordFilter for FilterSet {
+    fn apply<'a>(&self, record: &Record<'a>) -> bool {
+        let mut active = self
+            .filters
+            .iter()
+            .zip(self.active.iter())
+            .filter(|(_, a)| **a)
+            .peekable();
+        if active.peek().is_none() {
+            return true;
+        }
+
+        match self.mode {
+            FilterSetMode::Any => active.any(|((_, filter), _)| filter.apply(record)),
+            FilterSetMode::All => active.all(|((_, filter), _)| filter.apply(record)),
+        }
+    }
+}
+
+// ---
+
 pub struct ParserSettings {
     unix_ts_unit: Option<UnixTimestampUnit>,
+    ts_fallback: Option<FallbackFormats>,
+    custom_key_prefix: Option<&'static str>,
     level: Vec<(HashMap<String, Level>, Option<Level>)>,
     blocks: Vec<ParserSettingsBlock>,
     ignore: Vec<Pattern>,
@@ -547,15 +790,45 @@ impl ParserSettings {
     ) -> Self {
         let mut result = Self {
             unix_ts_unit,
+            ts_fallback: None,
+            custom_key_prefix: predefined.preset.and_then(|preset| preset.custom_key_prefix()),
             level: Vec::new(),
             blocks: vec![ParserSettingsBlock::default()],
             ignore: ignore.into_iter().map(Pattern::new).collect(),
         };
 
-        result.init(predefined);
+        result.init(&predefined.with_preset_applied());
         result
     }
 
+    /// Sets formats to try for timestamps that are neither RFC 3339 nor unix timestamps.
+    pub fn with_timestamp_fallback(self, fallback: Option<FallbackFormats>) -> Self {
+        Self {
+            ts_fallback: fallback,
+            ..self
+        }
+    }
+
+    /// Adds mapping of arbitrary values of level fields to levels, e.g. `SEVERE` to [`Level::Error`],
+    /// applied to all level field variants in addition to their own values.
+    /// Values are matched the same way as the configured ones, in their original, lower, upper and title case.
+    pub fn with_level_values<I, V>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = (V, Level)>,
+        V: AsRef<str>,
+    {
+        for (value, level) in values {
+            let value = value.as_ref();
+            for (mapping, _) in &mut self.level {
+                mapping.insert(value.to_owned(), level);
+                mapping.insert(value.to_lowercase(), level);
+                mapping.insert(value.to_uppercase(), level);
+                mapping.insert(titlecase(value), level);
+            }
+        }
+        self
+    }
+
     fn init(&mut self, pf: &PredefinedFields) {
         self.build_block(0, &pf.time.names, FieldSettings::Time, 0);
         self.build_block(0, &pf.message.names, FieldSettings::Message, 0);
@@ -693,6 +966,10 @@ impl ParserSettingsBlock {
                 return false;
             }
         }
+        let key = match ps.custom_key_prefix {
+            Some(prefix) => key.strip_prefix(prefix).filter(|key| !key.is_empty()).unwrap_or(key),
+            None => key,
+        };
         to.fields.push((key, value));
         false
     }
@@ -779,7 +1056,10 @@ impl FieldSettings {
                     s
                 };
                 if !s.is_empty() {
-                    let ts = Timestamp::new(s).with_unix_unit(ps.unix_ts_unit);
+                    let mut ts = Timestamp::new(s).with_unix_unit(ps.unix_ts_unit);
+                    if let Some(fallback) = &ps.ts_fallback {
+                        ts = ts.with_fallback(fallback);
+                    }
                     to.ts = Some(ts);
                     true
                 } else {
@@ -947,6 +1227,7 @@ type ObjectFields<'a, const N: usize> = heapopt::Vec<(&'a str, RawValue<'a>), N>
 pub struct RawRecordParser {
     allow_prefix: bool,
     format: Option<InputFormat>,
+    relaxed_json: bool,
 }
 
 impl Default for RawRecordParser {
@@ -961,6 +1242,7 @@ impl RawRecordParser {
         Self {
             allow_prefix: false,
             format: None,
+            relaxed_json: false,
         }
     }
 
@@ -977,24 +1259,50 @@ impl RawRecordParser {
         Self { format, ..self }
     }
 
+    /// Enables [`relax`](Self::relax) to accept relaxed, JSON5-like input
+    /// with single-quoted strings, unquoted keys, trailing commas and comments.
+    #[inline]
+    pub fn relaxed_json(self, value: bool) -> Self {
+        Self {
+            relaxed_json: value,
+            ..self
+        }
+    }
+
+    /// Converts `chunk` to strict JSON if relaxed JSON is enabled and `chunk` is a relaxed JSON input,
+    /// i.e. it fails to parse as strict JSON but succeeds after the conversion.
+    /// The resulting chunk can be passed to [`parse`](Self::parse) in place of the original one.
+    /// It contains a single record with the same prefix, so that the record corresponds to the rest of the original chunk.
+    pub fn relax(&self, chunk: &[u8]) -> Option<Vec<u8>> {
+        if !self.relaxed_json || self.format == Some(InputFormat::Logfmt) {
+            return None;
+        }
+
+        let (prefix, data) = self.split_prefix(chunk);
+        if data.first() != Some(&b'{') || Self::valid_json(data) {
+            return None;
+        }
+
+        let data = relaxed::to_strict(data)?;
+        if !Self::single_json(&data) {
+            return None;
+        }
+
+        Some([prefix, &data].concat())
+    }
+
     #[inline]
     pub fn parse<'a>(
         &self,
         chunk: &'a [u8],
     ) -> RawRecordStream<impl RawRecordIterator<'a> + use<'a>, impl RawRecordIterator<'a> + use<'a>> {
-        let prefix = if self.allow_prefix && chunk.last() == Some(&b'}') {
-            memchr::memchr(b'{', chunk).map_or(chunk, |pos| &chunk[..pos])
-        } else {
-            b""
-        };
-
+        let (prefix, data) = self.split_prefix(chunk);
         let xn = prefix.len();
-        let data = &chunk[xn..];
 
         let format = self.format.or_else(|| {
             if data.is_empty() {
                 None
-            } else if data[0] == b'{' {
+            } else if matches!(data[0], b'{' | b'[') {
                 Some(InputFormat::Json)
             } else {
                 Some(InputFormat::Logfmt)
@@ -1003,14 +1311,17 @@ impl RawRecordParser {
 
         match format {
             None => RawRecordStream::Empty,
-            Some(InputFormat::Json) => RawRecordStream::Json(RawRecordJsonStream {
+            Some(InputFormat::Json) if data.first() == Some(&b'[') => RawRecordStream::Json(RawRecordJsonInput::Array(
+                RawRecordJsonArrayStream::new(prefix, xn, data),
+            )),
+            Some(InputFormat::Json) => RawRecordStream::Json(RawRecordJsonInput::Objects(RawRecordJsonStream {
                 prefix,
                 xn,
                 delegate: StreamDeserializerWithOffsets {
                     inner: json::Deserializer::from_slice(data).into_iter::<RawRecord>(),
                     source: data,
                 },
-            }),
+            })),
             Some(InputFormat::Logfmt) => RawRecordStream::Logfmt(RawRecordLogfmtStream {
                 chunk,
                 prefix,
@@ -1018,6 +1329,27 @@ impl RawRecordParser {
             }),
         }
     }
+
+    #[inline]
+    fn split_prefix<'a>(&self, chunk: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+        let prefix = if self.allow_prefix && chunk.last() == Some(&b'}') {
+            memchr::memchr(b'{', chunk).map_or(chunk, |pos| &chunk[..pos])
+        } else {
+            b""
+        };
+        chunk.split_at(prefix.len())
+    }
+
+    fn single_json(data: &[u8]) -> bool {
+        let mut items = json::Deserializer::from_slice(data).into_iter::<serde::de::IgnoredAny>();
+        matches!((items.next(), items.next()), (Some(Ok(_)), None))
+    }
+
+    fn valid_json(data: &[u8]) -> bool {
+        json::Deserializer::from_slice(data)
+            .into_iter::<serde::de::IgnoredAny>()
+            .all(|item| item.is_ok())
+    }
 }
 
 // ---
@@ -1097,6 +1429,127 @@ where
 
 // ---
 
+enum RawRecordJsonInput<'a, R> {
+    Objects(RawRecordJsonStream<'a, R>),
+    Array(RawRecordJsonArrayStream<'a>),
+}
+
+impl<'a, R> RawRecordIterator<'a> for RawRecordJsonInput<'a, R>
+where
+    R: json::de::Read<'a>,
+{
+    #[inline]
+    fn next(&mut self) -> Option<Result<AnnotatedRawRecord<'a>>> {
+        match self {
+            Self::Objects(stream) => stream.next(),
+            Self::Array(stream) => stream.next(),
+        }
+    }
+}
+
+// ---
+
+/// Maximum number of records in a top-level JSON array.
+/// Arrays with more elements are rejected without parsing the elements beyond the limit.
+pub const MAX_RECORDS_IN_ARRAY: usize = 4096;
+
+/// Yields elements of a top-level JSON array as separate records.
+///
+/// The range of the last record is extended to the closing bracket of the array,
+/// so that the array is entirely covered by the records.
+struct RawRecordJsonArrayStream<'a> {
+    prefix: &'a [u8],
+    xn: usize,
+    data: &'a [u8],
+    items: std::vec::IntoIter<&'a json::value::RawValue>,
+    error: Option<json::Error>,
+}
+
+impl<'a> RawRecordJsonArrayStream<'a> {
+    fn new(prefix: &'a [u8], xn: usize, data: &'a [u8]) -> Self {
+        let mut items = Vec::new();
+        let mut deserializer = json::Deserializer::from_slice(data);
+        let error = deserializer
+            .deserialize_seq(RecordArrayVisitor::new(&mut items))
+            .and_then(|_| deserializer.end())
+            .err();
+        if error.is_some() {
+            items.clear();
+        }
+        Self {
+            prefix,
+            xn,
+            data,
+            items: items.into_iter(),
+            error,
+        }
+    }
+}
+
+/// Collects elements of a top-level JSON array, failing as soon as there are more than [`MAX_RECORDS_IN_ARRAY`] of them.
+struct RecordArrayVisitor<'a, 't> {
+    target: &'t mut Vec<&'a json::value::RawValue>,
+}
+
+impl<'a, 't> RecordArrayVisitor<'a, 't> {
+    #[inline]
+    fn new(target: &'t mut Vec<&'a json::value::RawValue>) -> Self {
+        Self { target }
+    }
+}
+
+impl<'a, 't> Visitor<'a> for RecordArrayVisitor<'a, 't> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "json array of at most {MAX_RECORDS_IN_ARRAY} records")
+    }
+
+    #[inline]
+    fn visit_seq<A: SeqAccess<'a>>(self, mut access: A) -> std::result::Result<Self::Value, A::Error> {
+        while let Some(item) = access.next_element()? {
+            if self.target.len() == MAX_RECORDS_IN_ARRAY {
+                return Err(serde::de::Error::custom("too many records in array"));
+            }
+            self.target.push(item);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> RawRecordIterator<'a> for RawRecordJsonArrayStream<'a> {
+    #[inline]
+    fn next(&mut self) -> Option<Result<AnnotatedRawRecord<'a>>> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(Error::JsonParseError(err)));
+        }
+
+        let item = self.items.next()?;
+        let start = item.get().as_ptr() as usize - self.data.as_ptr() as usize;
+        let end = if self.items.len() == 0 {
+            self.data
+                .iter()
+                .rposition(|&b| b == b']')
+                .map_or(self.data.len(), |i| i + 1)
+        } else {
+            start + item.get().len()
+        };
+
+        Some(
+            json::from_str::<RawRecord>(item.get())
+                .map(|record| AnnotatedRawRecord {
+                    prefix: self.prefix,
+                    record,
+                    offsets: start + self.xn..end + self.xn,
+                })
+                .map_err(Error::JsonParseError),
+        )
+    }
+}
+
+// ---
+
 struct RawRecordLogfmtStream<'a> {
     chunk: &'a [u8],
     prefix: &'a [u8],
@@ -1968,5 +2421,7 @@ const RAW_RECORD_FIELDS_CAPACITY: usize = RECORD_EXTRA_CAPACITY + MAX_PREDEFINED
 
 // ---
 
+mod relaxed;
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,133 @@
+//! Conversion of relaxed, JSON5-like input to strict JSON.
+//!
+//! Supported relaxations are single-quoted strings, unquoted object keys,
+//! trailing commas in objects and arrays, and line or block comments.
+
+/// Converts relaxed JSON to strict JSON.
+///
+/// Returns `None` if the input contains an unterminated string or comment.
+/// The result is not validated, other syntax errors are preserved as is.
+pub fn to_strict(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() + 16);
+    let mut i = 0;
+
+    while i < data.len() {
+        match data[i] {
+            b'"' => {
+                let end = string_end(data, i, b'"')?;
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+            }
+            b'\'' => {
+                let end = string_end(data, i, b'\'')?;
+                requote(&mut out, &data[i + 1..end - 1]);
+                i = end;
+            }
+            b'/' if data.get(i + 1) == Some(&b'/') => {
+                i = data[i..].iter().position(|&c| c == b'\n').map_or(data.len(), |n| i + n);
+            }
+            b'/' if data.get(i + 1) == Some(&b'*') => {
+                i = data[i + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map(|n| i + 2 + n + 2)?;
+            }
+            b',' => {
+                let next = skip_insignificant(data, i + 1)?;
+                if !matches!(data.get(next), Some(b'}' | b']')) {
+                    out.push(b',');
+                }
+                i += 1;
+            }
+            c if c.is_ascii_digit() || c == b'-' || c == b'+' || c == b'.' => {
+                let n = data[i + 1..]
+                    .iter()
+                    .position(|&c| !(c.is_ascii_alphanumeric() || matches!(c, b'.' | b'+' | b'-')))
+                    .unwrap_or(data.len() - i - 1);
+                out.extend_from_slice(&data[i..i + 1 + n]);
+                i += 1 + n;
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' || c == b'$' => {
+                let n = data[i..]
+                    .iter()
+                    .position(|&c| !(c.is_ascii_alphanumeric() || c == b'_' || c == b'$'))
+                    .unwrap_or(data.len() - i);
+                let word = &data[i..i + n];
+                if matches!(word, b"true" | b"false" | b"null") {
+                    out.extend_from_slice(word);
+                } else {
+                    out.push(b'"');
+                    out.extend_from_slice(word);
+                    out.push(b'"');
+                }
+                i += n;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    Some(out)
+}
+
+/// Returns the position right after the closing quote of the string starting at `start`.
+fn string_end(data: &[u8], start: usize, quote: u8) -> Option<usize> {
+    let mut i = start + 1;
+    while i < data.len() {
+        match data[i] {
+            b'\\' => i += 2,
+            c if c == quote => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Returns the position of the first character after `start` that is not a whitespace or a part of a comment.
+fn skip_insignificant(data: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    loop {
+        match data.get(i) {
+            Some(c) if c.is_ascii_whitespace() => i += 1,
+            Some(b'/') if data.get(i + 1) == Some(&b'/') => {
+                i = data[i..].iter().position(|&c| c == b'\n').map_or(data.len(), |n| i + n);
+            }
+            Some(b'/') if data.get(i + 1) == Some(&b'*') => {
+                i = data[i + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map(|n| i + 2 + n + 2)?;
+            }
+            _ => return Some(i),
+        }
+    }
+}
+
+/// Appends contents of a single-quoted string as a double-quoted string.
+fn requote(out: &mut Vec<u8>, content: &[u8]) {
+    out.push(b'"');
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            b'\\' if content.get(i + 1) == Some(&b'\'') => {
+                out.push(b'\'');
+                i += 2;
+            }
+            b'\\' => {
+                out.extend_from_slice(&content[i..(i + 2).min(content.len())]);
+                i += 2;
+            }
+            b'"' => {
+                out.extend_from_slice(b"\\\"");
+                i += 1;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out.push(b'"');
+}
@@ -5,7 +5,7 @@ use maplit::hashmap;
 use rstest::rstest;
 use serde_logfmt::logfmt;
 
-use crate::settings::{Field, FieldShowOption};
+use crate::settings::{Field, FieldShowOption, SchemaPreset};
 
 #[test]
 fn test_raw_record_parser_empty_line() {
@@ -60,6 +60,85 @@ fn test_raw_record_parser_default() {
     assert!(is_json_stream(&stream2));
 }
 
+#[test]
+fn test_raw_record_parser_relaxed_json() {
+    let input = br#"{msg: 'it\'s "quoted"', 'a': [1, 2.5e3,], /* note */ b: {c: true,},}"#;
+
+    let parser = RawRecordParser::new();
+    assert!(parser.relax(input).is_none());
+    assert!(matches!(parser.parse(input).next(), Some(Err(_))));
+
+    let parser = RawRecordParser::new().relaxed_json(true);
+    let relaxed = parser.relax(input).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&relaxed).unwrap(),
+        r#"{"msg": "it's \"quoted\"", "a": [1, 2.5e3],  "b": {"c": true}}"#
+    );
+
+    let rec = parser.parse(&relaxed).next().unwrap().unwrap();
+    let fields = rec.record.fields().collect::<Vec<_>>();
+    assert_eq!(fields.len(), 3);
+    assert_eq!(fields[0].0, "msg");
+    assert_eq!(fields[0].1.raw_str(), r#""it's \"quoted\"""#);
+    assert_eq!(fields[1].1.raw_str(), "[1, 2.5e3]");
+}
+
+#[rstest]
+#[case::strict(br#"{"a":1}"#)]
+#[case::not_json(b"a=1 b='x',")]
+#[case::unterminated(br#"{a: 'x}"#)]
+#[case::invalid(br#"{a: 1 2}"#)]
+#[case::multiple(br#"{a: 1} {b: 2,}"#)]
+fn test_raw_record_parser_relaxed_json_skipped(#[case] input: &[u8]) {
+    let parser = RawRecordParser::new().relaxed_json(true);
+    assert!(parser.relax(input).is_none());
+}
+
+#[test]
+fn test_raw_record_parser_relaxed_json_with_prefix() {
+    let parser = RawRecordParser::new().allow_prefix(true).relaxed_json(true);
+    let relaxed = parser.relax(b"prefix {a: 'x',}").unwrap();
+    assert_eq!(relaxed, br#"prefix {"a": "x"}"#);
+
+    let rec = parser.parse(&relaxed).next().unwrap().unwrap();
+    assert_eq!(rec.prefix, b"prefix ");
+}
+
+#[test]
+fn test_raw_record_parser_array() {
+    let input = br#"[{"a":1}, {"b":2} ,{"c":3} ]"#;
+    let records = RawRecordParser::new().parse(input).collect_vec();
+    assert_eq!(records.len(), 3);
+
+    let records = records.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>();
+    let keys = records
+        .iter()
+        .map(|r| r.record.fields().next().unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(keys, ["a", "b", "c"]);
+
+    let sources = records.iter().map(|r| &input[r.offsets.clone()]).collect::<Vec<_>>();
+    assert_eq!(sources, [&br#"{"a":1}"#[..], br#"{"b":2}"#, br#"{"c":3} ]"#]);
+}
+
+#[test]
+fn test_raw_record_parser_array_invalid() {
+    let mut stream = RawRecordParser::new().parse(br#"[{"a":1}, 2]"#);
+    assert!(stream.next().unwrap().is_ok());
+    assert!(matches!(stream.next(), Some(Err(Error::JsonParseError(_)))));
+
+    let input = format!("[{}{{}}]", "{},".repeat(MAX_RECORDS_IN_ARRAY));
+    let mut stream = RawRecordParser::new().parse(input.as_bytes());
+    assert!(matches!(stream.next(), Some(Err(Error::JsonParseError(_)))));
+    assert!(stream.next().is_none());
+
+    let input = format!("[{}{{}}, broken]", "{},".repeat(MAX_RECORDS_IN_ARRAY));
+    let mut stream = RawRecordParser::new().parse(input.as_bytes());
+    assert!(
+        matches!(stream.next(), Some(Err(Error::JsonParseError(err))) if err.to_string().contains("too many records"))
+    );
+}
+
 #[test]
 fn test_raw_value_auto() {
     let value = RawValue::auto("123");
@@ -350,6 +429,115 @@ fn test_record_filter_empty() {
     assert!(filter.apply(&record));
 }
 
+#[test]
+fn test_level_set_filter() {
+    let input = [
+        r#"{"level":"error","i":1}"#,
+        r#"{"level":"warning","i":2}"#,
+        r#"{"level":"info","i":3}"#,
+        r#"{"level":"debug","i":4}"#,
+        r#"{"level":"trace","i":5}"#,
+        r#"{"level":"error","i":6}"#,
+        r#"{"level":"unknown","i":7}"#,
+        r#"{"i":8}"#,
+    ];
+
+    let filter = LevelSetFilter::new([Level::Error, Level::Debug]);
+    assert_eq!(kept(&input, &filter), vec!["1", "4", "6"]);
+
+    let filter = filter.with_unknown(true);
+    assert_eq!(kept(&input, &filter), vec!["1", "4", "6", "7", "8"]);
+
+    let filter = LevelSetFilter::new([]);
+    assert_eq!(kept(&input, &filter), Vec::<&str>::new());
+}
+
+#[test]
+fn test_filter_set() {
+    let input = [
+        r#"{"level":"error","i":1}"#,
+        r#"{"level":"info","i":2,"user":"alice"}"#,
+        r#"{"level":"info","i":3,"user":"bob"}"#,
+        r#"{"level":"error","i":4,"user":"alice"}"#,
+    ];
+    let profiles = |mode| {
+        FilterSet::new(mode)
+            .with_filter("errors", Level::Error)
+            .with_filter("alice", FieldFilter::parse("user=alice").unwrap())
+    };
+
+    let mut filter = profiles(FilterSetMode::Any);
+    assert_eq!(filter.active().collect::<Vec<_>>(), vec!["errors", "alice"]);
+    assert_eq!(kept(&input, &filter), vec!["1", "2", "4"]);
+
+    filter.select(["alice"]).unwrap();
+    assert_eq!(kept(&input, &filter), vec!["2", "4"]);
+
+    assert!(matches!(filter.select(["alice", "slow"]), Err(Error::UnknownFilter(name)) if name == "slow"));
+    assert_eq!(filter.active().collect::<Vec<_>>(), vec!["alice"]);
+
+    filter.select(Vec::<&str>::new()).unwrap();
+    assert_eq!(kept(&input, &filter), vec!["1", "2", "3", "4"]);
+
+    let filter = profiles(FilterSetMode::All);
+    assert_eq!(filter.mode(), FilterSetMode::All);
+    assert_eq!(kept(&input, &filter), vec!["4"]);
+}
+
+#[test]
+fn test_not_filter() {
+    let input = [
+        r#"{"level":"error","i":1}"#,
+        r#"{"level":"warning","i":2}"#,
+        r#"{"level":"info","i":3}"#,
+        r#"{"level":"debug","i":4}"#,
+        r#"{"i":5}"#,
+    ];
+
+    assert_eq!(kept(&input, &Level::Warning), vec!["1", "2"]);
+    assert_eq!(kept(&input, &NotFilter::new(Level::Warning)), vec!["3", "4", "5"]);
+    assert_eq!(kept(&input, &Level::Warning.not().not()), vec!["1", "2"]);
+
+    let filter = FilterSet::new(FilterSetMode::All)
+        .with_filter("not-errors", Level::Error.not())
+        .with_filter(
+            "not-debug",
+            LevelSetFilter::new([Level::Debug]).with_unknown(true).not(),
+        );
+    assert_eq!(kept(&input, &filter), vec!["2", "3"]);
+}
+
+#[test]
+fn test_sampling_filter_every_nth() {
+    let filter = SamplingFilter::new(SamplingMode::EveryNth(3));
+    let input = (0..10).map(|i| format!(r#"{{"i":{i}}}"#)).collect::<Vec<_>>();
+    let input = input.iter().map(String::as_str).collect::<Vec<_>>();
+    assert_eq!(kept(&input, &filter), vec!["0", "3", "6", "9"]);
+
+    let filter = SamplingFilter::new(SamplingMode::EveryNth(0));
+    assert!((0..3).all(|_| filter.apply(&parse(r#"{"i":0}"#))));
+}
+
+#[test]
+fn test_sampling_filter_max_per_interval() {
+    let input = [
+        r#"{"ts":"2020-01-01T00:00:00.000Z","i":1}"#,
+        r#"{"ts":"2020-01-01T00:00:00.300Z","i":2}"#,
+        r#"{"ts":"2020-01-01T00:00:00.600Z","i":3}"#,
+        r#"{"ts":"2020-01-01T00:00:00.900Z","i":4}"#,
+        r#"{"i":5}"#,
+        r#"{"ts":"2020-01-01T00:00:01.000Z","i":6}"#,
+        r#"{"ts":"2020-01-01T00:00:01.100Z","i":7}"#,
+        r#"{"ts":"2020-01-01T00:00:01.200Z","i":8}"#,
+        r#"{"ts":"2020-01-01T00:00:05.000Z","i":9}"#,
+    ];
+    let filter = SamplingFilter::new(SamplingMode::MaxPerInterval {
+        count: 2,
+        interval: Duration::from_secs(1),
+    });
+    assert_eq!(kept(&input, &filter), vec!["1", "2", "5", "6", "7", "9"]);
+}
+
 #[test]
 fn test_record_filter_level() {
     let filter = Filter {
@@ -669,6 +857,21 @@ fn test_level(#[case] input: &[u8], #[case] expected: Option<Level>) {
     assert_eq!(record.level, expected);
 }
 
+#[rstest]
+#[case(br#"{"level":"SEVERE"}"#, Some(Level::Error))]
+#[case(br#"{"level":"severe"}"#, Some(Level::Error))]
+#[case(br#"{"level":"FINE"}"#, Some(Level::Debug))]
+#[case(br#"level=Fine"#, Some(Level::Debug))]
+#[case(br#"{"level":"info"}"#, Some(Level::Info))]
+#[case(br#"{"level":"FINEST"}"#, None)]
+fn test_level_values(#[case] input: &[u8], #[case] expected: Option<Level>) {
+    let settings = ParserSettings::default().with_level_values([("SEVERE", Level::Error), ("FINE", Level::Debug)]);
+    let parser = Parser::new(settings);
+    let record = RawRecord::parser().parse(input).next().unwrap().unwrap();
+    let record = parser.parse(&record.record);
+    assert_eq!(record.level, expected);
+}
+
 #[rstest]
 #[case(br#"{"logger":""}"#, None)]
 #[case(br#"{"logger":"x"}"#, Some("x"))]
@@ -768,6 +971,30 @@ fn test_logfmt_string(#[case] input: &[u8], #[case] expected: RawValue) {
     assert_eq!(actual, expected);
 }
 
+#[test]
+fn test_logfmt_record() {
+    let record = parse(r#"ts=2020-01-01T00:00:00Z level=info msg="hello world" k=1 v=some-value"#);
+
+    assert_eq!(record.ts.as_ref().map(|x| x.raw()), Some("2020-01-01T00:00:00Z"));
+    assert_eq!(record.level, Some(Level::Info));
+    assert_eq!(
+        record.message,
+        Some(RawValue::String(EncodedString::json(r#""hello world""#)))
+    );
+
+    let fields: Vec<_> = record
+        .fields()
+        .map(|(k, v)| (*k, RawValue::auto(v.raw_str())))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("k", RawValue::Number("1")),
+            ("v", RawValue::String(EncodedString::raw("some-value"))),
+        ]
+    );
+}
+
 #[rstest]
 #[case(r#"msg=Synced"#, "msg=Synced", true)] // 1
 #[case(r#"msg="Synced""#, "msg=Synced", true)] // 2
@@ -797,6 +1024,16 @@ fn test_logfmt_filter_include_absent(#[case] filter: &str, #[case] input: &str,
     assert_eq!(filter.apply(&record), expected);
 }
 
+/// Returns values of the `i` field of the records from `input` kept by the filter.
+fn kept<'a>(input: &[&'a str], filter: &dyn RecordFilter) -> Vec<&'a str> {
+    input
+        .iter()
+        .map(|line| parse(line))
+        .filter(|record| filter.apply(record))
+        .map(|record| record.fields().find(|(k, _)| *k == "i").unwrap().1.raw_str())
+        .collect()
+}
+
 fn parse(s: &str) -> Record<'_> {
     try_parse(s).unwrap()
 }
@@ -938,3 +1175,77 @@ fn test_object_parser_invalid_type() {
     let result = obj.parse();
     assert!(matches!(result, Err(Error::JsonParseError(_))));
 }
+
+#[rstest]
+#[case(br#"{"@timestamp":"2020-01-01T00:00:00Z","log.level":"warn","message":"m","log.logger":"l","a":1}"#)] // 1
+#[case(br#"{"@timestamp":"2020-01-01T00:00:00Z","log":{"level":"WARN","logger":"l"},"message":"m","a":1}"#)] // 2
+#[case(br#"{"@timestamp":"2020-01-01T00:00:00Z","log":{"level":"warning","logger":"l","origin":{"file":{"name":"f.rs","line":7}}},"message":"m","a":1}"#)] // 3
+fn test_ecs_preset(#[case] input: &[u8]) {
+    let predefined = PredefinedFields {
+        preset: Some(SchemaPreset::Ecs),
+        ..Default::default()
+    };
+    let parser = Parser::new(ParserSettings::new(&predefined, [], None));
+    let record = RawRecord::parser().parse(input).next().unwrap().unwrap();
+    let record = parser.parse(&record.record);
+
+    assert_eq!(record.ts.as_ref().unwrap().raw(), "2020-01-01T00:00:00Z");
+    assert_eq!(record.level, Some(Level::Warning));
+    assert_eq!(record.message.map(|x| x.raw_str()), Some(r#""m""#));
+    assert_eq!(record.logger, Some("l"));
+    assert_eq!(record.fields().last().map(|(k, _)| *k), Some("a"));
+}
+
+#[test]
+fn test_ecs_preset_caller() {
+    let predefined = PredefinedFields {
+        preset: Some(SchemaPreset::Ecs),
+        ..Default::default()
+    };
+    let parser = Parser::new(ParserSettings::new(&predefined, [], None));
+    let input = br#"{"log":{"origin":{"file":{"name":"f.rs","line":7}}},"level":"info"}"#;
+    let record = RawRecord::parser().parse(input).next().unwrap().unwrap();
+    let record = parser.parse(&record.record);
+
+    assert_eq!(record.caller, Caller::with_file_line("f.rs", "7"));
+    assert_eq!(record.level, Some(Level::Info));
+}
+
+#[test]
+fn test_gelf_preset() {
+    let predefined = PredefinedFields {
+        preset: Some(SchemaPreset::Gelf),
+        ..Default::default()
+    };
+    let parser = Parser::new(ParserSettings::new(&predefined, [], None));
+    let input = br#"{"version":"1.1","host":"example.org","short_message":"A short message","full_message":"Backtrace here","timestamp":1385053862.3072,"level":4,"_user_id":9001,"_some_info":"foo","_":1}"#;
+    let record = RawRecord::parser().parse(input).next().unwrap().unwrap();
+    let record = parser.parse(&record.record);
+
+    assert_eq!(record.message.map(|x| x.raw_str()), Some(r#""A short message""#));
+    assert_eq!(record.level, Some(Level::Warning));
+    let ts = record.ts.as_ref().unwrap().parse().unwrap();
+    assert_eq!(ts.timestamp(), 1385053862);
+    assert_eq!(ts.timestamp_subsec_millis(), 307);
+    assert_eq!(
+        record.fields().map(|(k, _)| *k).collect::<Vec<_>>(),
+        vec!["version", "host", "full_message", "user_id", "some_info", "_"]
+    );
+}
+
+#[rstest]
+#[case(br#"{"level":3}"#, Some(Level::Error))] // 1
+#[case(br#"{"level":0}"#, Some(Level::Error))] // 2
+#[case(br#"{"level":6}"#, Some(Level::Info))] // 3
+#[case(br#"{"level":7}"#, Some(Level::Debug))] // 4
+#[case(br#"{"level":"info"}"#, Some(Level::Info))] // 5
+fn test_gelf_preset_level(#[case] input: &[u8], #[case] expected: Option<Level>) {
+    let predefined = PredefinedFields {
+        preset: Some(SchemaPreset::Gelf),
+        ..Default::default()
+    };
+    let parser = Parser::new(ParserSettings::new(&predefined, [], None));
+    let record = RawRecord::parser().parse(input).next().unwrap().unwrap();
+    let record = parser.parse(&record.record);
+    assert_eq!(record.level, expected);
+}
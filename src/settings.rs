@@ -158,21 +158,64 @@ pub struct PredefinedFields {
     pub caller: CallerField,
     pub caller_file: CallerFileField,
     pub caller_line: CallerLineField,
+    #[serde(default)]
+    pub preset: Option<SchemaPreset>,
 }
 
 impl PredefinedFields {
+    /// Returns the fields extended with names and level values of the configured schema preset, if any.
+    pub fn with_preset_applied(&self) -> Self {
+        let mut result = self.clone();
+        let Some(preset) = self.preset else {
+            return result;
+        };
+
+        let other = preset.fields();
+        let merge = |dst: &mut Field, src: &Field| {
+            for name in &src.names {
+                if !dst.names.contains(name) {
+                    dst.names.push(name.clone());
+                }
+            }
+        };
+        merge(&mut result.time.0, &other.time.0);
+        merge(&mut result.message.0, &other.message.0);
+        merge(&mut result.logger.0, &other.logger.0);
+        merge(&mut result.caller.0, &other.caller.0);
+        merge(&mut result.caller_file.0, &other.caller_file.0);
+        merge(&mut result.caller_line.0, &other.caller_line.0);
+
+        for variant in other.level.variants {
+            match result.level.variants.iter_mut().find(|v| v.names == variant.names) {
+                Some(existing) => {
+                    for (level, values) in variant.values {
+                        let dst = existing.values.entry(level).or_default();
+                        for value in values {
+                            if !dst.contains(&value) {
+                                dst.push(value);
+                            }
+                        }
+                    }
+                }
+                None => result.level.variants.push(variant),
+            }
+        }
+
+        result
+    }
+
     pub fn nested_field_names(&self) -> impl Iterator<Item = &str> {
-        let fields: [&[String]; 7] = [
+        let fields: [&[String]; 6] = [
             &self.time.0.names,
             &self.message.0.names,
             &self.logger.0.names,
             &self.caller.0.names,
             &self.caller_file.0.names,
             &self.caller_line.0.names,
-            self.level.variants.first().map(|v| v.names.as_slice()).unwrap_or(&[]),
         ];
         fields
             .into_iter()
+            .chain(self.level.variants.iter().map(|v| v.names.as_slice()))
             .flatten()
             .filter(|name| name.contains('.'))
             .map(|s| s.as_str())
@@ -188,6 +231,91 @@ impl Default for &PredefinedFields {
 
 // ---
 
+/// Predefined field conventions of a well-known log schema.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchemaPreset {
+    /// Elastic Common Schema: `@timestamp`, `log.level`, `message`, `log.logger`, `log.origin.file.*`.
+    Ecs,
+    /// Graylog Extended Log Format: `timestamp`, numeric syslog `level`, `short_message`, `_`-prefixed custom fields.
+    Gelf,
+}
+
+impl SchemaPreset {
+    /// Returns the predefined fields introduced by the preset.
+    pub fn fields(&self) -> PredefinedFields {
+        let names = |names: &[&str]| Field::new(names.iter().map(|&name| name.into()).collect());
+        let empty = || Field::new(Vec::new());
+        let levels = |values: &[(Level, &[&str])]| {
+            values
+                .iter()
+                .map(|(level, names)| (InfallibleLevel::new(*level), names.iter().map(|&v| v.into()).collect()))
+                .collect()
+        };
+
+        match self {
+            Self::Ecs => PredefinedFields {
+                time: names(&["@timestamp"]).into(),
+                level: LevelField {
+                    show: FieldShowOption::default(),
+                    variants: vec![RawLevelFieldVariant {
+                        names: vec!["log.level".into()],
+                        values: levels(&[
+                            (
+                                Level::Error,
+                                &["error", "err", "fatal", "critical", "emergency", "alert"],
+                            ),
+                            (Level::Warning, &["warning", "warn"]),
+                            (Level::Info, &["info", "notice"]),
+                            (Level::Debug, &["debug"]),
+                            (Level::Trace, &["trace"]),
+                        ]),
+                        level: None,
+                    }],
+                },
+                message: names(&["message"]).into(),
+                logger: names(&["log.logger"]).into(),
+                caller: empty().into(),
+                caller_file: names(&["log.origin.file.name"]).into(),
+                caller_line: names(&["log.origin.file.line"]).into(),
+                preset: None,
+            },
+            Self::Gelf => PredefinedFields {
+                time: names(&["timestamp"]).into(),
+                level: LevelField {
+                    show: FieldShowOption::default(),
+                    variants: vec![RawLevelFieldVariant {
+                        names: vec!["level".into()],
+                        values: levels(&[
+                            (Level::Error, &["0", "1", "2", "3"]),
+                            (Level::Warning, &["4"]),
+                            (Level::Info, &["5", "6"]),
+                            (Level::Debug, &["7"]),
+                        ]),
+                        level: None,
+                    }],
+                },
+                message: names(&["short_message"]).into(),
+                logger: empty().into(),
+                caller: empty().into(),
+                caller_file: empty().into(),
+                caller_line: empty().into(),
+                preset: None,
+            },
+        }
+    }
+
+    /// Returns the prefix to strip from keys of custom fields, if the preset uses one.
+    pub fn custom_key_prefix(&self) -> Option<&'static str> {
+        match self {
+            Self::Ecs => None,
+            Self::Gelf => Some("_"),
+        }
+    }
+}
+
+// ---
+
 #[derive(Debug, Serialize, Deserialize, Deref, Clone, PartialEq, Eq, From)]
 pub struct TimeField(pub Field);
 
@@ -400,6 +528,7 @@ pub struct Formatting {
     pub message: MessageFormatting,
     pub punctuation: Punctuation,
     pub prettify_field_keys: Option<bool>,
+    pub sort_object_keys: Option<bool>,
 }
 
 #[cfg(test)]
@@ -410,9 +539,11 @@ impl Sample for Formatting {
             expansion: ExpansionOptions::default(),
             message: MessageFormatting {
                 format: MessageFormat::AutoQuoted,
+                template: None,
             },
             punctuation: Punctuation::sample(),
             prettify_field_keys: None,
+            sort_object_keys: None,
         }
     }
 }
@@ -444,6 +575,9 @@ pub enum ExpansionMode {
 #[serde(rename_all = "kebab-case")]
 pub struct MessageFormatting {
     pub format: MessageFormat,
+    /// Template message, tokens of a message that differ from the corresponding tokens of the template are highlighted.
+    #[serde(default)]
+    pub template: Option<String>,
 }
 
 // ---
@@ -508,7 +642,28 @@ pub struct Punctuation {
     pub input_name_clipping: DisplayVariant,
     pub input_name_common_part: DisplayVariant,
     pub array_separator: DisplayVariant,
+    pub array_opening_bracket: DisplayVariant,
+    pub array_closing_bracket: DisplayVariant,
+    pub object_opening_brace: DisplayVariant,
+    pub object_closing_brace: DisplayVariant,
+    pub object_closing_brace_padding: DisplayVariant,
+    pub object_opening_parenthesis: DisplayVariant,
+    pub object_closing_parenthesis: DisplayVariant,
+    pub object_field_separator: DisplayVariant,
     pub message_delimiter: DisplayVariant,
+    pub field_warning_indicator: DisplayVariant,
+    #[serde(default)]
+    pub string_key_value_separator: Option<DisplayVariant>,
+    #[serde(default)]
+    pub number_key_value_separator: Option<DisplayVariant>,
+    #[serde(default)]
+    pub boolean_key_value_separator: Option<DisplayVariant>,
+    #[serde(default)]
+    pub null_key_value_separator: Option<DisplayVariant>,
+    #[serde(default)]
+    pub object_key_value_separator: Option<DisplayVariant>,
+    #[serde(default)]
+    pub array_key_value_separator: Option<DisplayVariant>,
 }
 
 impl Punctuation {
@@ -533,13 +688,32 @@ impl Punctuation {
             input_name_clipping: Self::resolve_field(&self.input_name_clipping, mode),
             input_name_common_part: Self::resolve_field(&self.input_name_common_part, mode),
             array_separator: Self::resolve_field(&self.array_separator, mode),
+            array_opening_bracket: Self::resolve_field(&self.array_opening_bracket, mode),
+            array_closing_bracket: Self::resolve_field(&self.array_closing_bracket, mode),
+            object_opening_brace: Self::resolve_field(&self.object_opening_brace, mode),
+            object_closing_brace: Self::resolve_field(&self.object_closing_brace, mode),
+            object_closing_brace_padding: Self::resolve_field(&self.object_closing_brace_padding, mode),
+            object_opening_parenthesis: Self::resolve_field(&self.object_opening_parenthesis, mode),
+            object_closing_parenthesis: Self::resolve_field(&self.object_closing_parenthesis, mode),
+            object_field_separator: Self::resolve_field(&self.object_field_separator, mode),
             message_delimiter: Self::resolve_field(&self.message_delimiter, mode),
+            field_warning_indicator: Self::resolve_field(&self.field_warning_indicator, mode),
+            string_key_value_separator: self.resolve_key_value_separator(&self.string_key_value_separator, mode),
+            number_key_value_separator: self.resolve_key_value_separator(&self.number_key_value_separator, mode),
+            boolean_key_value_separator: self.resolve_key_value_separator(&self.boolean_key_value_separator, mode),
+            null_key_value_separator: self.resolve_key_value_separator(&self.null_key_value_separator, mode),
+            object_key_value_separator: self.resolve_key_value_separator(&self.object_key_value_separator, mode),
+            array_key_value_separator: self.resolve_key_value_separator(&self.array_key_value_separator, mode),
         }
     }
 
     fn resolve_field(field: &DisplayVariant, mode: AsciiMode) -> String {
         String::from(field.resolve(mode))
     }
+
+    fn resolve_key_value_separator(&self, field: &Option<DisplayVariant>, mode: AsciiMode) -> String {
+        Self::resolve_field(field.as_ref().unwrap_or(&self.field_key_value_separator), mode)
+    }
 }
 
 impl Default for Punctuation {
@@ -562,7 +736,22 @@ impl Default for Punctuation {
             input_name_clipping: "...".into(),
             input_name_common_part: "...".into(),
             array_separator: " ".into(),
+            array_opening_bracket: "[".into(),
+            array_closing_bracket: "]".into(),
+            object_opening_brace: "{".into(),
+            object_closing_brace: "}".into(),
+            object_closing_brace_padding: " ".into(),
+            object_opening_parenthesis: "(".into(),
+            object_closing_parenthesis: ")".into(),
+            object_field_separator: " ".into(),
             message_delimiter: "::".into(),
+            field_warning_indicator: "!".into(),
+            string_key_value_separator: None,
+            number_key_value_separator: None,
+            boolean_key_value_separator: None,
+            null_key_value_separator: None,
+            object_key_value_separator: None,
+            array_key_value_separator: None,
         }
     }
 }
@@ -588,7 +777,22 @@ impl Sample for Punctuation {
             input_name_clipping: DisplayVariant::ascii("..").unicode("··"),
             input_name_common_part: DisplayVariant::ascii("**").unicode("★★"),
             array_separator: ", ".into(),
+            array_opening_bracket: "[".into(),
+            array_closing_bracket: "]".into(),
+            object_opening_brace: "{".into(),
+            object_closing_brace: "}".into(),
+            object_closing_brace_padding: " ".into(),
+            object_opening_parenthesis: "(".into(),
+            object_closing_parenthesis: ")".into(),
+            object_field_separator: " ".into(),
             message_delimiter: "::".into(),
+            field_warning_indicator: DisplayVariant::ascii("!").unicode("⚠"),
+            string_key_value_separator: None,
+            number_key_value_separator: None,
+            boolean_key_value_separator: None,
+            null_key_value_separator: None,
+            object_key_value_separator: None,
+            array_key_value_separator: None,
         }
     }
 }
@@ -615,7 +819,22 @@ pub struct ResolvedPunctuation {
     pub input_name_clipping: String,
     pub input_name_common_part: String,
     pub array_separator: String,
+    pub array_opening_bracket: String,
+    pub array_closing_bracket: String,
+    pub object_opening_brace: String,
+    pub object_closing_brace: String,
+    pub object_closing_brace_padding: String,
+    pub object_opening_parenthesis: String,
+    pub object_closing_parenthesis: String,
+    pub object_field_separator: String,
     pub message_delimiter: String,
+    pub field_warning_indicator: String,
+    pub string_key_value_separator: String,
+    pub number_key_value_separator: String,
+    pub boolean_key_value_separator: String,
+    pub null_key_value_separator: String,
+    pub object_key_value_separator: String,
+    pub array_key_value_separator: String,
 }
 
 /// Configuration option for ASCII mode.
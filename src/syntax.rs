@@ -3,6 +3,14 @@ pub const EXPANDED_VALUE_HEADER: &str = "|=>";
 pub const EXPANDED_VALUE_INDENT: &str = " \t";
 pub const EXPANDED_MESSAGE_HEADER: &str = "~";
 pub const EXPANDED_OBJECT_HEADER: &str = ":";
+pub const WRAPPED_LINE_INDENT: &str = "  ";
+pub const BLOCK_KEY_HEADER: &str = ":";
+pub const BLOCK_VALUE_HEADER: &str = ": ";
+pub const BLOCK_ITEM_HEADER: &str = "- ";
+pub const HEX_VALUE_OPENING: &str = "<hex:";
+pub const HEX_VALUE_CLOSING: &str = ">";
+pub const DEBUG_STRING_SOURCE_OPENING: &str = " ⟨";
+pub const DEBUG_STRING_SOURCE_CLOSING: &str = "⟩";
 
 pub const TIME_PLACEHOLDER: u8 = b'#';
 
@@ -13,3 +21,12 @@ pub const LEVEL_DEBUG: &str = "DBG";
 pub const LEVEL_TRACE: &str = "TRC";
 pub const LEVEL_UNKNOWN: &str = "###";
 pub const LEVEL_EXPANDED: &str = " ~ ";
+pub const LEVEL_UNKNOWN_SHORT: &str = "#";
+pub const LEVEL_EXPANDED_SHORT: &str = "~";
+
+pub const GUTTER_ERROR: u8 = b'!';
+pub const GUTTER_WARNING: u8 = b'*';
+pub const GUTTER_INFO: u8 = b'+';
+pub const GUTTER_DEBUG: u8 = b'-';
+pub const GUTTER_TRACE: u8 = b'.';
+pub const GUTTER_UNKNOWN: u8 = b' ';
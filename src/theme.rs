@@ -1,8 +1,14 @@
 // std imports
-use std::{borrow::Borrow, collections::HashMap, sync::Arc, vec::Vec};
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    vec::Vec,
+};
 
 // third-party imports
 use enum_map::EnumMap;
+use strum::IntoEnumIterator;
 use unicode_width::UnicodeWidthStr;
 
 // local imports
@@ -32,6 +38,10 @@ pub trait StylingPush<B: Push<u8>> {
     fn batch<R, F: FnOnce(&mut B) -> R>(&mut self, f: F) -> R;
     fn space(&mut self);
     fn reset(&mut self);
+    /// Layers the faint mode over styles of all elements pushed within `f` if `enabled` is true.
+    fn dimmed<R, F: FnOnce(&mut Self) -> R>(&mut self, enabled: bool, f: F) -> R;
+    /// Overrides the foreground color of styles of all elements pushed within `f` with a 256-color palette entry if `color` is set.
+    fn recolored<R, F: FnOnce(&mut Self) -> R>(&mut self, color: Option<u8>, f: F) -> R;
 }
 
 #[derive(Default)]
@@ -108,16 +118,69 @@ impl Theme {
     ) {
         let mut styler = Styler {
             buf,
-            pack: match level {
-                &Some(level) => &self.levels.known[level],
-                None => &self.levels.unknown,
-            },
+            pack: self.pack(*level),
             synced: None,
             current: None,
+            dimmed: false,
+            synced_dimmed: false,
+            foreground: None,
+            synced_foreground: None,
         };
         f(&mut styler);
         styler.reset()
     }
+
+    /// Returns escape sequences the elements are styled with, for records of unknown level (`None`) and each level.
+    /// Elements without a style are omitted.
+    pub fn dump(&self) -> BTreeMap<(Element, Option<Level>), Vec<u8>> {
+        let mut result = BTreeMap::new();
+        for level in std::iter::once(None).chain(Level::iter().map(Some)) {
+            let pack = self.pack(level);
+            for (element, &style) in &pack.elements {
+                if let Some(style) = style {
+                    result.insert((element, level), pack.styles[style].0.data().to_vec());
+                }
+            }
+        }
+        result
+    }
+
+    #[inline]
+    fn pack(&self, level: Option<Level>) -> &StylePack {
+        match level {
+            Some(level) => &self.levels.known[level],
+            None => &self.levels.unknown,
+        }
+    }
+
+    /// Renders a preview of the theme, with a line for records of unknown level followed by a line for each level.
+    /// Each line contains names of all elements styled as the elements themselves,
+    /// with inner elements nested into their outer elements.
+    pub fn render_sample(&self) -> String {
+        fn render<B: Push<u8>, S: StylingPush<B>>(s: &mut S, element: Element) {
+            s.element(element, |s| {
+                s.batch(|buf| buf.extend_from_slice(serde_plain::to_string(&element).unwrap().as_bytes()));
+                for &(_, inner) in Element::nested().iter().filter(|(outer, _)| *outer == element) {
+                    s.space();
+                    render(s, inner);
+                }
+            });
+        }
+
+        let mut buf = Vec::new();
+        for level in std::iter::once(None).chain(Level::iter().map(Some)) {
+            self.apply(&mut buf, &level, |s| {
+                for (i, element) in Element::iter().filter(|e| !e.is_inner()).enumerate() {
+                    if i != 0 {
+                        s.space();
+                    }
+                    render(s, element);
+                }
+            });
+            buf.push(b'\n');
+        }
+        String::from_utf8(buf).unwrap()
+    }
 }
 
 impl From<themecfg::Theme> for Theme {
@@ -235,11 +298,18 @@ impl From<&themecfg::Style> for Style {
 
 // ---
 
+/// Adds the faint mode to the current style without resetting it.
+const FAINT_OVERLAY: &[u8] = b"\x1b[2m";
+
 pub struct Styler<'a, B: Push<u8>> {
     buf: &'a mut B,
     pack: &'a StylePack,
     synced: Option<usize>,
     current: Option<usize>,
+    dimmed: bool,
+    synced_dimmed: bool,
+    foreground: Option<u8>,
+    synced_foreground: Option<u8>,
 }
 
 impl<'a, B: Push<u8>> Styler<'a, B> {
@@ -250,6 +320,8 @@ impl<'a, B: Push<u8>> Styler<'a, B> {
         }
         self.current = None;
         self.synced = None;
+        self.synced_dimmed = false;
+        self.synced_foreground = None;
     }
 
     #[inline(always)]
@@ -264,11 +336,22 @@ impl<'a, B: Push<u8>> Styler<'a, B> {
 
     #[inline(always)]
     fn sync(&mut self) {
-        if self.synced != self.current {
+        if self.synced != self.current || self.synced_dimmed != self.dimmed || self.synced_foreground != self.foreground
+        {
             if let Some(style) = self.current.or(self.pack.reset) {
                 self.pack.styles[style].apply(self.buf);
+                if self.dimmed {
+                    self.buf.extend_from_slice(FAINT_OVERLAY);
+                }
+                if let Some(color) = self.foreground {
+                    self.buf.extend_from_slice(b"\x1b[38;5;");
+                    self.buf.extend_from_slice(itoa::Buffer::new().format(color).as_bytes());
+                    self.buf.push(b'm');
+                }
             }
             self.synced = self.current;
+            self.synced_dimmed = self.dimmed;
+            self.synced_foreground = self.foreground;
         }
     }
 }
@@ -281,12 +364,16 @@ impl<'a> Styler<'a, Vec<u8>> {
     {
         let current = self.current;
         let synced = self.synced;
+        let synced_dimmed = self.synced_dimmed;
+        let synced_foreground = self.synced_foreground;
         let n = self.buf.len();
         let result = f(self);
         if result.is_err() {
             self.buf.truncate(n);
             self.current = current;
             self.synced = synced;
+            self.synced_dimmed = synced_dimmed;
+            self.synced_foreground = synced_foreground;
         }
         result
     }
@@ -316,6 +403,24 @@ impl<'a, B: Push<u8>> StylingPush<B> for Styler<'a, B> {
         self.sync();
         f(self.buf)
     }
+
+    #[inline]
+    fn dimmed<R, F: FnOnce(&mut Self) -> R>(&mut self, enabled: bool, f: F) -> R {
+        let dimmed = self.dimmed;
+        self.dimmed |= enabled;
+        let result = f(self);
+        self.dimmed = dimmed;
+        result
+    }
+
+    #[inline]
+    fn recolored<R, F: FnOnce(&mut Self) -> R>(&mut self, color: Option<u8>, f: F) -> R {
+        let foreground = self.foreground;
+        self.foreground = color.or(foreground);
+        let result = f(self);
+        self.foreground = foreground;
+        result
+    }
 }
 
 // ---
@@ -351,6 +456,20 @@ impl StylePack {
             result.add(element, &Style::from(style));
         }
 
+        // Unless styled explicitly, level blocks are filled with the foreground color of the level
+        if !s.contains_key(&Element::LevelBlock) {
+            if let Some(color) = [Element::LevelInner, Element::Level]
+                .iter()
+                .find_map(|element| s.get(element)?.foreground)
+            {
+                let style = themecfg::Style {
+                    background: Some(color),
+                    ..Default::default()
+                };
+                result.add(Element::LevelBlock, &Style::from(&style));
+            }
+        }
+
         result
     }
 }
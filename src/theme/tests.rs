@@ -806,3 +806,69 @@ fn test_v1_level_inner_does_not_inherit_parent_modes() {
         output
     );
 }
+
+#[test]
+fn test_level_scoped_message_style() {
+    let theme = theme("v1-level-message");
+
+    let render = |level: Option<Level>| {
+        let mut buf = Vec::new();
+        theme.apply(&mut buf, &level, |s| {
+            s.element(Element::Message, |s| s.batch(|buf| buf.extend_from_slice(b"m")));
+        });
+        String::from_utf8(buf).unwrap()
+    };
+
+    let info = render(Some(Level::Info));
+    let error = render(Some(Level::Error));
+
+    assert_eq!(info, "\u{1b}[0;1;38;2;255;255;255mm\u{1b}[0m");
+    assert_eq!(error, "\u{1b}[0;1;38;2;255;136;136mm\u{1b}[0m");
+    assert_eq!(render(None), info);
+    assert_eq!(render(Some(Level::Warning)), info);
+}
+
+#[test]
+fn test_render_sample() {
+    let theme: Arc<Theme> = Sample::sample();
+    let sample = theme.render_sample();
+
+    let lines = sample.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1 + Level::iter().count());
+
+    for line in lines {
+        for element in Element::iter() {
+            assert!(line.contains(&serde_plain::to_string(&element).unwrap()), "{element:?}");
+        }
+        assert!(line.contains("\x1b[0;94mnumber"), "{line:?}");
+        assert!(line.contains("\x1b[0;92mboolean"), "{line:?}");
+        assert!(line.ends_with("\x1b[0m"), "{line:?}");
+    }
+
+    assert_eq!(
+        Theme::none().render_sample().lines().next().unwrap().split(' ').count(),
+        Element::iter().count()
+    );
+}
+
+#[test]
+fn test_dump() {
+    let theme: Arc<Theme> = Sample::sample();
+    let dump = theme.dump();
+
+    for level in std::iter::once(None).chain(Level::iter().map(Some)) {
+        for element in [
+            Element::Time,
+            Element::Level,
+            Element::Message,
+            Element::Key,
+            Element::Number,
+        ] {
+            assert!(dump.contains_key(&(element, level)), "{element:?} {level:?}");
+        }
+    }
+    assert_eq!(dump[&(Element::Number, None)], b"\x1b[0;94m");
+    assert_eq!(dump[&(Element::Message, Some(Level::Info))], b"\x1b[0;1m");
+
+    assert!(Theme::none().dump().is_empty());
+}
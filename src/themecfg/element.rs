@@ -38,6 +38,24 @@ pub enum Element {
     Ellipsis,
     Bullet,
     ValueExpansion,
+    Unparsed,
+    Delta,
+    HttpStatusSuccess,
+    HttpStatusRedirect,
+    HttpStatusClientError,
+    HttpStatusServerError,
+    ElapsedTime,
+    Match,
+    Marker,
+    FieldWarning,
+    LevelBlock,
+    NumberPositive,
+    NumberNegative,
+    NumberZero,
+    TraceId,
+    Binary,
+    GroupSeparator,
+    ErrorMessage,
 }
 
 impl Element {
@@ -129,6 +129,23 @@ fn test_v0_boolean_active_merge() {
     assert_eq!(boolean_false.foreground, Some(Color::RGB(RGB(255, 0, 0))));
 }
 
+#[test]
+fn test_number_sign_variants_inherit_number() {
+    let theme = Theme::load(&dirs(), "test").unwrap();
+
+    let number = &theme.elements[&Element::Number];
+    assert_eq!(number.foreground, Some(Color::Plain(PlainColor::BrightBlue)));
+
+    let positive = &theme.elements[&Element::NumberPositive];
+    assert_eq!(positive.foreground, Some(Color::Plain(PlainColor::Green)));
+
+    let negative = &theme.elements[&Element::NumberNegative];
+    assert_eq!(negative.foreground, Some(Color::Plain(PlainColor::Red)));
+
+    let zero = &theme.elements[&Element::NumberZero];
+    assert_eq!(zero, number);
+}
+
 #[test]
 fn test_v0_modes_replacement() {
     let theme = theme("v0-modes-replace");
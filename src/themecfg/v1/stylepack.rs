@@ -104,9 +104,18 @@ impl StylePack<Element, Style> {
             }
         }
 
-        // Step 3: Handle boolean variants inheriting from base boolean
-        if let Some(base) = self.0.get(&Element::Boolean).cloned() {
-            for variant in [Element::BooleanTrue, Element::BooleanFalse] {
+        // Step 3: Handle boolean and number variants inheriting from their base elements
+        for (base, variants) in [
+            (Element::Boolean, &[Element::BooleanTrue, Element::BooleanFalse][..]),
+            (
+                Element::Number,
+                &[Element::NumberPositive, Element::NumberNegative, Element::NumberZero][..],
+            ),
+        ] {
+            let Some(base) = self.0.get(&base).cloned() else {
+                continue;
+            };
+            for &variant in variants {
                 self.0
                     .entry(variant)
                     .and_modify(|style| {
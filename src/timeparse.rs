@@ -193,6 +193,9 @@ fn use_custom_format(s: &str, format: &DateTimeFormat, now: &DateTime<Tz>, tz: &
                 add_format_item(&mut buf, b"B", flags)?;
                 has_month = true;
             }
+            Item::MonthName(_) => {
+                return unsupported();
+            }
             Item::Day(flags) => {
                 add_format_item(&mut buf, b"d", flags)?;
                 has_day = true;
@@ -2,10 +2,10 @@
 use std::cell::OnceCell;
 
 // third-party imports
-use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDateTime, Offset, TimeZone, Utc};
 
 // local imports
-use crate::app::UnixTimestampUnit;
+use crate::{app::UnixTimestampUnit, timezone::Tz};
 
 // ---
 
@@ -41,6 +41,21 @@ impl<'a> Timestamp<'a> {
         }
     }
 
+    /// Tries the given fallback formats if the value cannot be parsed as RFC 3339 or unix timestamp.
+    pub fn with_fallback(self, fallback: &FallbackFormats) -> Self {
+        if self.parse().is_some() {
+            return self;
+        }
+
+        match fallback.parse(self.raw) {
+            Some(ts) => Self {
+                parsed: OnceCell::from(Some(ts)),
+                ..self
+            },
+            None => self,
+        }
+    }
+
     pub fn parsed(&self) -> &Option<DateTime<FixedOffset>> {
         self.parsed.get_or_init(|| self.reparse())
     }
@@ -104,6 +119,25 @@ impl<'a> Timestamp<'a> {
         rfc3339::Timestamp::parse(self.raw)
     }
 
+    /// Returns whole seconds and nanoseconds since the epoch if the value is an integer Unix timestamp.
+    ///
+    /// Unlike [`Self::parse`], this uses integer arithmetic only and does not try other formats first.
+    pub fn unix_integer(&self) -> Option<(i64, u32)> {
+        if !matches!(guess_number_type(self.raw.as_bytes())?, NumberType::Integer) {
+            return None;
+        }
+
+        let ts = self.raw.parse::<i64>().ok()?;
+        let scale = match self.unix_unit.unwrap_or_else(|| UnixTimestampUnit::guess(ts)) {
+            UnixTimestampUnit::Seconds => return Some((ts, 0)),
+            UnixTimestampUnit::Milliseconds => 1_000,
+            UnixTimestampUnit::Microseconds => 1_000_000,
+            UnixTimestampUnit::Nanoseconds => 1_000_000_000,
+        };
+        let nsecs = ts.rem_euclid(scale) * (1_000_000_000 / scale);
+        Some((ts.div_euclid(scale), nsecs as u32))
+    }
+
     pub fn unix_utc(&self) -> Option<(i64, u32)> {
         self.parsed()
             .and_then(|ts| Some((ts.timestamp(), ts.timestamp_subsec_nanos())))
@@ -112,6 +146,80 @@ impl<'a> Timestamp<'a> {
 
 // ---
 
+/// List of strftime-like patterns used to parse timestamps in non-standard formats.
+///
+/// Values without time zone information are interpreted in the configured time zone.
+/// Patterns without a year assume the current year in that time zone.
+#[derive(Clone, Debug)]
+pub struct FallbackFormats {
+    patterns: Vec<String>,
+    tz: Tz,
+}
+
+impl FallbackFormats {
+    pub fn new<I, S>(patterns: I, tz: Tz) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            patterns: patterns.into_iter().map(Into::into).collect(),
+            tz,
+        }
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn tz(&self) -> &Tz {
+        &self.tz
+    }
+
+    pub fn parse(&self, s: &str) -> Option<DateTime<FixedOffset>> {
+        self.patterns.iter().find_map(|pattern| self.parse_with(s, pattern))
+    }
+
+    fn parse_with(&self, s: &str, pattern: &str) -> Option<DateTime<FixedOffset>> {
+        if let Ok(ts) = DateTime::parse_from_str(s, pattern) {
+            return Some(ts);
+        }
+
+        let ts = if has_year(pattern) {
+            NaiveDateTime::parse_from_str(s, pattern).ok()?
+        } else {
+            let year = Utc::now().with_timezone(&self.tz).year();
+            NaiveDateTime::parse_from_str(&format!("{} {}", year, s), &format!("%Y {}", pattern)).ok()?
+        };
+
+        let ts = self.tz.from_local_datetime(&ts).earliest()?;
+        Some(ts.with_timezone(&ts.offset().fix()))
+    }
+}
+
+impl Default for FallbackFormats {
+    fn default() -> Self {
+        Self::new(DEFAULT_FALLBACK_FORMATS.iter().copied(), Tz::IANA(chrono_tz::UTC))
+    }
+}
+
+/// Common non-RFC 3339 timestamp formats.
+pub const DEFAULT_FALLBACK_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S,%3f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%d/%b/%Y:%H:%M:%S %z",
+    "%b %e %H:%M:%S%.f",
+    "%a %b %e %H:%M:%S %Y",
+];
+
+fn has_year(pattern: &str) -> bool {
+    ["%Y", "%y", "%G", "%g", "%C", "%c", "%D", "%F", "%x", "%s", "%+"]
+        .iter()
+        .any(|x| pattern.contains(x))
+}
+
+// ---
+
 pub mod rfc3339 {
     use super::only_digits;
 
@@ -42,6 +42,26 @@ fn test_parse() {
     );
 }
 
+#[test]
+fn test_unix_integer() {
+    let test = |s, unit| {
+        let ts = Timestamp::new(s).with_unix_unit(unit);
+        let expected = ts.parse().map(|ts| (ts.timestamp(), ts.timestamp_subsec_nanos()));
+        assert_eq!(ts.unix_integer(), expected, "{s}");
+    };
+    test("1597994448", None);
+    test("1597994448123", None);
+    test("1597994448123456", None);
+    test("1597994448123456789", None);
+    test("-1500", Some(UnixTimestampUnit::Milliseconds));
+    test("-1", Some(UnixTimestampUnit::Nanoseconds));
+    test("1597994448123", Some(UnixTimestampUnit::Seconds));
+
+    assert_eq!(Timestamp::new("1597994448.123").unix_integer(), None);
+    assert_eq!(Timestamp::new("2020-08-21T07:20:48Z").unix_integer(), None);
+    assert_eq!(Timestamp::new("").unix_integer(), None);
+}
+
 #[test]
 fn test_split_rfc3339() {
     use rfc3339::Timestamp;
@@ -171,3 +191,58 @@ fn test_fraction_parse() {
     let frac_invalid3 = Fraction::parse("123");
     assert!(frac_invalid3.is_none());
 }
+
+#[test]
+fn test_fallback_space_separated() {
+    let tz = Tz::FixedOffset(FixedOffset::east_opt(3 * 3600).unwrap());
+    let fallback = FallbackFormats::new(DEFAULT_FALLBACK_FORMATS.iter().copied(), tz);
+
+    let ts = Timestamp::new("2020-08-21 07:20:48,123").with_fallback(&fallback);
+    let ts = ts.parse().unwrap();
+    assert_eq!(ts.timestamp(), 1597994448 - 3 * 3600);
+    assert_eq!(ts.timestamp_subsec_nanos(), 123000000);
+    assert_eq!(ts.timezone().local_minus_utc(), 3 * 3600);
+
+    let ts = Timestamp::new("2020/08/21 07:20:48").with_fallback(&fallback);
+    assert_eq!(ts.parse().unwrap().timestamp(), 1597994448 - 3 * 3600);
+
+    let ts = Timestamp::new("21/Aug/2020:07:20:48 +0000").with_fallback(&fallback);
+    let ts = ts.parse().unwrap();
+    assert_eq!(ts.timestamp(), 1597994448);
+    assert_eq!(ts.timezone().local_minus_utc(), 0);
+
+    // standard formats are not affected by the fallback
+    let ts = Timestamp::new("2020-08-21 07:20:48").with_fallback(&fallback);
+    assert_eq!(ts.parse().unwrap().timestamp(), 1597994448);
+
+    let ts = Timestamp::new("not a timestamp").with_fallback(&fallback);
+    assert_eq!(ts.parse(), None);
+}
+
+#[test]
+fn test_fallback_syslog() {
+    let fallback = FallbackFormats::default();
+    let year = Utc::now().year();
+
+    let ts = Timestamp::new("Aug 21 07:20:48")
+        .with_fallback(&fallback)
+        .parse()
+        .unwrap();
+    assert_eq!((ts.year(), ts.month(), ts.day()), (year, 8, 21));
+    assert_eq!(ts.time(), chrono::NaiveTime::from_hms_opt(7, 20, 48).unwrap());
+    assert_eq!(ts.timezone().local_minus_utc(), 0);
+
+    let ts = Timestamp::new("Aug  1 07:20:48")
+        .with_fallback(&fallback)
+        .parse()
+        .unwrap();
+    assert_eq!((ts.year(), ts.month(), ts.day()), (year, 8, 1));
+
+    let ts = Timestamp::new("Fri Aug 21 07:20:48 2020")
+        .with_fallback(&fallback)
+        .parse()
+        .unwrap();
+    assert_eq!(ts.timestamp(), 1597994448);
+
+    assert_eq!(Timestamp::new("Aug 21").with_fallback(&fallback).parse(), None);
+}